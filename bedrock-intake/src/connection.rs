@@ -0,0 +1,363 @@
+use self::super::raknet::{
+	ConnectedMessage, Datagram, Frame, Reliability, Split,
+	DATAGRAM_HEADER_SIZE, FRAME_HEADER_SIZE, UDP_HEADER_SIZE
+};
+use std::{
+	collections::{BTreeMap, HashMap, HashSet, VecDeque},
+	io::{Error, ErrorKind, Result},
+	mem::take,
+	net::SocketAddr,
+	time::{Duration, Instant}
+};
+
+/// How long to go without hearing from a client before giving up on it.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait for a datagram to be acknowledged before resending it.
+const RESEND_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The most datagrams that may be waiting to be acknowledged at once.
+const MAX_IN_FLIGHT: usize = 64;
+
+/// The most parts a message may be split into, and the most split messages
+/// that may be waiting on parts at once.
+const MAX_SPLITS: u32 = 4096;
+const MAX_SPLIT_MESSAGES: usize = 16;
+
+/// The most datagrams a client may skip ahead by at once, so that a malicious
+/// sequence number can't fill our memory with missing ones.
+const MAX_SEQUENCE_GAP: u32 = 4096;
+
+/// The state of the frames received on one ordering channel.
+#[derive(Default)]
+struct Channel {
+	/// The order index of the next frame to handle.
+	order_index: u32,
+	/// Frames that arrived before the ones they're ordered after.
+	waiting: BTreeMap<u32, Vec<u8>>,
+	/// The sequence index of the newest sequenced frame.
+	sequence_index: Option<u32>
+}
+
+/// A RakNet connection to a client, which turns datagrams into messages and
+/// back again.
+pub struct Connection {
+	pub address: SocketAddr,
+	pub client_guid: u64,
+	/// The largest datagram either side may send, including the IP and UDP
+	/// headers.
+	pub mtu: u16,
+	/// When the server started, which connected messages' times count from.
+	epoch: Instant,
+	/// When the client last sent us anything.
+	last_received: Instant,
+	/// Whether the client finished connecting.
+	open: bool,
+	/// Whether either side disconnected.
+	closed: bool,
+
+	/// The sequence number of the next datagram we expect to receive.
+	received_sequence: u32,
+	acks: Vec<u32>,
+	nacks: HashSet<u32>,
+	/// Reliable indices of frames that were received ahead of the lowest one
+	/// that hasn't been.
+	reliable_window: (u32, HashSet<u32>),
+	splits: HashMap<u16, Vec<Option<Vec<u8>>>>,
+	channels: HashMap<u8, Channel>,
+
+	/// The sequence number of the next datagram we send.
+	send_sequence: u32,
+	reliable_index: u32,
+	sequence_index: u32,
+	order_index: u32,
+	split_id: u16,
+	/// Frames waiting to be put in a datagram.
+	queue: VecDeque<Frame>,
+	/// The reliable frames of every datagram that hasn't been acknowledged yet,
+	/// and when they were sent.
+	recovery: BTreeMap<u32, (Instant, Vec<Frame>)>
+}
+
+impl Connection {
+	pub fn new(address: SocketAddr, client_guid: u64, mtu: u16, epoch: Instant)
+			-> Self {
+		Self {
+			address,
+			client_guid,
+			mtu,
+			epoch,
+			last_received: Instant::now(),
+			open: false,
+			closed: false,
+			received_sequence: 0,
+			acks: Vec::new(),
+			nacks: HashSet::new(),
+			reliable_window: (0, HashSet::new()),
+			splits: HashMap::new(),
+			channels: HashMap::new(),
+			send_sequence: 0,
+			reliable_index: 0,
+			sequence_index: 0,
+			order_index: 0,
+			split_id: 0,
+			queue: VecDeque::new(),
+			recovery: BTreeMap::new()
+		}
+	}
+
+	/// Whether the client finished connecting, and game packets may be sent.
+	pub fn open(&self) -> bool {
+		self.open && !self.closed
+	}
+
+	/// Whether the connection is over, either because a side disconnected or
+	/// because the client stopped responding.
+	pub fn closed(&self) -> bool {
+		self.closed || self.last_received.elapsed() > TIMEOUT
+	}
+
+	/// Handles a datagram from the client, returning the game packets it
+	/// completed in the order they're to be handled.
+	pub fn receive(&mut self, datagram: &[u8]) -> Result<Vec<Vec<u8>>> {
+		self.last_received = Instant::now();
+		let mut packets = Vec::new();
+
+		match Datagram::decode(datagram)? {
+			Datagram::Ack(sequences) => sequences.into_iter()
+				.for_each(|sequence| {self.recovery.remove(&sequence);}),
+			Datagram::Nack(sequences) => {
+				let recovery = &mut self.recovery;
+				self.queue.extend(sequences.into_iter()
+					.filter_map(|sequence| recovery.remove(&sequence))
+					.flat_map(|(_, frames)| frames))
+			},
+			Datagram::Frames {sequence, frames} => {
+				self.nacks.remove(&sequence);
+				if sequence >= self.received_sequence {
+					if sequence - self.received_sequence > MAX_SEQUENCE_GAP {
+						return Err(Error::new(ErrorKind::InvalidData,
+							"Skipped too many datagrams."))
+					}
+					self.nacks.extend(self.received_sequence..sequence);
+					self.received_sequence = sequence + 1;
+				}
+				self.acks.push(sequence);
+
+				for frame in frames {
+					self.receive_frame(frame, &mut packets)?
+				}
+			}
+		}
+
+		Ok(packets)
+	}
+
+	fn receive_frame(&mut self, mut frame: Frame, packets: &mut Vec<Vec<u8>>)
+			-> Result<()> {
+		if frame.reliability.reliable() {
+			let (start, received) = &mut self.reliable_window;
+			if frame.reliable_index < *start
+					|| !received.insert(frame.reliable_index) {
+				return Ok(()) // We already have this one.
+			}
+			while received.remove(start) {*start += 1}
+		}
+
+		if let Some(split) = frame.split {
+			match self.reassemble(split, take(&mut frame.body))? {
+				Some(body) => frame.body = body,
+				None => return Ok(())
+			}
+		}
+
+		let channel = self.channels.entry(frame.order_channel).or_default();
+		if frame.reliability.sequenced() {
+			if frame.order_index < channel.order_index || channel.sequence_index
+					.map_or(false, |index| frame.sequence_index <= index) {
+				return Ok(()) // A newer frame already arrived.
+			}
+			channel.sequence_index = Some(frame.sequence_index);
+			self.handle(frame.body, packets)
+		} else if frame.reliability.ordered() {
+			if frame.order_index < channel.order_index {return Ok(())}
+			channel.waiting.insert(frame.order_index, frame.body);
+
+			let mut ready = Vec::new();
+			while let Some(body) = channel.waiting.remove(&channel.order_index) {
+				channel.order_index += 1;
+				channel.sequence_index = None;
+				ready.push(body)
+			}
+			ready.into_iter().try_for_each(|body| self.handle(body, packets))
+		} else {
+			self.handle(frame.body, packets)
+		}
+	}
+
+	/// Collects the parts of a split message, returning the whole message
+	/// once every part has arrived.
+	fn reassemble(&mut self, split: Split, body: Vec<u8>)
+			-> Result<Option<Vec<u8>>> {
+		if split.count > MAX_SPLITS || split.index >= split.count
+				|| (!self.splits.contains_key(&split.id)
+				&& self.splits.len() >= MAX_SPLIT_MESSAGES) {
+			return Err(Error::new(ErrorKind::InvalidData, "Bad split frame."))
+		}
+
+		let parts = self.splits.entry(split.id)
+			.or_insert_with(|| vec![None; split.count as usize]);
+		if parts.len() != split.count as usize {
+			return Err(Error::new(ErrorKind::InvalidData,
+				"Split frame count changed."))
+		}
+		parts[split.index as usize] = Some(body);
+
+		Ok(match parts.iter().all(Option::is_some) {
+			true => self.splits.remove(&split.id)
+				.map(|parts| parts.into_iter().flatten().flatten().collect()),
+			false => None
+		})
+	}
+
+	/// Handles a whole message, answering the ones RakNet takes care of.
+	fn handle(&mut self, body: Vec<u8>, packets: &mut Vec<Vec<u8>>)
+			-> Result<()> {
+		match ConnectedMessage::decode(&body)? {
+			ConnectedMessage::ConnectedPing {time} => {
+				let pong_time = self.time();
+				self.send(&ConnectedMessage::ConnectedPong {ping_time: time, pong_time},
+					Reliability::Unreliable)?
+			},
+			ConnectedMessage::ConnectedPong {..} => (),
+			ConnectedMessage::ConnectionRequest {client_guid, time} => {
+				if client_guid != self.client_guid {
+					return Err(Error::new(ErrorKind::InvalidData,
+						"Connection request GUID changed."))
+				}
+				self.send(&ConnectedMessage::ConnectionRequestAccepted {
+					client_address: self.address,
+					request_time: time,
+					time: self.time()
+				}, Reliability::Reliable)?
+			},
+			ConnectedMessage::ConnectionRequestAccepted {..} =>
+				return Err(Error::new(ErrorKind::InvalidData,
+					"Only servers accept connections.")),
+			ConnectedMessage::NewIncomingConnection => self.open = true,
+			ConnectedMessage::DisconnectionNotification => self.closed = true,
+			ConnectedMessage::Game(packet) => packets.push(packet)
+		}
+		Ok(())
+	}
+
+	/// Queues a message to be sent, splitting it into frames small enough to
+	/// fit in a datagram.
+	pub fn send(&mut self, message: &ConnectedMessage, reliability: Reliability)
+			-> Result<()> {
+		let body = message.encode()?;
+		let mut frame = Frame::new(reliability, Vec::new());
+		if reliability.sequenced() {
+			frame.sequence_index = self.sequence_index;
+			self.sequence_index += 1;
+		}
+		if reliability.ordered() {
+			frame.order_index = self.order_index;
+			if !reliability.sequenced() {
+				self.order_index += 1;
+				self.sequence_index = 0;
+			}
+		}
+
+		let room = self.mtu as usize - UDP_HEADER_SIZE - DATAGRAM_HEADER_SIZE
+			- FRAME_HEADER_SIZE;
+		let parts: Vec<_> = body.chunks(room).map(<[u8]>::to_vec).collect();
+		// Splitting needs every part to be resent until it arrives.
+		if parts.len() > 1 && !reliability.reliable() {
+			frame.reliability = Reliability::Reliable
+		}
+
+		let count = parts.len() as u32;
+		let id = self.split_id;
+		if count > 1 {self.split_id = self.split_id.wrapping_add(1)}
+
+		for (index, body) in parts.into_iter().enumerate() {
+			let mut frame = Frame {body, ..frame.clone()};
+			if count > 1 {
+				frame.split = Some(Split {count, id, index: index as u32})
+			}
+			if frame.reliability.reliable() {
+				frame.reliable_index = self.reliable_index;
+				self.reliable_index += 1;
+			}
+			self.queue.push_back(frame)
+		}
+		Ok(())
+	}
+
+	/// Tells the client we're disconnecting, once the connection is flushed.
+	pub fn disconnect(&mut self) -> Result<()> {
+		self.send(&ConnectedMessage::DisconnectionNotification,
+			Reliability::ReliableOrdered)?;
+		self.closed = true;
+		Ok(())
+	}
+
+	/// The datagrams that are ready to be sent: acknowledgements of what was
+	/// received, datagrams that weren't acknowledged in time, then everything
+	/// that was queued.
+	pub fn flush(&mut self) -> Result<Vec<Vec<u8>>> {
+		let mut datagrams = Vec::new();
+		if !self.acks.is_empty() {
+			datagrams.push(Datagram::Ack(take(&mut self.acks)).encode()?)
+		}
+		if !self.nacks.is_empty() {
+			// Lost frames are resent in new datagrams, so the missing ones are
+			// only asked for once.
+			let nacks = take(&mut self.nacks).into_iter().collect();
+			datagrams.push(Datagram::Nack(nacks).encode()?)
+		}
+
+		let expired: Vec<_> = self.recovery.iter()
+			.filter(|(_, (sent, _))| sent.elapsed() > RESEND_INTERVAL)
+			.map(|(sequence, _)| *sequence)
+			.collect();
+		let mut resent: VecDeque<_> = expired.into_iter()
+			.filter_map(|sequence| self.recovery.remove(&sequence))
+			.flat_map(|(_, frames)| frames)
+			.collect();
+		resent.append(&mut self.queue);
+		self.queue = resent;
+
+		let room = self.mtu as usize - UDP_HEADER_SIZE - DATAGRAM_HEADER_SIZE;
+		// Sending everything at once would overflow the client's buffers, and
+		// have to be resent anyway.
+		while !self.queue.is_empty() && self.recovery.len() < MAX_IN_FLIGHT {
+			let mut frames = Vec::new();
+			let mut size = 0;
+			while let Some(frame) = self.queue.front() {
+				if !frames.is_empty() && size + frame.size() > room {break}
+				size += frame.size();
+				frames.extend(self.queue.pop_front())
+			}
+
+			let sequence = self.send_sequence;
+			self.send_sequence += 1;
+			let reliable: Vec<_> = frames.iter()
+				.filter(|frame| frame.reliability.reliable())
+				.cloned()
+				.collect();
+			if !reliable.is_empty() {
+				self.recovery.insert(sequence, (Instant::now(), reliable));
+			}
+			datagrams.push(Datagram::Frames {sequence, frames}.encode()?)
+		}
+
+		Ok(datagrams)
+	}
+
+	/// How many milliseconds it's been since the server started.
+	fn time(&self) -> i64 {
+		self.epoch.elapsed().as_millis() as i64
+	}
+}
@@ -0,0 +1,13 @@
+#![feature(decl_macro, try_blocks)]
+
+pub mod connection;
+pub mod login;
+pub mod packet;
+pub mod raknet;
+pub mod server;
+pub mod types;
+
+pub use self::{
+	connection::Connection,
+	server::run_server
+};
@@ -0,0 +1,147 @@
+use base64::{URL_SAFE_NO_PAD, decode_config};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::from_slice;
+use std::{
+	error::Error, fmt::{Display, Formatter, Result as FMTResult},
+	time::{SystemTime, UNIX_EPOCH}
+};
+
+/// How far off a client's clock may be from ours, in seconds, before it's
+/// tokens are considered expired.
+const CLOCK_LEEWAY: u64 = 60;
+
+/// The longest a player's name may be.
+const MAX_NAME_LENGTH: usize = 16;
+
+/// Who a client says it is.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Identity {
+	pub display_name: String,
+	pub uuid: u128,
+	/// The client's Xbox user identifier, if it's signed into Xbox Live.
+	pub xuid: Option<String>
+}
+
+#[derive(Deserialize)]
+struct Chain {
+	chain: Vec<String>
+}
+
+#[derive(Deserialize)]
+struct Header {
+	alg: String,
+	/// The public key the token was signed with.
+	x5u: String
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Claims {
+	/// The public key the next token in the chain is signed with.
+	identity_public_key: String,
+	exp: Option<u64>,
+	nbf: Option<u64>,
+	extra_data: Option<ExtraData>
+}
+
+#[derive(Deserialize)]
+struct ExtraData {
+	#[serde(rename = "displayName")]
+	display_name: String,
+	identity: String,
+	#[serde(rename = "XUID", default)]
+	xuid: String
+}
+
+/// Reads the identity out of the chain of tokens a client logs in with.
+///
+/// Every token has to say it's signed with the key the token before it vouches
+/// for, and has to be in date, which can be checked without going online.
+/// Neither the signatures themselves nor who issued the first token are
+/// checked, so just like Java Edition players in offline mode, clients can
+/// claim to be anyone.
+pub fn parse_chain(chain: &str) -> Result<Identity, LoginError> {
+	let chain: Chain = from_slice(chain.as_bytes())
+		.map_err(|_| LoginError::Malformed("The chain isn't JSON."))?;
+	let now = SystemTime::now().duration_since(UNIX_EPOCH)
+		.map(|time| time.as_secs()).unwrap_or(0);
+
+	let mut previous_key: Option<String> = None;
+	let mut identity = None;
+	for token in &chain.chain {
+		let (header, claims) = parse_token(token)?;
+		if header.alg != "ES384" {
+			return Err(LoginError::Malformed("Tokens must be signed with ES384."))
+		}
+		if previous_key.map_or(false, |key| key != header.x5u) {
+			return Err(LoginError::BrokenChain)
+		}
+		let expired = claims.exp.map_or(false, |exp|
+			exp.saturating_add(CLOCK_LEEWAY) < now);
+		let premature = claims.nbf.map_or(false, |nbf|
+			nbf > now.saturating_add(CLOCK_LEEWAY));
+		if expired || premature {
+			return Err(LoginError::Expired)
+		}
+
+		// Only the last token may say who the client is, since anything after
+		// it could be signed by anyone.
+		if identity.is_some() {return Err(LoginError::BrokenChain)}
+		identity = claims.extra_data;
+		previous_key = Some(claims.identity_public_key);
+	}
+
+	let data = identity.ok_or(LoginError::Malformed("Nothing says who it is."))?;
+	let name = &data.display_name;
+	if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH
+			|| name.chars().any(char::is_control) {
+		return Err(LoginError::InvalidName(data.display_name))
+	}
+
+	let uuid = u128::from_str_radix(&data.identity.replace('-', ""), 16)
+		.map_err(|_| LoginError::Malformed("The identity isn't a UUID."))?;
+	Ok(Identity {
+		display_name: data.display_name,
+		uuid,
+		xuid: Some(data.xuid).filter(|xuid| !xuid.is_empty())
+	})
+}
+
+/// Reads the header and claims of a JSON Web Token, without checking it's
+/// signature.
+fn parse_token(token: &str) -> Result<(Header, Claims), LoginError> {
+	let mut parts = token.split('.');
+	Ok((parse_part(parts.next())?, parse_part(parts.next())?))
+}
+
+fn parse_part<T>(part: Option<&str>) -> Result<T, LoginError>
+		where T: DeserializeOwned {
+	let part = part.ok_or(LoginError::Malformed("A token is missing parts."))?;
+	// Padding isn't supposed to be there, but some clients add it anyway.
+	let bytes = decode_config(part.trim_end_matches('='), URL_SAFE_NO_PAD)
+		.map_err(|_| LoginError::Malformed("A token isn't base 64."))?;
+	from_slice(&bytes)
+		.map_err(|_| LoginError::Malformed("A token isn't the right JSON."))
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LoginError {
+	Malformed(&'static str),
+	/// A token wasn't signed with the key the token before it vouched for.
+	BrokenChain,
+	Expired,
+	InvalidName(String)
+}
+
+impl Display for LoginError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Malformed(reason) => write!(f, "Malformed login: {}", reason),
+			Self::BrokenChain => write!(f, "The login chain is broken."),
+			Self::Expired => write!(f, "The login has expired."),
+			Self::InvalidName(name) => write!(f, "Invalid name {:?}.", name)
+		}
+	}
+}
+
+impl Error for LoginError {}
@@ -0,0 +1,390 @@
+use self::super::types::{Read, Write};
+use flate2::{Compression, read::DeflateDecoder, write::DeflateEncoder};
+use std::io::{Error, ErrorKind, Read as IORead, Result};
+
+/// The version of the game's protocol that's spoken, and the version of the
+/// game it's from.
+pub const PROTOCOL: i32 = 419;
+pub const GAME_VERSION: &str = "1.16.100";
+
+/// The most a batch may inflate to, so that a small batch can't take up all of
+/// our memory.
+const MAX_BATCH_SIZE: u64 = 8 << 20;
+
+/// Reads every packet in a batch, which is what game messages are made of.
+/// Batches are deflated, and every packet in them is prefixed with it's length.
+pub fn decode_batch(batch: &[u8]) -> Result<Vec<Packet>> {
+	let mut bytes = Vec::new();
+	DeflateDecoder::new(batch).take(MAX_BATCH_SIZE).read_to_end(&mut bytes)?;
+
+	let mut bytes = &bytes[..];
+	let mut packets = Vec::new();
+	while !bytes.is_empty() {
+		packets.push(Packet::decode(&bytes.variable_bytes()?)?)
+	}
+	Ok(packets)
+}
+
+pub fn encode_batch(packets: &[Packet]) -> Result<Vec<u8>> {
+	let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+	for packet in packets {
+		encoder.variable_bytes(&packet.encode()?)?
+	}
+	encoder.finish()
+}
+
+/// How far along logging in a client is, or why it failed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PlayStatus {
+	LoginSuccess,
+	/// The client is older than the server.
+	FailedClient,
+	/// The server is older than the client.
+	FailedServer,
+	/// The client has everything it needs, and can be shown the world.
+	PlayerSpawn,
+	ServerFull
+}
+
+impl PlayStatus {
+	pub fn id(&self) -> i32 {
+		match self {
+			Self::LoginSuccess => 0,
+			Self::FailedClient => 1,
+			Self::FailedServer => 2,
+			Self::PlayerSpawn => 3,
+			Self::ServerFull => 7
+		}
+	}
+}
+
+/// What a client has to say about the resource packs it was offered.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResourcePackResponse {
+	Refused,
+	SendPacks,
+	HaveAllPacks,
+	Completed
+}
+
+/// Where a message is shown, and how.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TextKind {
+	Raw,
+	/// Said by a player, whose name is shown alongside it.
+	Chat,
+	/// Shown above the hotbar.
+	Tip,
+	System
+}
+
+impl TextKind {
+	pub fn id(&self) -> u8 {
+		match self {
+			Self::Raw => 0,
+			Self::Chat => 1,
+			Self::Tip => 5,
+			Self::System => 6
+		}
+	}
+}
+
+/// Everything a client needs to know about the world it's joining, and itself.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StartGame {
+	pub entity_id: i64,
+	pub gamemode: i32,
+	pub position: (f32, f32, f32),
+	/// The yaw and pitch the player is facing.
+	pub rotation: (f32, f32),
+	pub difficulty: i32,
+	pub spawn: (i32, i32, i32),
+	pub world_name: String,
+	/// Whether the world is superflat, rather than shaped by terrain.
+	pub flat: bool
+}
+
+/// Where a player is, which clients send as they move and servers send to
+/// move them. The position is of the player's eyes, rather than their feet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MovePlayer {
+	pub entity_id: u64,
+	pub position: (f32, f32, f32),
+	/// The yaw and pitch the player is facing.
+	pub rotation: (f32, f32),
+	/// Whether the player is being forced to move, rather than moving on it's
+	/// own.
+	pub teleport: bool,
+	pub grounded: bool,
+	pub tick: u64
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Packet {
+	/// The first thing a client sends, with the chain of tokens that
+	/// identifies it and a token with it's skin and such.
+	Login {protocol: i32, chain: String, client_data: String},
+	PlayStatus(PlayStatus),
+	/// Disconnects a client, showing it the message if there is one.
+	Disconnect(Option<String>),
+	/// Offers a client resource packs, which we don't have any of.
+	ResourcePacksInfo,
+	/// Tells a client which resource packs to apply, which are none.
+	ResourcePackStack,
+	ResourcePackClientResponse(ResourcePackResponse),
+	Text {kind: TextKind, source: String, message: String},
+	StartGame(StartGame),
+	MovePlayer(MovePlayer),
+	/// Something a player started or stopped doing, by it's identifier.
+	PlayerAction(i32),
+	/// What a player may do, which clients send when they start or stop
+	/// flying.
+	AdventureSettings {flags: u32},
+	/// A chunk column, and how many of it's sections are in `payload`.
+	LevelChunk {x: i32, z: i32, sections: u32, payload: Vec<u8>},
+	/// How far a client would like to see, in chunks.
+	RequestChunkRadius(i32),
+	/// How far a client may see, in chunks.
+	ChunkRadiusUpdated(i32),
+	/// The player finished spawning, and is in the world.
+	SetLocalPlayerAsInitialized,
+	/// Where chunks are loaded around, and how far in blocks. Clients drop
+	/// chunks outside of it.
+	NetworkChunkPublisherUpdate {position: (i32, i32, i32), radius: u32},
+	/// A packet that's ignored, by it's identifier.
+	Unknown(u32)
+}
+
+impl Packet {
+	pub fn decode(mut bytes: &[u8]) -> Result<Self> {
+		// The rest of the header is for split screen, which isn't supported.
+		let id = bytes.variable_unsigned_integer()? & 0x3FF;
+		Ok(match id {
+			0x01 => {
+				let protocol = bytes.unsigned_int()? as i32;
+				let mut request = &bytes.variable_bytes()?[..];
+				let chain = little_string(&mut request)?;
+				let client_data = little_string(&mut request)?;
+				Self::Login {protocol, chain, client_data}
+			},
+			0x08 => Self::ResourcePackClientResponse(match bytes.unsigned_byte()? {
+				1 => ResourcePackResponse::Refused,
+				2 => ResourcePackResponse::SendPacks,
+				3 => ResourcePackResponse::HaveAllPacks,
+				4 => ResourcePackResponse::Completed,
+				status => return Err(Error::new(ErrorKind::InvalidData,
+					format!("Unknown resource pack response {}.", status)))
+			}),
+			0x09 => {
+				let kind = bytes.unsigned_byte()?;
+				bytes.bool()?; // Whether the message needs translating.
+				match kind {
+					1 => Self::Text {
+						kind: TextKind::Chat,
+						source: bytes.variable_string()?,
+						message: bytes.variable_string()?
+					},
+					kind => return Err(Error::new(ErrorKind::InvalidData,
+						format!("Clients don't send text of kind {}.", kind)))
+				}
+			},
+			0x13 => {
+				let entity_id = bytes.variable_unsigned_long()?;
+				let position = vector(&mut bytes)?;
+				let (pitch, yaw) = (bytes.little_float()?, bytes.little_float()?);
+				bytes.little_float()?; // The head's yaw.
+				let teleport = bytes.unsigned_byte()? == 2;
+				let grounded = bytes.bool()?;
+				bytes.variable_unsigned_long()?; // What's being ridden.
+				if teleport {
+					bytes.little_int()?; // Why.
+					bytes.little_int()?; // What item caused it.
+				}
+				Self::MovePlayer(MovePlayer {
+					entity_id,
+					position,
+					rotation: (yaw, pitch),
+					teleport,
+					grounded,
+					tick: bytes.variable_unsigned_long()?
+				})
+			},
+			0x24 => {
+				bytes.variable_unsigned_long()?; // The player's entity.
+				Self::PlayerAction(bytes.variable_integer()?)
+			},
+			0x37 => Self::AdventureSettings {
+				flags: bytes.variable_unsigned_integer()?
+			},
+			0x45 => Self::RequestChunkRadius(bytes.variable_integer()?),
+			0x71 => Self::SetLocalPlayerAsInitialized,
+			id => Self::Unknown(id)
+		})
+	}
+
+	pub fn encode(&self) -> Result<Vec<u8>> {
+		let mut bytes = Vec::new();
+		match self {
+			Self::PlayStatus(status) => {
+				bytes.variable_unsigned_integer(0x02)?;
+				bytes.unsigned_int(status.id() as u32)?;
+			},
+			Self::Disconnect(message) => {
+				bytes.variable_unsigned_integer(0x05)?;
+				bytes.bool(message.is_none())?;
+				if let Some(message) = message {bytes.variable_string(message)?}
+			},
+			Self::ResourcePacksInfo => {
+				bytes.variable_unsigned_integer(0x06)?;
+				bytes.bool(false)?; // Whether packs must be accepted.
+				bytes.bool(false)?; // Whether there are scripts.
+				bytes.little_unsigned_short(0)?; // Behavior packs.
+				bytes.little_unsigned_short(0)?; // Resource packs.
+			},
+			Self::ResourcePackStack => {
+				bytes.variable_unsigned_integer(0x07)?;
+				bytes.bool(false)?; // Whether packs must be accepted.
+				bytes.variable_unsigned_integer(0)?; // Behavior packs.
+				bytes.variable_unsigned_integer(0)?; // Resource packs.
+				bytes.variable_string(GAME_VERSION)?;
+				bytes.little_int(0)?; // Experiments.
+				bytes.bool(false)?; // Whether experiments were ever on.
+			},
+			Self::Text {kind, source, message} => {
+				bytes.variable_unsigned_integer(0x09)?;
+				bytes.unsigned_byte(kind.id())?;
+				bytes.bool(false)?; // Whether the message needs translating.
+				if *kind == TextKind::Chat {bytes.variable_string(source)?}
+				bytes.variable_string(message)?;
+				bytes.variable_string("")?; // The sender's Xbox user identifier.
+				bytes.variable_string("")?; // The sender's platform chat identifier.
+			},
+			Self::StartGame(start) => {
+				bytes.variable_unsigned_integer(0x0B)?;
+				start_game(&mut bytes, start)?
+			},
+			Self::MovePlayer(movement) => {
+				bytes.variable_unsigned_integer(0x13)?;
+				bytes.variable_unsigned_long(movement.entity_id)?;
+				let (x, y, z) = movement.position;
+				[x, y, z, movement.rotation.1, movement.rotation.0, movement.rotation.0]
+					.iter().try_for_each(|value| bytes.little_float(*value))?;
+				bytes.unsigned_byte(if movement.teleport {2} else {0})?;
+				bytes.bool(movement.grounded)?;
+				bytes.variable_unsigned_long(0)?; // What's being ridden.
+				if movement.teleport {
+					bytes.little_int(0)?; // Why, which isn't any reason.
+					bytes.little_int(0)?; // What item caused it.
+				}
+				bytes.variable_unsigned_long(movement.tick)?;
+			},
+			Self::LevelChunk {x, z, sections, payload} => {
+				bytes.variable_unsigned_integer(0x3A)?;
+				bytes.variable_integer(*x)?;
+				bytes.variable_integer(*z)?;
+				bytes.variable_unsigned_integer(*sections)?;
+				bytes.bool(false)?; // Whether the client may cache it.
+				bytes.variable_bytes(payload)?;
+			},
+			Self::ChunkRadiusUpdated(radius) => {
+				bytes.variable_unsigned_integer(0x46)?;
+				bytes.variable_integer(*radius)?;
+			},
+			Self::NetworkChunkPublisherUpdate {position: (x, y, z), radius} => {
+				bytes.variable_unsigned_integer(0x79)?;
+				bytes.variable_integer(*x)?;
+				bytes.variable_unsigned_integer(*y as u32)?;
+				bytes.variable_integer(*z)?;
+				bytes.variable_unsigned_integer(*radius)?;
+			},
+			_ => return Err(Error::new(ErrorKind::InvalidInput,
+				format!("Servers don't send {:?}.", self)))
+		}
+		Ok(bytes)
+	}
+}
+
+/// Writes the body of a Start Game packet. Most of it's settings are for
+/// features that aren't supported, and are left off.
+fn start_game(bytes: &mut Vec<u8>, start: &StartGame) -> Result<()> {
+	bytes.variable_long(start.entity_id)?;
+	bytes.variable_unsigned_long(start.entity_id as u64)?; // The runtime one.
+	bytes.variable_integer(start.gamemode)?;
+	let (x, y, z) = start.position;
+	[x, y, z, start.rotation.1, start.rotation.0].iter()
+		.try_for_each(|value| bytes.little_float(*value))?;
+
+	bytes.variable_integer(0)?; // The seed.
+	bytes.little_short(0)?; // The default spawn biome.
+	bytes.variable_string("plains")?; // The user defined spawn biome.
+	bytes.variable_integer(0)?; // The overworld.
+	// Whether the world is infinite or flat.
+	bytes.variable_integer(if start.flat {2} else {1})?;
+	bytes.variable_integer(start.gamemode)?; // The world's game mode.
+	bytes.variable_integer(start.difficulty)?;
+	let (x, y, z) = start.spawn;
+	bytes.variable_integer(x)?;
+	bytes.variable_unsigned_integer(y as u32)?;
+	bytes.variable_integer(z)?;
+	bytes.bool(true)?; // Whether achievements are disabled.
+	bytes.variable_integer(0)?; // The time the day cycle stops at.
+	bytes.variable_integer(0)?; // The education edition offer.
+	bytes.bool(false)?; // Whether education features are on.
+	bytes.variable_string("")?; // The education product identifier.
+	bytes.little_float(0.)?; // Rain.
+	bytes.little_float(0.)?; // Lightning.
+	bytes.bool(false)?; // Whether platform locked content was confirmed.
+	bytes.bool(true)?; // Whether it's a multiplayer game.
+	bytes.bool(true)?; // Whether it's broadcast over LAN.
+	bytes.variable_integer(0)?; // How it's broadcast over Xbox Live.
+	bytes.variable_integer(0)?; // How it's broadcast over the platform.
+	bytes.bool(true)?; // Whether commands are enabled.
+	bytes.bool(false)?; // Whether texture packs are required.
+	bytes.variable_unsigned_integer(0)?; // Game rules.
+	bytes.little_int(0)?; // Experiments.
+	bytes.bool(false)?; // Whether experiments were ever on.
+	bytes.bool(false)?; // Bonus chest.
+	bytes.bool(false)?; // Start with a map.
+	bytes.variable_integer(1)?; // Players are members by default.
+	bytes.little_int(4)?; // The radius chunks are ticked in.
+	// Whether behavior packs, resource packs and the world are locked, whether
+	// only Microsoft accounts are shown, whether the world is from a template,
+	// whether template options are locked, and whether only old villagers
+	// spawn.
+	(0..7).try_for_each(|_| bytes.bool(false))?;
+	bytes.variable_string(GAME_VERSION)?;
+	bytes.little_int(0)?; // The limited world's width.
+	bytes.little_int(0)?; // The limited world's length.
+	bytes.bool(false)?; // Whether the new nether is on.
+	bytes.bool(false)?; // Whether experimental gameplay is overridden.
+
+	bytes.variable_string("")?; // The level's identifier.
+	bytes.variable_string(&start.world_name)?;
+	bytes.variable_string("")?; // The premium world template.
+	bytes.bool(false)?; // Whether it's a trial.
+	bytes.variable_integer(0)?; // Movement is up to the client.
+	bytes.little_long(0)?; // The current tick.
+	bytes.variable_integer(0)?; // The enchantment seed.
+	bytes.variable_unsigned_integer(0)?; // Custom blocks.
+	bytes.variable_unsigned_integer(0)?; // Items.
+	bytes.variable_string("")?; // The multiplayer correlation identifier.
+	bytes.bool(false) // Whether the new inventory system is on.
+}
+
+/// Reads three little endian floats.
+fn vector(bytes: &mut &[u8]) -> Result<(f32, f32, f32)> {
+	Ok((bytes.little_float()?, bytes.little_float()?, bytes.little_float()?))
+}
+
+/// Reads a string prefixed with it's length as a little endian integer, which
+/// is only used in the Login packet.
+fn little_string(bytes: &mut &[u8]) -> Result<String> {
+	let length = bytes.little_unsigned_int()? as usize;
+	if length > bytes.len() {
+		return Err(Error::new(ErrorKind::UnexpectedEof, "String was cut short."))
+	}
+	let (string, rest) = bytes.split_at(length);
+	*bytes = rest;
+	String::from_utf8(string.to_vec()).map_err(|_|
+		Error::new(ErrorKind::InvalidData, "String data was not UTF-8."))
+}
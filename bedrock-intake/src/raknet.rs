@@ -0,0 +1,427 @@
+use self::super::types::{Read, Write};
+use std::{
+	io::{Error, ErrorKind, Result},
+	net::{Ipv4Addr, SocketAddr}
+};
+
+/// The version of RakNet Bedrock speaks.
+pub const RAKNET_PROTOCOL: u8 = 10;
+
+/// How many bytes of every datagram are taken up by the IP and UDP headers.
+pub const UDP_HEADER_SIZE: usize = 28;
+
+/// The size of a datagram's flags and sequence number.
+pub const DATAGRAM_HEADER_SIZE: usize = 4;
+
+/// The largest a frame's header can be, when it's reliable, ordered and split.
+pub const FRAME_HEADER_SIZE: usize = 23;
+
+/// The most sequence numbers a single acknowledgement may cover, so that
+/// malicious ranges can't take up all of our memory.
+const MAX_RECORDS: usize = 4096;
+
+/// How many internal addresses Bedrock sends when connecting, rather than the
+/// 10 RakNet normally does.
+const INTERNAL_ADDRESSES: usize = 20;
+
+/// Messages sent by clients without a connection.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OfflineRequest {
+	/// Asks about the server, such as for a server list. `open` pings only
+	/// want an answer if the server has room for more connections.
+	UnconnectedPing {time: i64, client_guid: u64, open: bool},
+	/// The first step of connecting. The request is padded to the size of the
+	/// largest datagram the client can send, so the MTU includes that padding.
+	OpenConnectionRequest1 {protocol: u8, mtu: u16},
+	OpenConnectionRequest2 {server_address: SocketAddr, mtu: u16, client_guid: u64}
+}
+
+impl OfflineRequest {
+	pub fn decode(mut bytes: &[u8]) -> Result<Self> {
+		let length = bytes.len();
+		Ok(match bytes.unsigned_byte()? {
+			id @ (0x01 | 0x02) => {
+				let time = bytes.long()?;
+				bytes.magic()?;
+				Self::UnconnectedPing {time, client_guid: bytes.unsigned_long()?,
+					open: id == 0x02}
+			},
+			0x05 => {
+				bytes.magic()?;
+				Self::OpenConnectionRequest1 {
+					protocol: bytes.unsigned_byte()?,
+					mtu: (length + UDP_HEADER_SIZE) as u16
+				}
+			},
+			0x07 => {
+				bytes.magic()?;
+				Self::OpenConnectionRequest2 {
+					server_address: bytes.address()?,
+					mtu: bytes.unsigned_short()?,
+					client_guid: bytes.unsigned_long()?
+				}
+			},
+			id => return Err(Error::new(ErrorKind::InvalidData,
+				format!("Unknown offline message {:#04X}.", id)))
+		})
+	}
+}
+
+/// Messages the server sends without a connection.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OfflineResponse {
+	/// Answers a ping, with whatever the server advertises itself as.
+	UnconnectedPong {time: i64, server_guid: u64, data: String},
+	OpenConnectionReply1 {server_guid: u64, mtu: u16},
+	OpenConnectionReply2 {server_guid: u64, client_address: SocketAddr, mtu: u16},
+	IncompatibleProtocol {protocol: u8, server_guid: u64}
+}
+
+impl OfflineResponse {
+	pub fn encode(&self) -> Result<Vec<u8>> {
+		let mut bytes = Vec::new();
+		match self {
+			Self::UnconnectedPong {time, server_guid, data} => {
+				bytes.unsigned_byte(0x1C)?;
+				bytes.long(*time)?;
+				bytes.unsigned_long(*server_guid)?;
+				bytes.magic()?;
+				bytes.string(data)?;
+			},
+			Self::OpenConnectionReply1 {server_guid, mtu} => {
+				bytes.unsigned_byte(0x06)?;
+				bytes.magic()?;
+				bytes.unsigned_long(*server_guid)?;
+				bytes.bool(false)?; // No security.
+				bytes.unsigned_short(*mtu)?;
+			},
+			Self::OpenConnectionReply2 {server_guid, client_address, mtu} => {
+				bytes.unsigned_byte(0x08)?;
+				bytes.magic()?;
+				bytes.unsigned_long(*server_guid)?;
+				bytes.address(*client_address)?;
+				bytes.unsigned_short(*mtu)?;
+				bytes.bool(false)?; // No encryption.
+			},
+			Self::IncompatibleProtocol {protocol, server_guid} => {
+				bytes.unsigned_byte(0x19)?;
+				bytes.unsigned_byte(*protocol)?;
+				bytes.magic()?;
+				bytes.unsigned_long(*server_guid)?;
+			}
+		}
+		Ok(bytes)
+	}
+}
+
+/// Messages sent inside of a connection, in frames. Times are in milliseconds,
+/// since whenever the side that sent them started counting.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConnectedMessage {
+	ConnectedPing {time: i64},
+	ConnectedPong {ping_time: i64, pong_time: i64},
+	ConnectionRequest {client_guid: u64, time: i64},
+	ConnectionRequestAccepted {client_address: SocketAddr, request_time: i64,
+		time: i64},
+	/// The client got the server's acceptance, and the connection is open.
+	NewIncomingConnection,
+	DisconnectionNotification,
+	/// A packet of the game itself, without it's identifier.
+	Game(Vec<u8>)
+}
+
+impl ConnectedMessage {
+	pub fn decode(mut bytes: &[u8]) -> Result<Self> {
+		Ok(match bytes.unsigned_byte()? {
+			0x00 => Self::ConnectedPing {time: bytes.long()?},
+			0x03 => Self::ConnectedPong {
+				ping_time: bytes.long()?,
+				pong_time: bytes.long()?
+			},
+			0x09 => Self::ConnectionRequest {
+				client_guid: bytes.unsigned_long()?,
+				time: bytes.long()?
+			},
+			// The addresses and times it holds aren't of any use.
+			0x13 => Self::NewIncomingConnection,
+			0x15 => Self::DisconnectionNotification,
+			0xFE => Self::Game(bytes.to_vec()),
+			id => return Err(Error::new(ErrorKind::InvalidData,
+				format!("Unknown connected message {:#04X}.", id)))
+		})
+	}
+
+	pub fn encode(&self) -> Result<Vec<u8>> {
+		let mut bytes = Vec::new();
+		match self {
+			Self::ConnectedPing {time} => {
+				bytes.unsigned_byte(0x00)?;
+				bytes.long(*time)?;
+			},
+			Self::ConnectedPong {ping_time, pong_time} => {
+				bytes.unsigned_byte(0x03)?;
+				bytes.long(*ping_time)?;
+				bytes.long(*pong_time)?;
+			},
+			Self::ConnectionRequest {client_guid, time} => {
+				bytes.unsigned_byte(0x09)?;
+				bytes.unsigned_long(*client_guid)?;
+				bytes.long(*time)?;
+				bytes.bool(false)?; // No security.
+			},
+			Self::ConnectionRequestAccepted {client_address, request_time, time} => {
+				bytes.unsigned_byte(0x10)?;
+				bytes.address(*client_address)?;
+				bytes.unsigned_short(0)?; // The system index.
+				let unspecified = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0);
+				(0..INTERNAL_ADDRESSES).try_for_each(|_| bytes.address(unspecified))?;
+				bytes.long(*request_time)?;
+				bytes.long(*time)?;
+			},
+			Self::NewIncomingConnection => return Err(Error::new(
+				ErrorKind::InvalidInput, "Only clients send new incoming connections.")),
+			Self::DisconnectionNotification => bytes.unsigned_byte(0x15)?,
+			Self::Game(packet) => {
+				bytes.unsigned_byte(0xFE)?;
+				bytes.bytes(packet)?;
+			}
+		}
+		Ok(bytes)
+	}
+}
+
+/// How a frame is delivered. Reliable frames are resent until they arrive,
+/// ordered frames are handled in the order they were sent, and sequenced
+/// frames are dropped if a newer one already arrived.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Reliability {
+	Unreliable,
+	UnreliableSequenced,
+	Reliable,
+	ReliableOrdered,
+	ReliableSequenced,
+	UnreliableAckReceipt,
+	ReliableAckReceipt,
+	ReliableOrderedAckReceipt
+}
+
+impl Reliability {
+	pub fn id(&self) -> u8 {
+		*self as u8
+	}
+
+	pub fn from_id(id: u8) -> Result<Self> {
+		Ok(match id {
+			0 => Self::Unreliable,
+			1 => Self::UnreliableSequenced,
+			2 => Self::Reliable,
+			3 => Self::ReliableOrdered,
+			4 => Self::ReliableSequenced,
+			5 => Self::UnreliableAckReceipt,
+			6 => Self::ReliableAckReceipt,
+			7 => Self::ReliableOrderedAckReceipt,
+			id => return Err(Error::new(ErrorKind::InvalidData,
+				format!("Unknown reliability {}.", id)))
+		})
+	}
+
+	pub fn reliable(&self) -> bool {
+		matches!(self, Self::Reliable | Self::ReliableOrdered
+			| Self::ReliableSequenced | Self::ReliableAckReceipt
+			| Self::ReliableOrderedAckReceipt)
+	}
+
+	pub fn sequenced(&self) -> bool {
+		matches!(self, Self::UnreliableSequenced | Self::ReliableSequenced)
+	}
+
+	/// Whether frames have an order index and channel, which sequenced frames
+	/// have too.
+	pub fn ordered(&self) -> bool {
+		matches!(self, Self::UnreliableSequenced | Self::ReliableOrdered
+			| Self::ReliableSequenced | Self::ReliableOrderedAckReceipt)
+	}
+}
+
+/// Where a split frame's body belongs, among the other parts of the message.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Split {
+	pub count: u32,
+	pub id: u16,
+	pub index: u32
+}
+
+/// A message, or part of one, and how it's delivered.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+	pub reliability: Reliability,
+	pub reliable_index: u32,
+	pub sequence_index: u32,
+	pub order_index: u32,
+	pub order_channel: u8,
+	pub split: Option<Split>,
+	pub body: Vec<u8>
+}
+
+impl Frame {
+	pub fn new(reliability: Reliability, body: Vec<u8>) -> Self {
+		Self {
+			reliability,
+			reliable_index: 0,
+			sequence_index: 0,
+			order_index: 0,
+			order_channel: 0,
+			split: None,
+			body
+		}
+	}
+
+	/// How many bytes this frame takes up in a datagram.
+	pub fn size(&self) -> usize {
+		let mut size = 3 + self.body.len();
+		if self.reliability.reliable() {size += 3}
+		if self.reliability.sequenced() {size += 3}
+		if self.reliability.ordered() {size += 4}
+		if self.split.is_some() {size += 10}
+		size
+	}
+
+	pub fn decode(bytes: &mut &[u8]) -> Result<Self> {
+		let flags = bytes.unsigned_byte()?;
+		let reliability = Reliability::from_id(flags >> 5)?;
+		// The length is in bits, for whatever reason.
+		let length = (bytes.unsigned_short()? as usize + 7) / 8;
+		let mut frame = Self::new(reliability, Vec::new());
+
+		if reliability.reliable() {frame.reliable_index = bytes.triad()?}
+		if reliability.sequenced() {frame.sequence_index = bytes.triad()?}
+		if reliability.ordered() {
+			frame.order_index = bytes.triad()?;
+			frame.order_channel = bytes.unsigned_byte()?;
+		}
+		if flags & 0x10 != 0 {
+			frame.split = Some(Split {
+				count: bytes.unsigned_int()?,
+				id: bytes.unsigned_short()?,
+				index: bytes.unsigned_int()?
+			})
+		}
+
+		if length > bytes.len() {
+			return Err(Error::new(ErrorKind::UnexpectedEof, "Frame was cut short."))
+		}
+		frame.body = bytes[..length].to_vec();
+		*bytes = &bytes[length..];
+		Ok(frame)
+	}
+
+	pub fn encode(&self, bytes: &mut Vec<u8>) -> Result<()> {
+		let split = if self.split.is_some() {0x10} else {0};
+		bytes.unsigned_byte(self.reliability.id() << 5 | split)?;
+		bytes.unsigned_short((self.body.len() * 8) as u16)?;
+
+		if self.reliability.reliable() {bytes.triad(self.reliable_index)?}
+		if self.reliability.sequenced() {bytes.triad(self.sequence_index)?}
+		if self.reliability.ordered() {
+			bytes.triad(self.order_index)?;
+			bytes.unsigned_byte(self.order_channel)?;
+		}
+		if let Some(split) = self.split {
+			bytes.unsigned_int(split.count)?;
+			bytes.unsigned_short(split.id)?;
+			bytes.unsigned_int(split.index)?;
+		}
+
+		bytes.bytes(&self.body)
+	}
+}
+
+/// Everything sent over a connection, with the sequence numbers of datagrams
+/// that were or weren't received.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Datagram {
+	Frames {sequence: u32, frames: Vec<Frame>},
+	Ack(Vec<u32>),
+	Nack(Vec<u32>)
+}
+
+impl Datagram {
+	pub fn decode(mut bytes: &[u8]) -> Result<Self> {
+		let flags = bytes.unsigned_byte()?;
+		if flags & 0x80 == 0 {
+			return Err(Error::new(ErrorKind::InvalidData, "Not a datagram."))
+		}
+
+		Ok(if flags & 0x40 != 0 {
+			Self::Ack(decode_records(&mut bytes)?)
+		} else if flags & 0x20 != 0 {
+			Self::Nack(decode_records(&mut bytes)?)
+		} else {
+			let sequence = bytes.triad()?;
+			let mut frames = Vec::new();
+			while !bytes.is_empty() {frames.push(Frame::decode(&mut bytes)?)}
+			Self::Frames {sequence, frames}
+		})
+	}
+
+	pub fn encode(&self) -> Result<Vec<u8>> {
+		let mut bytes = Vec::new();
+		match self {
+			Self::Frames {sequence, frames} => {
+				bytes.unsigned_byte(0x84)?;
+				bytes.triad(*sequence)?;
+				frames.iter().try_for_each(|frame| frame.encode(&mut bytes))?
+			},
+			Self::Ack(sequences) => {
+				bytes.unsigned_byte(0xC0)?;
+				encode_records(&mut bytes, sequences)?
+			},
+			Self::Nack(sequences) => {
+				bytes.unsigned_byte(0xA0)?;
+				encode_records(&mut bytes, sequences)?
+			}
+		}
+		Ok(bytes)
+	}
+}
+
+/// Reads the ranges of sequence numbers an acknowledgement is made of.
+fn decode_records(bytes: &mut &[u8]) -> Result<Vec<u32>> {
+	let mut sequences = Vec::new();
+	for _ in 0..bytes.unsigned_short()? {
+		let single = bytes.bool()?;
+		let start = bytes.triad()?;
+		let end = if single {start} else {bytes.triad()?};
+		if end < start || sequences.len() + (end - start) as usize >= MAX_RECORDS {
+			return Err(Error::new(ErrorKind::InvalidData,
+				"Bad acknowledgement range."))
+		}
+		sequences.extend(start..=end)
+	}
+	Ok(sequences)
+}
+
+/// Writes sequence numbers as ranges of consecutive numbers.
+fn encode_records(bytes: &mut Vec<u8>, sequences: &[u32]) -> Result<()> {
+	let mut sequences = sequences.to_vec();
+	sequences.sort_unstable();
+	sequences.dedup();
+
+	let mut ranges: Vec<(u32, u32)> = Vec::new();
+	for sequence in sequences {
+		match ranges.last_mut() {
+			Some((_, end)) if *end + 1 == sequence => *end = sequence,
+			_ => ranges.push((sequence, sequence))
+		}
+	}
+
+	bytes.unsigned_short(ranges.len() as u16)?;
+	ranges.into_iter().try_for_each(|(start, end)| {
+		bytes.bool(start == end)?;
+		bytes.triad(start)?;
+		match start == end {
+			true => Ok(()),
+			false => bytes.triad(end)
+		}
+	})
+}
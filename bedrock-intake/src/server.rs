@@ -0,0 +1,561 @@
+use self::super::{
+	connection::Connection,
+	login::{Identity, parse_chain},
+	packet::{
+		MovePlayer, Packet, PlayStatus, ResourcePackResponse, StartGame, TextKind,
+		GAME_VERSION, PROTOCOL, decode_batch, encode_batch
+	},
+	raknet::{
+		ConnectedMessage, OfflineRequest, OfflineResponse, RAKNET_PROTOCOL,
+		Reliability
+	},
+	types::Write
+};
+use hermaphrodite::{
+	block::BlockState, chat::ChatPosition, chunk::Chunk, generator::BIOMES,
+	interface::{Edition, MinecraftServer, Movement, PovUpdate, UpdateQueue},
+	position::{ChunkPos, EntityPos},
+	translation::{BedrockBlock, bedrock_biome, bedrock_block}
+};
+use std::{
+	collections::{HashMap, hash_map::RandomState},
+	hash::{BuildHasher, Hasher},
+	io::{Error, ErrorKind, Result},
+	mem::take,
+	net::{SocketAddr, ToSocketAddrs, UdpSocket},
+	sync::Arc,
+	time::{Duration, Instant}
+};
+
+/// How long to wait for a datagram before sending what's queued.
+const RECEIVE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The largest datagrams we'll agree to, which is what fits in an ethernet
+/// frame.
+const MAX_MTU: u16 = 1492;
+
+/// The smallest datagrams we'll agree to, which every IPv4 host has to accept.
+/// Anything smaller wouldn't leave room for the headers.
+const MIN_MTU: u16 = 576;
+
+/// How far above a player's feet their eyes are. Bedrock Edition positions
+/// players by their eyes, and the server by their feet.
+const EYE_HEIGHT: f64 = 1.62;
+
+/// How many connections one IP address may have open before they've logged
+/// in, so that opening connections can't use up the server's memory.
+const MAX_HALF_OPEN: usize = 4;
+
+/// Accepts connections until the server stops running, then returns once every
+/// client has been disconnected.
+pub fn run_server<'s, S>(server: Arc<S>, address: impl ToSocketAddrs)
+		where S: MinecraftServer<'s> + 'static {
+	let socket = UdpSocket::bind(address).unwrap();
+	// Receiving can't block, so that queued datagrams are sent in time.
+	socket.set_read_timeout(Some(RECEIVE_INTERVAL)).unwrap();
+	let guid = RandomState::new().build_hasher().finish();
+	let epoch = Instant::now();
+	let mut clients: HashMap<SocketAddr, Client> = HashMap::new();
+	let mut buffer = [0; MAX_MTU as usize];
+
+	loop {
+		// The server's last updates, such as disconnecting everyone, still
+		// have to be sent once it stops.
+		let stopped = !server.running();
+
+		match socket.recv_from(&mut buffer) {
+			// Empty datagrams are allowed, but don't mean anything.
+			Ok((0, _)) => (),
+			Ok((length, address)) => {
+				let datagram = &buffer[..length];
+				// Connected datagrams all have their highest bit set.
+				let connected = datagram[0] & 0x80 != 0;
+				let result = match clients.get_mut(&address) {
+					Some(client) if connected =>
+						client.connection.receive(datagram).and_then(|batches|
+							batches.iter().try_for_each(|batch|
+								decode_batch(batch)?.into_iter().try_for_each(|packet|
+									process_packet(packet, client, &*server)))),
+					// What's left of a connection that was already closed.
+					None if connected => Ok(()),
+					_ => process_offline(datagram, address, &socket, guid,
+						epoch, &mut clients, &*server)
+				};
+
+				if let Err(error) = result {
+					eprintln!("Disconnecting {}: {}", address, error);
+					if let Some(client) = clients.remove(&address) {
+						disconnect(client, &socket, &*server)
+					}
+				}
+			},
+			Err(error) if matches!(error.kind(),
+				ErrorKind::WouldBlock | ErrorKind::TimedOut) => (),
+			Err(error) => eprintln!("Couldn't receive a datagram: {}", error)
+		}
+
+		let mut disconnected = Vec::new();
+		clients.iter_mut().for_each(|(address, client)| {
+			let result: Result<()> = try {
+				process_updates(client, &*server)?;
+				client.flush(&socket)?
+			};
+
+			match result {
+				Ok(()) if !client.connection.closed() => (),
+				Ok(()) => disconnected.push(*address),
+				Err(error) => {
+					eprintln!("Disconnecting {}: {}", address, error);
+					disconnected.push(*address)
+				}
+			}
+		});
+
+		disconnected.into_iter()
+			.filter_map(|address| clients.remove(&address))
+			.for_each(|client| disconnect(client, &socket, &*server));
+
+		if stopped {break}
+	}
+
+	clients.into_iter()
+		.for_each(|(_, client)| disconnect(client, &socket, &*server))
+}
+
+/// A connection, and the point of view it controls once it's spawned.
+pub struct Client {
+	pub connection: Connection,
+	/// Who the client said it is, once it's logged in.
+	pub identity: Option<Identity>,
+	/// The name of the client's point of view, once it's spawned.
+	pub username: Option<String>,
+	entity_id: i32,
+	/// Whether the client was told it can be shown the world.
+	spawned: bool,
+	view_center: Option<ChunkPos>,
+	/// How far the client would like to see, in chunks.
+	requested_distance: Option<u8>,
+	/// What the server has to tell the point of view.
+	updates: UpdateQueue,
+	/// Packets waiting to be sent together in the next batch.
+	outgoing: Vec<Packet>
+}
+
+impl Client {
+	pub fn new(connection: Connection) -> Self {
+		let address = connection.address.ip();
+		Self {
+			connection,
+			identity: None,
+			username: None,
+			entity_id: 0,
+			spawned: false,
+			view_center: None,
+			requested_distance: None,
+			updates: UpdateQueue::new(Edition::Bedrock, Some(address)),
+			outgoing: Vec::new()
+		}
+	}
+
+	/// Batches up every queued packet, and sends whatever the connection has
+	/// ready.
+	pub fn flush(&mut self, socket: &UdpSocket) -> Result<()> {
+		if !self.outgoing.is_empty() {
+			let batch = encode_batch(&take(&mut self.outgoing))?;
+			self.connection.send(&ConnectedMessage::Game(batch),
+				Reliability::ReliableOrdered)?
+		}
+
+		for datagram in self.connection.flush()? {
+			socket.send_to(&datagram, self.connection.address)?;
+		}
+		Ok(())
+	}
+}
+
+/// Tells a client it's being disconnected, and forgets about it's point of
+/// view.
+fn disconnect<'s, S>(mut client: Client, socket: &UdpSocket, server: &S)
+		where S: MinecraftServer<'s> {
+	let result: Result<()> = try {
+		if !client.connection.closed() {
+			client.connection.disconnect()?;
+			client.flush(socket)?
+		}
+	};
+
+	if let Err(error) = result {
+		eprintln!("Couldn't disconnect {}: {}", client.connection.address, error)
+	}
+	if let Some(username) = client.username {server.remove_pov(&username)}
+}
+
+/// Answers messages sent without a connection, opening one if it's asked for.
+fn process_offline<'s, S>(datagram: &[u8], address: SocketAddr,
+		socket: &UdpSocket, guid: u64, epoch: Instant,
+		clients: &mut HashMap<SocketAddr, Client>, server: &S) -> Result<()>
+		where S: MinecraftServer<'s> {
+	let response = match OfflineRequest::decode(datagram)? {
+		// Open pings only want an answer if there's room to join.
+		OfflineRequest::UnconnectedPing {open: true, ..}
+				if server.player_count() >= server.max_players() =>
+			return Ok(()),
+		OfflineRequest::UnconnectedPing {time, ..} =>
+			OfflineResponse::UnconnectedPong {
+				time,
+				server_guid: guid,
+				data: advertisement(server, guid, socket.local_addr()?.port())
+			},
+		OfflineRequest::OpenConnectionRequest1 {protocol, ..}
+				if protocol != RAKNET_PROTOCOL =>
+			OfflineResponse::IncompatibleProtocol {
+				protocol: RAKNET_PROTOCOL,
+				server_guid: guid
+			},
+		OfflineRequest::OpenConnectionRequest1 {mtu, ..} =>
+			OfflineResponse::OpenConnectionReply1 {
+				server_guid: guid,
+				mtu: mtu.max(MIN_MTU).min(MAX_MTU)
+			},
+		OfflineRequest::OpenConnectionRequest2 {mtu, client_guid, ..} => {
+			// Reopening a connection has to close the old one properly, or it's
+			// point of view would be left behind.
+			if let Some(client) = clients.remove(&address) {
+				disconnect(client, socket, server)
+			}
+
+			let half_open = clients.values().filter(|client| client.identity.is_none()
+				&& client.connection.address.ip() == address.ip()).count();
+			// The client will give up on hearing nothing back.
+			if half_open >= MAX_HALF_OPEN {return Ok(())}
+
+			let mtu = mtu.max(MIN_MTU).min(MAX_MTU);
+			let connection = Connection::new(address, client_guid, mtu, epoch);
+			clients.insert(address, Client::new(connection));
+			OfflineResponse::OpenConnectionReply2 {
+				server_guid: guid,
+				client_address: address,
+				mtu
+			}
+		}
+	};
+
+	socket.send_to(&response.encode()?, address)?;
+	Ok(())
+}
+
+/// What the server advertises itself as in server lists, as fields separated
+/// by semicolons. The message of the day's first line is the server's name,
+/// and it's second line, if it has one, is shown as the world's name.
+fn advertisement<'s, S>(server: &S, guid: u64, port: u16) -> String
+		where S: MinecraftServer<'s> {
+	// Semicolons would start a new field.
+	let message = server.message_of_the_day().replace(';', "\\;");
+	let mut lines = message.lines();
+	let name = lines.next().unwrap_or_default();
+	let world_name = lines.next().unwrap_or(name);
+	let gamemode = server.gamemode();
+	let gamemode_name = gamemode.name();
+
+	format!("MCPE;{};{};{};{};{};{};{};{}{};{};{};{};",
+		name,
+		PROTOCOL,
+		GAME_VERSION,
+		server.player_count(),
+		server.max_players(),
+		guid,
+		world_name,
+		gamemode_name[..1].to_uppercase(),
+		&gamemode_name[1..],
+		gamemode.id(),
+		port, // The IPv4 port.
+		port) // The IPv6 port.
+}
+
+/// Queues everything the server has to tell a client's point of view.
+pub fn process_updates<'s, S>(client: &mut Client, server: &S) -> Result<()>
+		where S: MinecraftServer<'s> {
+	let updates = client.updates.take();
+	queue_updates(client, server, updates)
+}
+
+fn queue_updates<'s, S>(client: &mut Client, server: &S,
+		updates: Vec<PovUpdate>) -> Result<()> where S: MinecraftServer<'s> {
+	let username = match &client.username {
+		Some(username) => username,
+		None => return Ok(())
+	};
+
+	let mut kicked = None;
+	for update in updates {
+		match update {
+			PovUpdate::ChunkLoad(chunk) => {
+				client.outgoing.push(level_chunk(&chunk)?);
+				// The client waits on the chunk it's in before showing the world.
+				if !client.spawned && client.view_center == Some(chunk.position()) {
+					client.outgoing.push(Packet::PlayStatus(PlayStatus::PlayerSpawn));
+					client.spawned = true
+				}
+			},
+			// Clients drop chunks outside of where they're published around.
+			PovUpdate::ChunkUnload(_) => (),
+			PovUpdate::ViewCenter(position) => {
+				client.view_center = Some(position);
+				client.outgoing.push(publisher_update(position,
+					view_distance(client, server.view_distance())))
+			},
+			PovUpdate::ViewDistance(distance) => {
+				let distance = view_distance(client, distance);
+				client.outgoing.push(Packet::ChunkRadiusUpdated(distance as i32));
+				if let Some(position) = client.view_center {
+					client.outgoing.push(publisher_update(position, distance))
+				}
+			},
+			PovUpdate::Teleport {position, rotation, teleport_id} => {
+				client.outgoing.push(Packet::MovePlayer(MovePlayer {
+					entity_id: client.entity_id as u64,
+					position: eye_position(position),
+					rotation,
+					teleport: true,
+					grounded: false,
+					tick: 0
+				}));
+				// Bedrock Edition has no way of confirming teleports.
+				server.pov_teleport_confirm(username, teleport_id)
+			},
+			PovUpdate::Chat {message, position, ..} =>
+				client.outgoing.push(Packet::Text {
+					// Messages players said already say who said them.
+					kind: match position {
+						ChatPosition::Chat => TextKind::Raw,
+						ChatPosition::System => TextKind::System,
+						ChatPosition::ActionBar => TextKind::Tip
+					},
+					source: String::new(),
+					message: message.into()
+				}),
+			PovUpdate::Disconnect(reason) => {
+				client.outgoing.push(Packet::Disconnect(Some(reason.clone().into())));
+				kicked = Some(reason)
+			},
+			// Other players and commands aren't shown to Bedrock Edition
+			// clients yet.
+			PovUpdate::PlayerJoin(_)
+				| PovUpdate::PlayerLeave(_)
+				| PovUpdate::EntitySpawn {..}
+				| PovUpdate::EntityMove {..}
+				| PovUpdate::EntityLook {..}
+				| PovUpdate::EntityMetadata {..}
+				| PovUpdate::EntityDestroy(_)
+				| PovUpdate::Commands(_) => ()
+		}
+	}
+
+	match kicked {
+		Some(reason) => Err(Error::new(ErrorKind::ConnectionAborted,
+			format!("Kicked: {}", reason))),
+		None => Ok(())
+	}
+}
+
+pub fn process_packet<'s, S>(packet: Packet, client: &mut Client, server: &S)
+		-> Result<()> where S: MinecraftServer<'s> {
+	match packet {
+		Packet::Login {protocol, chain, ..} => {
+			if client.identity.is_some() {
+				return Err(Error::new(ErrorKind::InvalidData, "Already logged in."))
+			}
+
+			if protocol != PROTOCOL {
+				client.outgoing.push(Packet::PlayStatus(match protocol < PROTOCOL {
+					true => PlayStatus::FailedClient,
+					false => PlayStatus::FailedServer
+				}));
+				return Err(Error::new(ErrorKind::InvalidData,
+					format!("Unsupported protocol {}.", protocol)))
+			}
+
+			let identity = parse_chain(&chain)
+				.map_err(|error| Error::new(ErrorKind::InvalidData, error))?;
+			client.identity = Some(identity);
+			client.outgoing.push(Packet::PlayStatus(PlayStatus::LoginSuccess));
+			// We don't have any resource packs, but the client asks for them
+			// anyway.
+			client.outgoing.push(Packet::ResourcePacksInfo);
+			Ok(())
+		},
+		Packet::ResourcePackClientResponse(response) => match response {
+			ResourcePackResponse::Refused => Err(Error::new(
+				ErrorKind::ConnectionAborted, "Refused resource packs.")),
+			ResourcePackResponse::SendPacks
+				| ResourcePackResponse::HaveAllPacks => {
+					client.outgoing.push(Packet::ResourcePackStack);
+					Ok(())
+				},
+			ResourcePackResponse::Completed => spawn(client, server)
+		},
+		Packet::RequestChunkRadius(radius) => {
+			if let Some(username) = &client.username {
+				let radius = radius.max(0).min(u8::MAX as i32) as u8;
+				client.requested_distance = Some(radius);
+				server.pov_view_distance(username, radius);
+				let distance = view_distance(client, server.view_distance());
+				client.outgoing.push(Packet::ChunkRadiusUpdated(distance as i32))
+			}
+			Ok(())
+		},
+		Packet::MovePlayer(MovePlayer {position: (x, y, z), rotation, grounded, ..}) => {
+			if let Some(username) = &client.username {
+				server.pov_move(username, Movement {
+					position: Some(EntityPos::new(x as f64, y as f64 - EYE_HEIGHT,
+						z as f64)),
+					rotation: Some(rotation),
+					grounded
+				})
+			}
+			Ok(())
+		},
+		Packet::PlayerAction(action) => {
+			if let Some(username) = &client.username {
+				match action {
+					9 => server.pov_sprinting(username, true),
+					10 => server.pov_sprinting(username, false),
+					11 => server.pov_sneaking(username, true),
+					12 => server.pov_sneaking(username, false),
+					_ => ()
+				}
+			}
+			Ok(())
+		},
+		Packet::AdventureSettings {flags} => {
+			if let Some(username) = &client.username {
+				server.pov_flying(username, flags & 0x200 != 0)
+			}
+			Ok(())
+		},
+		Packet::Text {message, ..} => {
+			if let Some(username) = &client.username {
+				server.pov_chat(username, &message)
+			}
+			Ok(())
+		},
+		Packet::SetLocalPlayerAsInitialized
+			| Packet::Unknown(_) =>
+				Ok(()),
+		_ => Err(Error::new(ErrorKind::InvalidData,
+			format!("Idk this packet, {:?}.", packet)))
+	}
+}
+
+/// Gives a client that's finished logging in a point of view, and starts the
+/// game where it is.
+fn spawn<'s, S>(client: &mut Client, server: &S) -> Result<()>
+		where S: MinecraftServer<'s> {
+	let username = match (&client.identity, &client.username) {
+		(Some(identity), None) => identity.display_name.clone(),
+		_ => return Err(Error::new(ErrorKind::InvalidData,
+			"Spawning before logging in, or twice."))
+	};
+
+	let joined = server.new_pov(username.clone().into_boxed_str(),
+		Box::new(client.updates.clone()));
+	if let Err(reason) = joined {
+		client.outgoing.push(Packet::Disconnect(Some(reason.clone().into())));
+		return Err(Error::new(ErrorKind::ConnectionAborted,
+			format!("Kicked: {}", reason)))
+	}
+	client.entity_id = server.pov_entity_id(&username).unwrap_or(0);
+	client.username = Some(username);
+
+	// The game starts wherever the point of view was first teleported to.
+	let updates = client.updates.take();
+	let (position, rotation) = updates.iter()
+		.find_map(|update| match update {
+			PovUpdate::Teleport {position, rotation, ..} => Some((*position, *rotation)),
+			_ => None
+		})
+		.unwrap_or((EntityPos::new(0., 0., 0.), (0., 0.)));
+	let spawn = position.block();
+
+	client.outgoing.push(Packet::StartGame(StartGame {
+		entity_id: client.entity_id as i64,
+		gamemode: server.gamemode().id() as i32,
+		position: eye_position(position),
+		rotation,
+		difficulty: server.difficulty().id() as i32,
+		spawn: (spawn.x, spawn.y, spawn.z),
+		world_name: server.message_of_the_day(),
+		flat: server.world_flat()
+	}));
+	queue_updates(client, server, updates)
+}
+
+/// How far a client may see, given how far the server lets it.
+fn view_distance(client: &Client, server_distance: u8) -> u8 {
+	client.requested_distance
+		.map_or(server_distance, |distance| distance.min(server_distance))
+}
+
+/// Where chunks are loaded around, in blocks.
+fn publisher_update(position: ChunkPos, distance: u8) -> Packet {
+	let origin = position.origin();
+	Packet::NetworkChunkPublisherUpdate {
+		position: (origin.x + 8, 0, origin.z + 8),
+		radius: distance as u32 * 16
+	}
+}
+
+fn eye_position(position: EntityPos) -> (f32, f32, f32) {
+	(position.x as f32, (position.y + EYE_HEIGHT) as f32, position.z as f32)
+}
+
+/// Writes a chunk's sections in the legacy format, which identifies blocks by
+/// their numeric identifier and data value. Unlike runtime identifiers, those
+/// don't depend on which blocks the client knows about.
+fn level_chunk(chunk: &Chunk) -> Result<Packet> {
+	let sections = chunk.sections();
+	let count = sections.iter().rposition(Option::is_some).map_or(0, |top| top + 1);
+	let mut translated = HashMap::new();
+	let mut payload = Vec::new();
+
+	for section in &sections[..count] {
+		let mut identifiers = vec![0; 4096];
+		let mut data = vec![0; 2048];
+		if let Some(section) = section {
+			// Bedrock Edition orders blocks by x, then z, then y.
+			for index in 0..4096 {
+				let (x, y, z) = (index & 15, index >> 8, index >> 4 & 15);
+				let state = section.get(index);
+				let BedrockBlock {id, data: value} = *translated.entry(state)
+					.or_insert_with(|| bedrock_block(
+						BlockState::from_id(state).unwrap_or(BlockState::AIR)));
+				let legacy_index = x << 8 | z << 4 | y;
+				identifiers[legacy_index] = id;
+				data[legacy_index / 2] |= value << (legacy_index % 2 * 4);
+			}
+		}
+
+		payload.unsigned_byte(0)?; // The legacy format's version.
+		payload.bytes(&identifiers)?;
+		payload.bytes(&data)?;
+	}
+
+	// Bedrock Edition has a biome per column, rather than per 4x4x4 cell, so
+	// the cells at sea level are used.
+	let biomes = chunk.biomes();
+	for column in 0..256 {
+		let (x, z) = (column & 15, column >> 4);
+		let biome = biomes.get(16 << 4 | (z >> 2) << 2 | x >> 2);
+		payload.unsigned_byte(bedrock_biome(BIOMES.get(biome as usize)
+			.copied().unwrap_or("plains")))?
+	}
+	payload.unsigned_byte(0)?; // Blocks on the world's border.
+
+	let position = chunk.position();
+	Ok(Packet::LevelChunk {
+		x: position.x,
+		z: position.z,
+		sections: count as u32,
+		payload
+	})
+}
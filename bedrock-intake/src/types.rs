@@ -0,0 +1,303 @@
+use std::{
+	io::{Error, ErrorKind, Read as IORead, Result, Write as IOWrite},
+	mem::size_of,
+	net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6}
+};
+
+/// Starts off every offline message, to tell them apart from garbage.
+pub const MAGIC: [u8; 16] = [
+	0x00, 0xFF, 0xFF, 0x00, 0xFE, 0xFE, 0xFE, 0xFE,
+	0xFD, 0xFD, 0xFD, 0xFD, 0x12, 0x34, 0x56, 0x78
+];
+
+/// The address family of IPv6 addresses, as Windows numbers it.
+const AF_INET6: u16 = 23;
+
+macro read_primitive_type($target:ty, $name:ident) {
+	fn $name(&mut self) -> Result<$target> {
+		let mut buffer = [0; size_of::<$target>()];
+		self.read_exact(&mut buffer)?;
+		Ok(<$target>::from_be_bytes(buffer))
+	}
+}
+
+macro write_primitive_type($target:ty, $name:ident) {
+	fn $name(&mut self, value: $target) -> Result<()> {
+		self.write_all(&<$target>::to_be_bytes(value))
+	}
+}
+
+macro read_little_type($target:ty, $name:ident) {
+	fn $name(&mut self) -> Result<$target> {
+		let mut buffer = [0; size_of::<$target>()];
+		self.read_exact(&mut buffer)?;
+		Ok(<$target>::from_le_bytes(buffer))
+	}
+}
+
+macro write_little_type($target:ty, $name:ident) {
+	fn $name(&mut self, value: $target) -> Result<()> {
+		self.write_all(&<$target>::to_le_bytes(value))
+	}
+}
+
+/// Reads RakNet's types, which are big endian unless said otherwise, and the
+/// game's types, which are little endian or variable length.
+pub trait Read {
+	fn bool(&mut self) -> Result<bool>;
+	fn unsigned_byte(&mut self) -> Result<u8>;
+	fn unsigned_short(&mut self) -> Result<u16>;
+	fn unsigned_int(&mut self) -> Result<u32>;
+	fn long(&mut self) -> Result<i64>;
+	fn unsigned_long(&mut self) -> Result<u64>;
+
+	/// Reads a little endian 24 bit integer, which RakNet calls a triad.
+	fn triad(&mut self) -> Result<u32>;
+	/// Reads the offline message magic, failing if it's anything else.
+	fn magic(&mut self) -> Result<()>;
+	fn address(&mut self) -> Result<SocketAddr>;
+	/// Reads a string prefixed with it's length as an unsigned short.
+	fn string(&mut self) -> Result<String>;
+
+	fn little_short(&mut self) -> Result<i16>;
+	fn little_unsigned_short(&mut self) -> Result<u16>;
+	fn little_int(&mut self) -> Result<i32>;
+	fn little_unsigned_int(&mut self) -> Result<u32>;
+	fn little_long(&mut self) -> Result<i64>;
+	fn little_float(&mut self) -> Result<f32>;
+
+	/// Reads an unsigned integer, 7 bits at a time.
+	fn variable_unsigned_integer(&mut self) -> Result<u32>;
+	fn variable_unsigned_long(&mut self) -> Result<u64>;
+	/// Reads a signed integer, which is zigzag encoded so that small negative
+	/// numbers stay short.
+	fn variable_integer(&mut self) -> Result<i32>;
+	fn variable_long(&mut self) -> Result<i64>;
+
+	/// Reads bytes prefixed with their length as a variable unsigned integer.
+	fn variable_bytes(&mut self) -> Result<Vec<u8>>;
+	/// Reads a string prefixed with it's length as a variable unsigned integer.
+	fn variable_string(&mut self) -> Result<String>;
+}
+
+impl<R> Read for R
+		where R: IORead {
+	fn bool(&mut self) -> Result<bool> {
+		Ok(self.unsigned_byte()? != 0)
+	}
+
+	read_primitive_type!(u8, unsigned_byte);
+	read_primitive_type!(u16, unsigned_short);
+	read_primitive_type!(u32, unsigned_int);
+	read_primitive_type!(i64, long);
+	read_primitive_type!(u64, unsigned_long);
+
+	fn triad(&mut self) -> Result<u32> {
+		let mut buffer = [0; 4];
+		self.read_exact(&mut buffer[..3])?;
+		Ok(u32::from_le_bytes(buffer))
+	}
+
+	fn magic(&mut self) -> Result<()> {
+		let mut magic = [0; 16];
+		self.read_exact(&mut magic)?;
+		match magic == MAGIC {
+			true => Ok(()),
+			false => Err(Error::new(ErrorKind::InvalidData, "Bad offline magic."))
+		}
+	}
+
+	fn address(&mut self) -> Result<SocketAddr> {
+		match self.unsigned_byte()? {
+			4 => {
+				let mut ip = [0; 4];
+				self.read_exact(&mut ip)?;
+				// IPv4 addresses are sent with every bit flipped.
+				let ip = Ipv4Addr::from(u32::from_be_bytes(ip) ^ u32::MAX);
+				Ok(SocketAddr::new(IpAddr::V4(ip), self.unsigned_short()?))
+			},
+			6 => {
+				self.unsigned_short()?; // The address family, which must be IPv6.
+				let port = self.unsigned_short()?;
+				let flow = self.unsigned_int()?;
+				let mut ip = [0; 16];
+				self.read_exact(&mut ip)?;
+				let scope = self.unsigned_int()?;
+				Ok(SocketAddrV6::new(Ipv6Addr::from(ip), port, flow, scope).into())
+			},
+			version => Err(Error::new(ErrorKind::InvalidData,
+				format!("Unknown IP version {}.", version)))
+		}
+	}
+
+	fn string(&mut self) -> Result<String> {
+		let mut buffer = vec![0; self.unsigned_short()? as usize];
+		self.read_exact(&mut buffer)?;
+		String::from_utf8(buffer).map_err(|_|
+			Error::new(ErrorKind::InvalidData, "String data was not UTF-8."))
+	}
+
+	read_little_type!(i16, little_short);
+	read_little_type!(u16, little_unsigned_short);
+	read_little_type!(i32, little_int);
+	read_little_type!(u32, little_unsigned_int);
+	read_little_type!(i64, little_long);
+	read_little_type!(f32, little_float);
+
+	fn variable_unsigned_integer(&mut self) -> Result<u32> {
+		match self.variable_unsigned_long()? {
+			value if value > u32::MAX as u64 => Err(Error::new(
+				ErrorKind::InvalidData, "Variable integer was too big.")),
+			value => Ok(value as u32)
+		}
+	}
+
+	fn variable_unsigned_long(&mut self) -> Result<u64> {
+		let mut value = 0;
+		for shift in (0..70).step_by(7) {
+			let byte = self.unsigned_byte()?;
+			value |= ((byte & 0x7F) as u64) << shift;
+			if byte & 0x80 == 0 {return Ok(value)}
+		}
+		Err(Error::new(ErrorKind::InvalidData, "Variable long was too big."))
+	}
+
+	fn variable_integer(&mut self) -> Result<i32> {
+		let value = self.variable_unsigned_integer()?;
+		Ok((value >> 1) as i32 ^ -((value & 1) as i32))
+	}
+
+	fn variable_long(&mut self) -> Result<i64> {
+		let value = self.variable_unsigned_long()?;
+		Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+	}
+
+	fn variable_bytes(&mut self) -> Result<Vec<u8>> {
+		let length = self.variable_unsigned_integer()? as usize;
+		let mut buffer = Vec::new();
+		self.by_ref().take(length as u64).read_to_end(&mut buffer)?;
+		match buffer.len() == length {
+			true => Ok(buffer),
+			false => Err(Error::new(ErrorKind::UnexpectedEof, "Bytes were cut short."))
+		}
+	}
+
+	fn variable_string(&mut self) -> Result<String> {
+		String::from_utf8(self.variable_bytes()?).map_err(|_|
+			Error::new(ErrorKind::InvalidData, "String data was not UTF-8."))
+	}
+}
+
+pub trait Write {
+	fn bool(&mut self, value: bool) -> Result<()>;
+	fn unsigned_byte(&mut self, value: u8) -> Result<()>;
+	fn unsigned_short(&mut self, value: u16) -> Result<()>;
+	fn unsigned_int(&mut self, value: u32) -> Result<()>;
+	fn long(&mut self, value: i64) -> Result<()>;
+	fn unsigned_long(&mut self, value: u64) -> Result<()>;
+
+	fn triad(&mut self, value: u32) -> Result<()>;
+	fn magic(&mut self) -> Result<()>;
+	fn address(&mut self, value: SocketAddr) -> Result<()>;
+	fn string(&mut self, value: &str) -> Result<()>;
+	fn bytes(&mut self, value: &[u8]) -> Result<()>;
+
+	fn little_short(&mut self, value: i16) -> Result<()>;
+	fn little_unsigned_short(&mut self, value: u16) -> Result<()>;
+	fn little_int(&mut self, value: i32) -> Result<()>;
+	fn little_unsigned_int(&mut self, value: u32) -> Result<()>;
+	fn little_long(&mut self, value: i64) -> Result<()>;
+	fn little_float(&mut self, value: f32) -> Result<()>;
+
+	fn variable_unsigned_integer(&mut self, value: u32) -> Result<()>;
+	fn variable_unsigned_long(&mut self, value: u64) -> Result<()>;
+	fn variable_integer(&mut self, value: i32) -> Result<()>;
+	fn variable_long(&mut self, value: i64) -> Result<()>;
+
+	fn variable_bytes(&mut self, value: &[u8]) -> Result<()>;
+	fn variable_string(&mut self, value: &str) -> Result<()>;
+}
+
+impl<W> Write for W
+		where W: IOWrite {
+	fn bool(&mut self, value: bool) -> Result<()> {
+		self.unsigned_byte(value as u8)
+	}
+
+	write_primitive_type!(u8, unsigned_byte);
+	write_primitive_type!(u16, unsigned_short);
+	write_primitive_type!(u32, unsigned_int);
+	write_primitive_type!(i64, long);
+	write_primitive_type!(u64, unsigned_long);
+
+	fn triad(&mut self, value: u32) -> Result<()> {
+		self.write_all(&value.to_le_bytes()[..3])
+	}
+
+	fn magic(&mut self) -> Result<()> {
+		self.write_all(&MAGIC)
+	}
+
+	fn address(&mut self, value: SocketAddr) -> Result<()> {
+		match value {
+			SocketAddr::V4(address) => {
+				self.unsigned_byte(4)?;
+				self.unsigned_int(u32::from(*address.ip()) ^ u32::MAX)?;
+				self.unsigned_short(address.port())
+			},
+			SocketAddr::V6(address) => {
+				self.unsigned_byte(6)?;
+				self.write_all(&AF_INET6.to_le_bytes())?;
+				self.unsigned_short(address.port())?;
+				self.unsigned_int(address.flowinfo())?;
+				self.write_all(&address.ip().octets())?;
+				self.unsigned_int(address.scope_id())
+			}
+		}
+	}
+
+	fn string(&mut self, value: &str) -> Result<()> {
+		self.unsigned_short(value.len() as u16)?;
+		self.write_all(value.as_bytes())
+	}
+
+	fn bytes(&mut self, value: &[u8]) -> Result<()> {
+		self.write_all(value)
+	}
+
+	write_little_type!(i16, little_short);
+	write_little_type!(u16, little_unsigned_short);
+	write_little_type!(i32, little_int);
+	write_little_type!(u32, little_unsigned_int);
+	write_little_type!(i64, little_long);
+	write_little_type!(f32, little_float);
+
+	fn variable_unsigned_integer(&mut self, value: u32) -> Result<()> {
+		self.variable_unsigned_long(value as u64)
+	}
+
+	fn variable_unsigned_long(&mut self, mut value: u64) -> Result<()> {
+		while value >= 0x80 {
+			self.unsigned_byte(value as u8 | 0x80)?;
+			value >>= 7;
+		}
+		self.unsigned_byte(value as u8)
+	}
+
+	fn variable_integer(&mut self, value: i32) -> Result<()> {
+		self.variable_unsigned_integer((value << 1 ^ value >> 31) as u32)
+	}
+
+	fn variable_long(&mut self, value: i64) -> Result<()> {
+		self.variable_unsigned_long((value << 1 ^ value >> 63) as u64)
+	}
+
+	fn variable_bytes(&mut self, value: &[u8]) -> Result<()> {
+		self.variable_unsigned_integer(value.len() as u32)?;
+		self.write_all(value)
+	}
+
+	fn variable_string(&mut self, value: &str) -> Result<()> {
+		self.variable_bytes(value.as_bytes())
+	}
+}
@@ -0,0 +1,320 @@
+use hermaphrodite::settings::{Settings, UnknownVariant, VIEW_DISTANCES};
+use std::{
+	error::Error, fmt::{Display, Formatter, Result as FMTResult},
+	fs::{read_to_string, write}, io::{Error as IOError, ErrorKind},
+	net::IpAddr, path::PathBuf, str::FromStr, time::{SystemTime, UNIX_EPOCH}
+};
+
+/// Where the configuration is read from, unless `--config` says otherwise.
+const DEFAULT_PATH: &str = "server.properties";
+
+/// Everything the server is configured with, read from a vanilla style
+/// `server.properties` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+	/// Where this configuration was read from.
+	pub path: PathBuf,
+	/// The address to listen on, or every address when it's `None`.
+	pub ip: Option<IpAddr>,
+	pub port: u16,
+	/// The port Bedrock Edition clients connect to, over UDP.
+	pub bedrock_port: u16,
+	/// Whether remote consoles may connect over RCON.
+	pub enable_rcon: bool,
+	pub rcon_port: u16,
+	/// What remote consoles log in with. RCON isn't started without one.
+	pub rcon_password: String,
+	pub settings: Settings,
+	/// Whether players are authenticated with Mojang. This isn't supported yet,
+	/// so players always join in offline mode.
+	pub online_mode: bool,
+	/// How long packets have to be to be compressed, or `None` to never
+	/// compress them.
+	pub compression_threshold: Option<usize>,
+	pub level_type: LevelType,
+	/// What the world is generated from. It may be a number, or any other text
+	/// that's hashed into one, and a random seed is chosen when it's empty.
+	pub level_seed: String,
+	/// Where the world is kept. Worlds aren't saved yet, so this isn't used.
+	pub world_path: PathBuf
+}
+
+impl Config {
+	/// Reads the configuration file, creating it with the default
+	/// configuration if it doesn't exist, then applies any overrides given on
+	/// the command line. Overrides look like `--key value` or `--key=value`,
+	/// using the same keys as the file, and `--config path` changes which file
+	/// is read.
+	pub fn load(arguments: impl IntoIterator<Item = String>)
+			-> Result<Self, ConfigError> {
+		let overrides = overrides(arguments)?;
+		let path = overrides.iter()
+			.find(|(key, _)| key == "config")
+			.map(|(_, path)| PathBuf::from(path))
+			.unwrap_or_else(|| DEFAULT_PATH.into());
+
+		let mut config = Self {path: path.clone(), ..Self::default()};
+		match read_to_string(&path) {
+			Ok(contents) => contents.lines().enumerate()
+				.map(|(index, line)| (index + 1, line.trim()))
+				.filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+				.try_for_each(|(number, line)| {
+					let index = line.find('=').ok_or(ConfigError::Syntax(number))?;
+					let (key, value) = (line[..index].trim(), line[index + 1..].trim());
+					match config.set(key, value) {
+						// Vanilla files have plenty of settings we don't use.
+						Err(ConfigError::UnknownKey(key)) =>
+							Ok(eprintln!("Ignoring unknown setting {:?}.", key)),
+						result => result
+					}
+				})?,
+			Err(error) if error.kind() == ErrorKind::NotFound => {
+				println!("Creating {}.", path.display());
+				write(&path, config.to_string()).map_err(ConfigError::Io)?
+			},
+			Err(error) => return Err(ConfigError::Io(error))
+		}
+
+		overrides.iter()
+			.filter(|(key, _)| key != "config")
+			.try_for_each(|(key, value)| config.set(key, value))?;
+		Ok(config)
+	}
+
+	/// The keys of the settings that differ between two configurations, but
+	/// only take effect when the server starts.
+	pub fn restart_required(&self, other: &Self) -> Vec<&'static str> {
+		let changes = [
+			(self.ip != other.ip, "server-ip"),
+			(self.port != other.port, "server-port"),
+			(self.bedrock_port != other.bedrock_port, "bedrock-port"),
+			(self.enable_rcon != other.enable_rcon, "enable-rcon"),
+			(self.rcon_port != other.rcon_port, "rcon.port"),
+			(self.rcon_password != other.rcon_password, "rcon.password"),
+			(self.online_mode != other.online_mode, "online-mode"),
+			(self.compression_threshold != other.compression_threshold,
+				"network-compression-threshold"),
+			(self.settings.gamemode != other.settings.gamemode, "gamemode"),
+			(self.settings.difficulty != other.settings.difficulty, "difficulty"),
+			(self.level_type != other.level_type, "level-type"),
+			(self.level_seed != other.level_seed, "level-seed"),
+			(self.world_path != other.world_path, "level-name")
+		];
+		changes.iter()
+			.filter(|(changed, _)| *changed)
+			.map(|(_, key)| *key)
+			.collect()
+	}
+
+	/// The seed the world is generated from. Text that isn't a number is
+	/// hashed the same way vanilla hashes it, so it generates the same seed.
+	pub fn seed(&self) -> u64 {
+		match self.level_seed.as_str() {
+			"" => SystemTime::now().duration_since(UNIX_EPOCH)
+				.map(|time| time.as_nanos() as u64).unwrap_or_default(),
+			seed => seed.parse::<i64>().unwrap_or_else(|_| seed.encode_utf16()
+				.fold(0i32, |hash, unit| hash.wrapping_mul(31).wrapping_add(unit as i32))
+				as i64) as u64
+		}
+	}
+
+	/// Where to listen for Java Edition connections.
+	pub fn address(&self) -> String {
+		self.address_with_port(self.port)
+	}
+
+	/// Where to listen for Bedrock Edition connections.
+	pub fn bedrock_address(&self) -> String {
+		self.address_with_port(self.bedrock_port)
+	}
+
+	/// Where to listen for remote consoles.
+	pub fn rcon_address(&self) -> String {
+		self.address_with_port(self.rcon_port)
+	}
+
+	fn address_with_port(&self, port: u16) -> String {
+		match self.ip {
+			Some(IpAddr::V6(ip)) => format!("[{}]:{}", ip, port),
+			Some(IpAddr::V4(ip)) => format!("{}:{}", ip, port),
+			None => format!("0.0.0.0:{}", port)
+		}
+	}
+
+	/// Changes a setting by it's key in the file.
+	fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+		let invalid = |expected| ConfigError::Invalid {
+			key: key.into(),
+			value: value.into(),
+			expected
+		};
+
+		match key {
+			"server-ip" => self.ip = match value {
+				"" => None,
+				ip => Some(ip.parse().map_err(|_| invalid("an IP address"))?)
+			},
+			"server-port" =>
+				self.port = value.parse().map_err(|_| invalid("a port"))?,
+			"bedrock-port" =>
+				self.bedrock_port = value.parse().map_err(|_| invalid("a port"))?,
+			"enable-rcon" => self.enable_rcon =
+				value.parse().map_err(|_| invalid("true or false"))?,
+			"rcon.port" =>
+				self.rcon_port = value.parse().map_err(|_| invalid("a port"))?,
+			"rcon.password" => self.rcon_password = value.into(),
+			"motd" => self.settings.message_of_the_day = value.into(),
+			"max-players" => self.settings.max_players =
+				value.parse().map_err(|_| invalid("a number of players"))?,
+			"view-distance" => self.settings.view_distance = value.parse().ok()
+				.filter(|distance| VIEW_DISTANCES.contains(distance))
+				.ok_or_else(|| invalid("a view distance from 2 to 32"))?,
+			"white-list" => self.settings.whitelist =
+				value.parse().map_err(|_| invalid("true or false"))?,
+			"enforce-whitelist" => self.settings.enforce_whitelist =
+				value.parse().map_err(|_| invalid("true or false"))?,
+			"online-mode" => self.online_mode =
+				value.parse().map_err(|_| invalid("true or false"))?,
+			"network-compression-threshold" => self.compression_threshold =
+				match value.parse::<i32>().map_err(|_| invalid("a number of bytes"))? {
+					threshold if threshold < 0 => None,
+					threshold => Some(threshold as usize)
+				},
+			"gamemode" => self.settings.gamemode = value.parse()
+				.map_err(|_| invalid("survival, creative, adventure or spectator"))?,
+			"difficulty" => self.settings.difficulty = value.parse()
+				.map_err(|_| invalid("peaceful, easy, normal or hard"))?,
+			"level-type" => self.level_type =
+				value.parse().map_err(|_| invalid("default or flat"))?,
+			"level-seed" => self.level_seed = value.into(),
+			"level-name" => self.world_path = match value {
+				"" => return Err(invalid("a world path")),
+				path => path.into()
+			},
+			_ => return Err(ConfigError::UnknownKey(key.into()))
+		}
+		Ok(())
+	}
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			path: DEFAULT_PATH.into(),
+			ip: None,
+			port: 25565,
+			bedrock_port: 19132,
+			enable_rcon: false,
+			rcon_port: 25575,
+			rcon_password: String::new(),
+			settings: Settings::default(),
+			online_mode: false,
+			compression_threshold: Some(256),
+			level_type: LevelType::Flat,
+			level_seed: String::new(),
+			world_path: "world".into()
+		}
+	}
+}
+
+impl Display for Config {
+	/// Writes this configuration in the same format it's read in.
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		writeln!(f, "#Minecraft server properties")?;
+		writeln!(f, "server-ip={}", self.ip.map(|ip| ip.to_string())
+			.unwrap_or_default())?;
+		writeln!(f, "server-port={}", self.port)?;
+		writeln!(f, "bedrock-port={}", self.bedrock_port)?;
+		writeln!(f, "enable-rcon={}", self.enable_rcon)?;
+		writeln!(f, "rcon.port={}", self.rcon_port)?;
+		writeln!(f, "rcon.password={}", self.rcon_password)?;
+		writeln!(f, "motd={}", self.settings.message_of_the_day)?;
+		writeln!(f, "max-players={}", self.settings.max_players)?;
+		writeln!(f, "view-distance={}", self.settings.view_distance)?;
+		writeln!(f, "white-list={}", self.settings.whitelist)?;
+		writeln!(f, "enforce-whitelist={}", self.settings.enforce_whitelist)?;
+		writeln!(f, "online-mode={}", self.online_mode)?;
+		writeln!(f, "network-compression-threshold={}", self.compression_threshold
+			.map(|threshold| threshold as i64).unwrap_or(-1))?;
+		writeln!(f, "gamemode={}", self.settings.gamemode.name())?;
+		writeln!(f, "difficulty={}", self.settings.difficulty.name())?;
+		writeln!(f, "level-type={}", self.level_type.name())?;
+		writeln!(f, "level-seed={}", self.level_seed)?;
+		writeln!(f, "level-name={}", self.world_path.display())
+	}
+}
+
+/// How the world is generated.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LevelType {
+	/// Terrain shaped by noise, with caves and ores, from the level seed.
+	Default,
+	/// The same layers of blocks everywhere.
+	Flat
+}
+
+impl LevelType {
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Default => "default",
+			Self::Flat => "flat"
+		}
+	}
+}
+
+impl FromStr for LevelType {
+	type Err = UnknownVariant;
+
+	fn from_str(name: &str) -> Result<Self, UnknownVariant> {
+		Ok(match name {
+			"default" => Self::Default,
+			"flat" => Self::Flat,
+			_ => return Err(UnknownVariant(name.into()))
+		})
+	}
+}
+
+/// Reads `--key value` and `--key=value` pairs from the command line.
+fn overrides(arguments: impl IntoIterator<Item = String>)
+		-> Result<Vec<(String, String)>, ConfigError> {
+	let mut arguments = arguments.into_iter();
+	let mut overrides = Vec::new();
+
+	while let Some(argument) = arguments.next() {
+		let key = argument.strip_prefix("--")
+			.ok_or_else(|| ConfigError::UnknownKey(argument.as_str().into()))?;
+		overrides.push(match key.find('=') {
+			Some(index) => (key[..index].to_owned(), key[index + 1..].to_owned()),
+			None => (key.to_owned(), arguments.next()
+				.ok_or_else(|| ConfigError::MissingValue(key.into()))?)
+		});
+	}
+
+	Ok(overrides)
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+	Io(IOError),
+	/// A line that's neither `key=value` nor a comment, by it's line number.
+	Syntax(usize),
+	UnknownKey(Box<str>),
+	/// An override on the command line without a value.
+	MissingValue(Box<str>),
+	Invalid {key: Box<str>, value: Box<str>, expected: &'static str}
+}
+
+impl Display for ConfigError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Io(error) => write!(f, "Couldn't read the configuration: {}", error),
+			Self::Syntax(line) => write!(f, "Expected key=value on line {}.", line),
+			Self::UnknownKey(key) => write!(f, "Unknown setting {:?}.", key),
+			Self::MissingValue(key) => write!(f, "Expected a value for {:?}.", key),
+			Self::Invalid {key, value, expected} =>
+				write!(f, "Expected {} for {}, not {:?}.", expected, key, value)
+		}
+	}
+}
+
+impl Error for ConfigError {}
@@ -0,0 +1,61 @@
+use hermaphrodite::{
+	command::CommandSource, interface::MinecraftServer, server::Server
+};
+#[cfg(unix)]
+use libc::{STDIN_FILENO, TCSANOW, tcgetattr, tcsetattr, termios};
+use rustyline::{Editor, error::ReadlineError};
+#[cfg(unix)]
+use std::mem::MaybeUninit;
+use std::sync::Arc;
+
+/// The terminal's settings from before the console started editing lines,
+/// which are put back once this is dropped. The console's thread is usually
+/// still waiting for input when the server stops, with the terminal in raw
+/// mode, and can't be woken up to leave it.
+#[cfg(unix)]
+pub struct Terminal(Option<termios>);
+
+#[cfg(unix)]
+impl Terminal {
+	pub fn save() -> Self {
+		let mut saved = MaybeUninit::uninit();
+		match unsafe {tcgetattr(STDIN_FILENO, saved.as_mut_ptr())} {
+			0 => Self(Some(unsafe {saved.assume_init()})),
+			// Standard input isn't a terminal, so there's nothing to restore.
+			_ => Self(None)
+		}
+	}
+}
+
+#[cfg(unix)]
+impl Drop for Terminal {
+	fn drop(&mut self) {
+		if let Some(saved) = &self.0 {
+			unsafe {tcsetattr(STDIN_FILENO, TCSANOW, saved)};
+		}
+	}
+}
+
+/// Reads commands from standard input, with line editing and history, and runs
+/// them as the console until the server stops or input ends. Interrupting the
+/// console stops the server.
+pub fn run_console(server: Arc<Server<'static>>) {
+	let mut editor = Editor::<()>::new();
+
+	while server.running() {
+		let line = match editor.readline("> ") {
+			Ok(line) => line,
+			Err(ReadlineError::Interrupted) => "stop".to_owned(),
+			Err(ReadlineError::Eof) => return,
+			Err(error) => return eprintln!("Console closed: {}", error)
+		};
+
+		// Commands may be written with the slash players use too.
+		let command = line.trim();
+		let command = command.strip_prefix('/').unwrap_or(command);
+		if command.is_empty() {continue}
+
+		editor.add_history_entry(command);
+		server.command_execute(CommandSource::Console, command);
+	}
+}
@@ -1,16 +1,106 @@
 #![feature(try_blocks)]
 
-use hermaphrodite::server::Server;
-use java_intake::server::run_server;
-use std::{sync::Arc, thread::spawn as thread};
+mod config;
+mod console;
+mod reload;
+
+use self::{
+	config::{Config, LevelType}, console::run_console, reload::{Reloader, reload_command}
+};
+#[cfg(unix)]
+use self::console::Terminal;
+use hermaphrodite::{
+	access::AccessLists, generator::NoiseGenerator, permission::Permissions,
+	server::Server
+};
+use std::{env::args, process::exit, sync::Arc, thread::spawn as thread};
 
 fn main() {
-	let server = Arc::new(Server::new());
+	let arguments = args().skip(1).collect::<Vec<_>>();
+	let config = match Config::load(arguments.iter().cloned()) {
+		Ok(config) => config,
+		Err(error) => {
+			eprintln!("{}", error);
+			exit(1)
+		}
+	};
+	if config.online_mode {
+		eprintln!("Online mode isn't supported yet, players join in offline mode.")
+	}
+
+	// The whitelist, bans, operators and permissions are kept next to the
+	// configuration.
+	let directory = config.path.parent().map(|path| path.to_owned())
+		.unwrap_or_default();
+	let access = match AccessLists::load(&directory) {
+		Ok(access) => access,
+		Err(error) => {
+			eprintln!("{}", error);
+			exit(1)
+		}
+	};
+	let permissions = match Permissions::load(directory) {
+		Ok(permissions) => permissions,
+		Err(error) => {
+			eprintln!("{}", error);
+			exit(1)
+		}
+	};
+
+	let server = match config.level_type {
+		LevelType::Default => Server::with_generator(NoiseGenerator::new(config.seed())),
+		LevelType::Flat => Server::new()
+	};
+	let server = Arc::new(server
+		.with_settings(config.settings.clone())
+		.with_access(access)
+		.with_permissions(permissions));
+	let (address, compression) = (config.address(), config.compression_threshold);
+	let bedrock_address = config.bedrock_address();
+	let rcon = match (config.enable_rcon, config.rcon_password.is_empty()) {
+		(true, true) => {
+			eprintln!("RCON is enabled, but isn't started without an rcon.password.");
+			None
+		},
+		(true, false) => Some((config.rcon_address(), config.rcon_password.clone())),
+		(false, _) => None
+	};
+
+	let reloader = Arc::new(Reloader::new(arguments, config));
+	server.command_register(reload_command(reloader.clone()));
+
+	// Both SIGINT and SIGTERM.
+	let signaled = server.clone();
+	ctrlc::set_handler(move || signaled.stop()).unwrap();
 
 	let java_intake = server.clone();
-	thread(move || {
-		run_server(java_intake, "0.0.0.0:25565");
+	let java_intake = thread(move || {
+		java_intake::run_server(java_intake, address, compression);
 	});
 
+	let bedrock_intake = server.clone();
+	let bedrock_intake = thread(move || {
+		bedrock_intake::run_server(bedrock_intake, bedrock_address);
+	});
+
+	let rcon_intake = rcon.map(|(address, password)| {
+		let rcon_intake = server.clone();
+		thread(move || rcon_intake::run_server(rcon_intake, address, password))
+	});
+
+	let watched = server.clone();
+	let watcher = thread(move || reloader.watch(&watched));
+
+	// The console blocks on reading input, so it isn't waited for. Instead,
+	// the terminal is restored once everything else has stopped.
+	#[cfg(unix)]
+	let _terminal = Terminal::save();
+	let console = server.clone();
+	thread(move || run_console(console));
+
 	server.run();
+	java_intake.join().unwrap();
+	bedrock_intake.join().unwrap();
+	if let Some(rcon_intake) = rcon_intake {rcon_intake.join().unwrap()}
+	watcher.join().unwrap();
 }
@@ -0,0 +1,98 @@
+use self::super::config::{Config, ConfigError};
+use hermaphrodite::{
+	command::{CommandBuilder, CommandError, MAX_PERMISSION_LEVEL, literal},
+	interface::MinecraftServer, server::Server, settings::Settings
+};
+use std::{
+	fs::metadata, path::Path, sync::{Arc, Mutex}, thread::sleep,
+	time::{Duration, SystemTime}
+};
+
+/// How often the configuration file is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Keeps a running server's settings in line with it's configuration file.
+/// Only the message of the day, max players, view distance and whitelist are
+/// changed, everything else needs the server to restart.
+pub struct Reloader {
+	arguments: Vec<String>,
+	/// The configuration the server started with.
+	started: Config,
+	/// When the configuration file was last changed, as of the last reload.
+	modified: Mutex<Option<SystemTime>>
+}
+
+impl Reloader {
+	/// Creates a reloader for a server started with `config`, which was loaded
+	/// with `arguments` as command line overrides.
+	pub fn new(arguments: Vec<String>, started: Config) -> Self {
+		let modified = Mutex::new(modified(&started.path));
+		Self {arguments, started, modified}
+	}
+
+	/// Reads the configuration again and applies it to the server, returning
+	/// the keys of the settings that changed but need a restart. The
+	/// whitelist, ban and operator lists and the permissions are read again
+	/// too, but only by the `reload` command.
+	pub fn reload(&self, server: &Server)
+			-> Result<Vec<&'static str>, ConfigError> {
+		*self.modified.lock().unwrap() = modified(&self.started.path);
+		let config = Config::load(self.arguments.iter().cloned())?;
+
+		server.settings_update(Settings {
+			message_of_the_day: config.settings.message_of_the_day.clone(),
+			max_players: config.settings.max_players,
+			view_distance: config.settings.view_distance,
+			whitelist: config.settings.whitelist,
+			enforce_whitelist: config.settings.enforce_whitelist,
+			..server.settings()
+		});
+		Ok(self.started.restart_required(&config))
+	}
+
+	/// Reloads the configuration whenever it's file changes, until the server
+	/// stops.
+	pub fn watch(&self, server: &Server) {
+		while server.running() {
+			sleep(WATCH_INTERVAL);
+			if modified(&self.started.path) == *self.modified.lock().unwrap() {
+				continue
+			}
+
+			println!("The configuration changed, reloading it.");
+			match self.reload(server) {
+				Ok(keys) =>
+					keys.into_iter().for_each(|key| println!("{}", restart(key))),
+				Err(error) => eprintln!("{}", error)
+			}
+		}
+	}
+}
+
+/// The `reload` command, which reloads the configuration.
+pub fn reload_command(reloader: Arc<Reloader>) -> CommandBuilder<Server<'static>> {
+	literal("reload").requires(MAX_PERMISSION_LEVEL)
+		.permission("minecraft.command.reload")
+		.executes(move |server: &Server<'static>, context| {
+			let keys = reloader.reload(server)
+				.map_err(|error| CommandError::Failed(error.to_string().into()))?;
+			server.access_reload()
+				.map_err(|error| CommandError::Failed(error.to_string().into()))?;
+			server.permissions_reload()
+				.map_err(|error| CommandError::Failed(error.to_string().into()))?;
+
+			server.command_reply(&context.source, "Reloaded the configuration.");
+			keys.into_iter().for_each(|key|
+				server.command_reply(&context.source, &restart(key)));
+			Ok(())
+		})
+}
+
+fn restart(key: &str) -> String {
+	format!("{} changed, but only takes effect once the server restarts.", key)
+}
+
+/// When a file was last changed, if it exists.
+fn modified(path: &Path) -> Option<SystemTime> {
+	metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -0,0 +1,159 @@
+use serde_json::{Map, Value};
+use std::{collections::HashMap, env::var, fs::{read_to_string, write}, path::Path};
+
+/// Generates the block registry from `data/blocks.json`, the block data report
+/// produced by a vanilla server run with `--reports`.
+///
+/// Vanilla lays every block's states out as the cartesian product of it's
+/// properties, sorted by name, with the last property changing fastest. The
+/// registry relies on this to compute state identifiers, so it's verified
+/// against every state listed in the report here.
+fn main() {
+	println!("cargo:rerun-if-changed=data/blocks.json");
+	println!("cargo:rerun-if-changed=data/collisions.json");
+
+	let report = read_to_string("data/blocks.json")
+		.expect("Could not read data/blocks.json.");
+	let report: Map<String, Value> = serde_json::from_str(&report)
+		.expect("data/blocks.json is not a valid block report.");
+
+	let collisions = read_collisions(&report);
+	let mut blocks = report.iter()
+		.map(|(name, block)| parse_block(name, block))
+		.collect::<Vec<_>>();
+	blocks.sort_by_key(|block| block.base);
+
+	// Every identifier below the state count has to belong to a block, so that
+	// any state can be looked up without failing.
+	if blocks.first().map_or(true, |block| block.base != 0) {
+		panic!("No block has the state 0.")
+	}
+	blocks.windows(2).for_each(|pair| match pair[0].base + pair[0].count() {
+		end if end > pair[1].base =>
+			panic!("States of {} overlap with {}.", pair[0].name, pair[1].name),
+		end if end < pair[1].base =>
+			panic!("No block has the states from {} to {}.", end, pair[1].base - 1),
+		_ => ()
+	});
+	let state_count = blocks.last().map_or(0, |block| block.base + block.count());
+
+	let mut by_name = (0..blocks.len()).collect::<Vec<_>>();
+	by_name.sort_by_key(|index| &blocks[*index].name);
+
+	let mut output = String::new();
+	output.push_str("/// How many block states there are. Every identifier below\n");
+	output.push_str("/// this is the state of some block.\n");
+	output.push_str(&format!("pub const STATE_COUNT: u32 = {};\n\n", state_count));
+	output.push_str("/// Every block in the registry, ordered by state identifier.\n");
+	output.push_str("pub static BLOCKS: &[Block] = &[\n");
+	blocks.iter().for_each(|block| {
+		let properties = block.properties.iter()
+			.map(|(name, values)| format!("({:?}, &[{}])", name, values.iter()
+				.map(|value| format!("{:?}", value)).collect::<Vec<_>>().join(", ")))
+			.collect::<Vec<_>>().join(", ");
+
+		let collision = collisions.get(&block.name).copied().unwrap_or("Full");
+
+		output.push_str(&format!(concat!("\tBlock {{name: {:?}, properties: &[{}], ",
+			"base: {}, default: {}, collision: Collision::{}}},\n"),
+			block.name, properties, block.base, block.default, collision));
+	});
+	output.push_str("];\n\n");
+
+	output.push_str("/// Indices into `BLOCKS`, ordered by block name.\n");
+	output.push_str("static BLOCKS_BY_NAME: &[usize] = &[");
+	output.push_str(&by_name.iter().map(usize::to_string)
+		.collect::<Vec<_>>().join(", "));
+	output.push_str("];\n");
+
+	let path = Path::new(&var("OUT_DIR").unwrap()).join("blocks.rs");
+	write(path, output).expect("Could not write the block registry.");
+}
+
+/// Reads which blocks aren't full cubes from `data/collisions.json`, by the name
+/// of their `Collision` variant. Block data reports don't include collision
+/// shapes, so these are listed separately, and blocks that aren't listed are
+/// full cubes.
+fn read_collisions(report: &Map<String, Value>) -> HashMap<String, &'static str> {
+	let lists = read_to_string("data/collisions.json")
+		.expect("Could not read data/collisions.json.");
+	let lists: Map<String, Value> = serde_json::from_str(&lists)
+		.expect("data/collisions.json is not valid JSON.");
+
+	let mut collisions = HashMap::new();
+	[("passable", "None"), ("climbable", "Climbable"), ("partial", "Partial")].iter()
+		.for_each(|(list, collision)| lists.get(*list).and_then(Value::as_array)
+			.unwrap_or_else(|| panic!("data/collisions.json has no {} list.", list))
+			.iter()
+			.map(|name| name.as_str().expect("Block names must be strings."))
+			.for_each(|name| {
+				if !report.contains_key(name) {
+					panic!("{} isn't in the block report.", name)
+				}
+				if collisions.insert(name.to_owned(), *collision).is_some() {
+					panic!("{} is listed twice in data/collisions.json.", name)
+				}
+			}));
+	collisions
+}
+
+struct ReportBlock {
+	name: String,
+	properties: Vec<(String, Vec<String>)>,
+	base: u32,
+	default: u32
+}
+
+impl ReportBlock {
+	fn count(&self) -> u32 {
+		self.properties.iter().map(|(_, values)| values.len() as u32).product()
+	}
+}
+
+fn parse_block(name: &str, block: &Value) -> ReportBlock {
+	let mut properties = block.get("properties")
+		.and_then(Value::as_object)
+		.map(|properties| properties.iter()
+			.map(|(property, values)| (property.clone(), values.as_array()
+				.expect("Property values must be a list.").iter()
+				.map(|value| value.as_str()
+					.expect("Property values must be strings.").to_owned())
+				.collect()))
+			.collect::<Vec<(String, Vec<String>)>>())
+		.unwrap_or_else(Vec::new);
+	properties.sort_by(|a, b| a.0.cmp(&b.0));
+
+	let states = block.get("states").and_then(Value::as_array)
+		.unwrap_or_else(|| panic!("{} has no states.", name));
+	let id = |state: &Value| state.get("id").and_then(Value::as_u64)
+		.unwrap_or_else(|| panic!("A state of {} has no id.", name)) as u32;
+
+	let base = states.iter().map(id).min()
+		.unwrap_or_else(|| panic!("{} has no states.", name));
+	let default = states.iter()
+		.find(|state| state.get("default").and_then(Value::as_bool) == Some(true))
+		.map(id).unwrap_or(base);
+
+	let block = ReportBlock {name: name.to_owned(), properties, base, default};
+	if states.len() as u32 != block.count() {
+		panic!("{} lists {} states, but it's properties make {}.",
+			name, states.len(), block.count())
+	}
+
+	states.iter().for_each(|state| {
+		let offset = block.properties.iter().fold(0, |offset, (property, values)| {
+			let value = state.get("properties").and_then(|values| values.get(property))
+				.and_then(Value::as_str)
+				.unwrap_or_else(|| panic!("A state of {} is missing {}.", name, property));
+			let index = values.iter().position(|other| other == value)
+				.unwrap_or_else(|| panic!("{} is not a value of {}.", value, property));
+			offset * values.len() as u32 + index as u32
+		});
+
+		if base + offset != id(state) {
+			panic!("States of {} are not in vanilla order.", name)
+		}
+	});
+
+	block
+}
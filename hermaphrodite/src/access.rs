@@ -0,0 +1,460 @@
+use md5::compute as md5;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{
+	convert::TryInto, error::Error, fmt::{Display, Formatter, Result as FMTResult},
+	fs::{read_to_string, write}, io::{Error as IOError, ErrorKind}, net::IpAddr,
+	path::PathBuf, time::{Duration, SystemTime, UNIX_EPOCH}
+};
+
+pub const WHITELIST_FILE: &str = "whitelist.json";
+pub const OPS_FILE: &str = "ops.json";
+pub const BANNED_PLAYERS_FILE: &str = "banned-players.json";
+pub const BANNED_IPS_FILE: &str = "banned-ips.json";
+
+/// What players are shown when they aren't on the whitelist.
+pub const NOT_WHITELISTED: &str = "You are not white-listed on this server!";
+
+/// Who may join the server, and who runs it, kept in the same JSON files as
+/// vanilla. Players are told apart by name, ignoring case, since they all join
+/// in offline mode, but entries still carry the offline UUID vanilla would
+/// write.
+#[derive(Clone, Debug, Default)]
+pub struct AccessLists {
+	/// The directory the lists are kept in.
+	directory: PathBuf,
+	whitelist: Vec<ListedPlayer>,
+	ops: Vec<Operator>,
+	banned_players: Vec<PlayerBan>,
+	banned_ips: Vec<IpBan>
+}
+
+impl AccessLists {
+	/// Reads the lists from `directory`. Lists that don't exist yet are empty,
+	/// and are created once they're changed.
+	pub fn load(directory: impl Into<PathBuf>) -> Result<Self, AccessError> {
+		let mut lists = Self {directory: directory.into(), ..Self::default()};
+		lists.whitelist = lists.read(WHITELIST_FILE)?;
+		lists.ops = lists.read(OPS_FILE)?;
+		lists.banned_players = lists.read(BANNED_PLAYERS_FILE)?;
+		lists.banned_ips = lists.read(BANNED_IPS_FILE)?;
+		Ok(lists)
+	}
+
+	/// Reads the lists again, keeping the old ones if any can't be read.
+	pub fn reload(&mut self) -> Result<(), AccessError> {
+		*self = Self::load(self.directory.clone())?;
+		Ok(())
+	}
+
+	/// Why a player can't join, if they can't. Players on the whitelist and
+	/// operators may join while the whitelist is on.
+	pub fn denial(&self, name: &str, address: Option<IpAddr>, whitelist: bool)
+			-> Option<String> {
+		if let Some(ban) = self.player_ban(name) {
+			return Some(ban.message("You are"))
+		}
+		if let Some(ban) = address.and_then(|address| self.ip_ban(address)) {
+			return Some(ban.message("Your IP address is"))
+		}
+		match whitelist && !self.whitelisted(name) && self.operator(name).is_none() {
+			true => Some(NOT_WHITELISTED.to_owned()),
+			false => None
+		}
+	}
+
+	pub fn whitelisted(&self, name: &str) -> bool {
+		self.whitelist.iter().any(|player| player.is(name))
+	}
+
+	/// The names on the whitelist, in the order they were added.
+	pub fn whitelist(&self) -> Vec<&str> {
+		self.whitelist.iter().map(|player| player.name.as_str()).collect()
+	}
+
+	/// Adds a player to the whitelist, returning whether they weren't already.
+	pub fn whitelist_add(&mut self, name: &str) -> Result<bool, AccessError> {
+		if self.whitelisted(name) {return Ok(false)}
+		self.whitelist.push(ListedPlayer::new(name));
+		self.write(WHITELIST_FILE, &self.whitelist).map(|_| true)
+	}
+
+	/// Removes a player from the whitelist, returning whether they were on it.
+	pub fn whitelist_remove(&mut self, name: &str) -> Result<bool, AccessError> {
+		let count = self.whitelist.len();
+		self.whitelist.retain(|player| !player.is(name));
+		if self.whitelist.len() == count {return Ok(false)}
+		self.write(WHITELIST_FILE, &self.whitelist).map(|_| true)
+	}
+
+	pub fn operator(&self, name: &str) -> Option<&Operator> {
+		self.ops.iter().find(|operator| operator.is(name))
+	}
+
+	/// Makes a player an operator with permission `level`, returning whether
+	/// they weren't one already.
+	pub fn op(&mut self, name: &str, level: u8) -> Result<bool, AccessError> {
+		if self.operator(name).is_some() {return Ok(false)}
+		let ListedPlayer {uuid, name} = ListedPlayer::new(name);
+		self.ops.push(Operator {uuid, name, level, bypasses_player_limit: false});
+		self.write(OPS_FILE, &self.ops).map(|_| true)
+	}
+
+	/// Stops a player being an operator, returning whether they were one.
+	pub fn deop(&mut self, name: &str) -> Result<bool, AccessError> {
+		let count = self.ops.len();
+		self.ops.retain(|operator| !operator.is(name));
+		if self.ops.len() == count {return Ok(false)}
+		self.write(OPS_FILE, &self.ops).map(|_| true)
+	}
+
+	/// The ban keeping a player out, unless they aren't banned or it's expired.
+	pub fn player_ban(&self, name: &str) -> Option<&Ban> {
+		self.banned_players.iter()
+			.find(|banned| banned.name.eq_ignore_ascii_case(name))
+			.map(|banned| &banned.ban)
+			.filter(|ban| ban.active())
+	}
+
+	/// The ban keeping an address out, unless it isn't banned or it's expired.
+	pub fn ip_ban(&self, address: IpAddr) -> Option<&Ban> {
+		self.banned_ips.iter()
+			.find(|banned| banned.ip == address)
+			.map(|banned| &banned.ban)
+			.filter(|ban| ban.active())
+	}
+
+	/// Every banned player and why, expired bans included.
+	pub fn banned_players(&self) -> Vec<(&str, &Ban)> {
+		self.banned_players.iter()
+			.map(|banned| (banned.name.as_str(), &banned.ban))
+			.collect()
+	}
+
+	/// Every banned address and why, expired bans included.
+	pub fn banned_ips(&self) -> Vec<(IpAddr, &Ban)> {
+		self.banned_ips.iter().map(|banned| (banned.ip, &banned.ban)).collect()
+	}
+
+	/// Bans a player, replacing any ban they already had.
+	pub fn ban(&mut self, name: &str, ban: Ban) -> Result<(), AccessError> {
+		self.banned_players.retain(|banned| !banned.name.eq_ignore_ascii_case(name));
+		let ListedPlayer {uuid, name} = ListedPlayer::new(name);
+		self.banned_players.push(PlayerBan {uuid, name, ban});
+		self.write(BANNED_PLAYERS_FILE, &self.banned_players)
+	}
+
+	/// Lifts a player's ban, returning whether they were banned.
+	pub fn pardon(&mut self, name: &str) -> Result<bool, AccessError> {
+		let count = self.banned_players.len();
+		self.banned_players.retain(|banned| !banned.name.eq_ignore_ascii_case(name));
+		if self.banned_players.len() == count {return Ok(false)}
+		self.write(BANNED_PLAYERS_FILE, &self.banned_players).map(|_| true)
+	}
+
+	/// Bans an address, replacing any ban it already had.
+	pub fn ban_ip(&mut self, ip: IpAddr, ban: Ban) -> Result<(), AccessError> {
+		self.banned_ips.retain(|banned| banned.ip != ip);
+		self.banned_ips.push(IpBan {ip, ban});
+		self.write(BANNED_IPS_FILE, &self.banned_ips)
+	}
+
+	/// Lifts an address' ban, returning whether it was banned.
+	pub fn pardon_ip(&mut self, ip: IpAddr) -> Result<bool, AccessError> {
+		let count = self.banned_ips.len();
+		self.banned_ips.retain(|banned| banned.ip != ip);
+		if self.banned_ips.len() == count {return Ok(false)}
+		self.write(BANNED_IPS_FILE, &self.banned_ips).map(|_| true)
+	}
+
+	fn read<T>(&self, file: &'static str) -> Result<Vec<T>, AccessError>
+			where T: DeserializeOwned {
+		match read_to_string(self.directory.join(file)) {
+			Ok(contents) if contents.trim().is_empty() => Ok(Vec::new()),
+			Ok(contents) => serde_json::from_str(&contents)
+				.map_err(|error| AccessError::Malformed(file, error)),
+			Err(error) if error.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+			Err(error) => Err(AccessError::Io(file, error))
+		}
+	}
+
+	fn write<T>(&self, file: &'static str, list: &[T]) -> Result<(), AccessError>
+			where T: Serialize {
+		let contents = serde_json::to_string_pretty(list)
+			.map_err(|error| AccessError::Malformed(file, error))?;
+		write(self.directory.join(file), contents)
+			.map_err(|error| AccessError::Io(file, error))
+	}
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ListedPlayer {
+	pub uuid: String,
+	pub name: String
+}
+
+impl ListedPlayer {
+	fn new(name: &str) -> Self {
+		Self {uuid: format_uuid(offline_uuid(name)), name: name.to_owned()}
+	}
+
+	fn is(&self, name: &str) -> bool {
+		self.name.eq_ignore_ascii_case(name)
+	}
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Operator {
+	pub uuid: String,
+	pub name: String,
+	/// The permission level the operator runs commands with.
+	pub level: u8,
+	/// Whether the operator may join while the server is full.
+	#[serde(default)]
+	pub bypasses_player_limit: bool
+}
+
+impl Operator {
+	fn is(&self, name: &str) -> bool {
+		self.name.eq_ignore_ascii_case(name)
+	}
+}
+
+/// When, why and by whom someone was banned.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Ban {
+	#[serde(with = "date")]
+	pub created: SystemTime,
+	/// Who banned them, which is `Server` for the console.
+	pub source: String,
+	/// When the ban ends, or `None` if it's forever.
+	#[serde(with = "expiry")]
+	pub expires: Option<SystemTime>,
+	pub reason: String
+}
+
+impl Ban {
+	/// A ban starting now, lasting for `duration`, or forever if it's `None`.
+	pub fn new(source: &str, reason: &str, duration: Option<Duration>) -> Self {
+		let now = SystemTime::now();
+		Self {
+			created: now,
+			source: source.to_owned(),
+			expires: duration.and_then(|duration| now.checked_add(duration)),
+			reason: reason.to_owned()
+		}
+	}
+
+	/// Whether the ban hasn't expired yet.
+	pub fn active(&self) -> bool {
+		self.expires.map_or(true, |expires| expires > SystemTime::now())
+	}
+
+	/// What whoever's banned is shown when they try to join, where `subject`
+	/// says who's banned.
+	fn message(&self, subject: &str) -> String {
+		let mut message = format!("{} banned from this server.\nReason: {}",
+			subject, self.reason);
+		if let Some(expires) = self.expires {
+			message.push_str(&format!("\nYour ban will be removed on {}",
+				format_date(expires)))
+		}
+		message
+	}
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+struct PlayerBan {
+	uuid: String,
+	name: String,
+	#[serde(flatten)]
+	ban: Ban
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+struct IpBan {
+	ip: IpAddr,
+	#[serde(flatten)]
+	ban: Ban
+}
+
+mod date {
+	use serde::{Deserialize, Deserializer, Serializer, de::Error};
+	use std::time::SystemTime;
+
+	pub fn serialize<S>(date: &SystemTime, serializer: S)
+			-> Result<S::Ok, S::Error> where S: Serializer {
+		serializer.serialize_str(&self::super::format_date(*date))
+	}
+
+	pub fn deserialize<'d, D>(deserializer: D) -> Result<SystemTime, D::Error>
+			where D: Deserializer<'d> {
+		let date = String::deserialize(deserializer)?;
+		self::super::parse_date(&date)
+			.ok_or_else(|| D::Error::custom(format!("invalid date {:?}", date)))
+	}
+}
+
+/// Expiry dates, which are `forever` for bans that don't expire.
+mod expiry {
+	use serde::{Deserialize, Deserializer, Serializer, de::Error};
+	use std::time::SystemTime;
+
+	pub fn serialize<S>(date: &Option<SystemTime>, serializer: S)
+			-> Result<S::Ok, S::Error> where S: Serializer {
+		match date {
+			Some(date) => self::super::date::serialize(date, serializer),
+			None => serializer.serialize_str("forever")
+		}
+	}
+
+	pub fn deserialize<'d, D>(deserializer: D)
+			-> Result<Option<SystemTime>, D::Error> where D: Deserializer<'d> {
+		let date = String::deserialize(deserializer)?;
+		match date.as_str() {
+			"forever" => Ok(None),
+			date => self::super::parse_date(date).map(Some)
+				.ok_or_else(|| D::Error::custom(format!("invalid date {:?}", date)))
+		}
+	}
+}
+
+/// Writes a date like vanilla does in it's lists, such as
+/// `2020-11-21 14:03:52 +0000`, always in UTC.
+fn format_date(date: SystemTime) -> String {
+	let seconds = date.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+	let (days, time) = ((seconds / 86400) as i64, seconds % 86400);
+
+	// Converts days since the epoch to a date in the Gregorian calendar, with
+	// eras of 400 years starting on the 1st of March.
+	let days = days + 719468;
+	let (era, day) = (days / 146097, days % 146097);
+	let year = (day - day / 1460 + day / 36524 - day / 146096) / 365;
+	let day = day - (365 * year + year / 4 - year / 100); // From the 1st of March.
+	let month = (5 * day + 2) / 153;
+	let day = day - (153 * month + 2) / 5 + 1;
+	let month = if month < 10 {month + 3} else {month - 9};
+	let year = year + era * 400 + (month <= 2) as i64;
+
+	format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} +0000", year, month, day,
+		time / 3600, time / 60 % 60, time % 60)
+}
+
+/// Reads a date written like `2020-11-21 14:03:52 +0000`, in any time zone.
+/// Years past 9999 aren't accepted, so that nothing can overflow.
+fn parse_date(date: &str) -> Option<SystemTime> {
+	let mut parts = date.split(' ');
+	let mut numbers = |separator| parts.next().map(|part: &str| part.split(separator)
+		.map(str::parse::<i64>).collect::<Result<Vec<_>, _>>());
+	let (date, time) = (numbers('-')?.ok()?, numbers(':')?.ok()?);
+	let offset = parts.next()?;
+	let (year, month, day) = match date[..] {
+		[year @ 0..=9999, month @ 1..=12, day @ 1..=31] => (year, month, day),
+		_ => return None
+	};
+	let (hour, minute, second) = match time[..] {
+		[hour @ 0..=23, minute @ 0..=59, second @ 0..=60] => (hour, minute, second),
+		_ => return None
+	};
+	let offset = match (offset.get(..1)?, offset[1..].parse::<i64>().ok()?) {
+		(_, offset) if !(0..=2359).contains(&offset) => return None,
+		("+", offset) => offset / 100 * 3600 + offset % 100 * 60,
+		("-", offset) => -(offset / 100 * 3600 + offset % 100 * 60),
+		_ => return None
+	};
+
+	// The reverse of `format_date`.
+	let year = year - (month <= 2) as i64;
+	let (era, year) = (year.div_euclid(400), year.rem_euclid(400));
+	let day = (153 * (month + if month > 2 {-3} else {9}) + 2) / 5 + day - 1;
+	let day = year * 365 + year / 4 - year / 100 + day;
+	let days = era.checked_mul(146097)?.checked_add(day - 719468)?;
+
+	let seconds = days.checked_mul(86400)?
+		.checked_add(hour * 3600 + minute * 60 + second - offset)?;
+	UNIX_EPOCH.checked_add(Duration::from_secs(seconds.try_into().ok()?))
+}
+
+/// Reads how long a temporary ban lasts, written as a number followed by `s`,
+/// `m`, `h`, `d` or `w`, like `30m` or `7d`.
+pub fn parse_duration(duration: &str) -> Option<Duration> {
+	let unit = duration.chars().last()?;
+	let amount: u64 = duration[..duration.len() - unit.len_utf8()].parse().ok()
+		.filter(|amount| *amount > 0)?;
+	let unit = match unit {
+		's' => 1,
+		'm' => 60,
+		'h' => 60 * 60,
+		'd' => 24 * 60 * 60,
+		'w' => 7 * 24 * 60 * 60,
+		_ => return None
+	};
+	amount.checked_mul(unit).map(Duration::from_secs)
+}
+
+/// The UUID a vanilla server in offline mode gives a player, a version 3 UUID
+/// of `OfflinePlayer:<username>`.
+pub fn offline_uuid(username: &str) -> u128 {
+	let mut bytes = md5(format!("OfflinePlayer:{}", username)).0;
+	bytes[6] = bytes[6] & 0x0F | 0x30;
+	bytes[8] = bytes[8] & 0x3F | 0x80;
+	u128::from_be_bytes(bytes)
+}
+
+/// Writes a UUID with hyphens, like `069a79f4-44e9-4726-a5be-fca90e38aaf5`.
+fn format_uuid(uuid: u128) -> String {
+	let hex = format!("{:032x}", uuid);
+	format!("{}-{}-{}-{}-{}",
+		&hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+#[derive(Debug)]
+pub enum AccessError {
+	Io(&'static str, IOError),
+	Malformed(&'static str, serde_json::Error)
+}
+
+impl Display for AccessError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Io(file, error) => write!(f, "Couldn't access {}: {}", file, error),
+			Self::Malformed(file, error) => write!(f, "{} is malformed: {}", file, error)
+		}
+	}
+}
+
+impl Error for AccessError {}
+
+#[cfg(test)]
+mod tests {
+	use self::super::*;
+	use std::{env::temp_dir, fs::{create_dir_all, remove_dir_all}};
+
+	#[test]
+	fn dates_survive_a_round_trip() {
+		let date = UNIX_EPOCH + Duration::from_secs(1605967432);
+		assert_eq!(format_date(date), "2020-11-21 14:03:52 +0000");
+		assert_eq!(parse_date("2020-11-21 14:03:52 +0000"), Some(date));
+		assert_eq!(parse_date("2020-11-21 15:33:52 +0130"), Some(date));
+		assert_eq!(parse_date("9999-12-31 23:59:59 -2359").map(format_date),
+			Some("10000-01-01 23:58:59 +0000".to_owned()));
+	}
+
+	#[test]
+	fn out_of_range_dates_are_malformed() {
+		assert_eq!(parse_date("10000-01-01 00:00:00 +0000"), None);
+		assert_eq!(parse_date("-1-01-01 00:00:00 +0000"), None);
+		assert_eq!(parse_date("9223372036854775807-01-01 00:00:00 +0000"), None);
+		assert_eq!(parse_date("2020-11-21 14:03:52 +9223372036854775807"), None);
+
+		let directory = temp_dir().join("hermaphrodite-access-dates");
+		create_dir_all(&directory).unwrap();
+		write(directory.join(BANNED_PLAYERS_FILE), r#"[{
+			"uuid": "", "name": "Notch", "created": "99999-01-01 00:00:00 +0000",
+			"source": "Server", "expires": "forever", "reason": "Banned"
+		}]"#).unwrap();
+		let lists = AccessLists::load(&directory);
+		remove_dir_all(&directory).unwrap();
+		assert!(matches!(lists, Err(AccessError::Malformed(BANNED_PLAYERS_FILE, _))));
+	}
+}
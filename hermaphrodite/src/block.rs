@@ -0,0 +1,260 @@
+use std::{
+	error::Error, fmt::{Display, Formatter, Result as FMTResult}, str::FromStr
+};
+
+include!(concat!(env!("OUT_DIR"), "/blocks.rs"));
+
+/// A kind of block, and all the properties it's states are made of.
+///
+/// Blocks are generated at build time from `data/blocks.json`, which is a
+/// vanilla block data report. Only blocks listed there are known to the server.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Block {
+	pub name: &'static str,
+	pub properties: &'static [(&'static str, &'static [&'static str])],
+	pub base: u32,
+	pub default: u32,
+	pub collision: Collision
+}
+
+impl Block {
+	/// Looks up a block by it's namespaced name. The namespace may be omitted.
+	pub fn by_name(name: &str) -> Option<&'static Self> {
+		let name = match name.contains(':') {
+			true => name.to_owned(),
+			false => format!("minecraft:{}", name)
+		};
+
+		BLOCKS_BY_NAME
+			.binary_search_by(|index| BLOCKS[*index].name.cmp(&name.as_str()))
+			.ok().map(|index| &BLOCKS[BLOCKS_BY_NAME[index]])
+	}
+
+	/// The amount of states this block has.
+	pub fn state_count(&self) -> u32 {
+		self.properties.iter().map(|(_, values)| values.len() as u32).product()
+	}
+
+	pub fn default_state(&self) -> BlockState {
+		BlockState(self.default)
+	}
+
+	/// How far apart two states differing only by one value of `property` are.
+	fn stride(&self, property: usize) -> u32 {
+		self.properties[property + 1..].iter()
+			.map(|(_, values)| values.len() as u32).product()
+	}
+}
+
+/// How a block gets in the way of players moving through it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Collision {
+	/// Nothing stops players, like air or flowers.
+	None,
+	/// Nothing stops players, and they can swim or climb up it, like water or
+	/// ladders.
+	Climbable,
+	/// Only part of the block is solid, like stairs or fences.
+	Partial,
+	/// The whole block is solid.
+	Full
+}
+
+/// A block, along with values for all of it's properties, identified by it's
+/// global state identifier.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct BlockState(u32);
+
+impl BlockState {
+	pub const AIR: Self = Self(0);
+
+	/// Checks that `id` is a known state identifier.
+	pub fn from_id(id: u32) -> Option<Self> {
+		match id < STATE_COUNT {
+			true => Some(Self(id)),
+			false => None
+		}
+	}
+
+	/// Returns the default state of the block with the namespaced `name`.
+	pub fn default_of(name: &str) -> Option<Self> {
+		Block::by_name(name).map(Block::default_state)
+	}
+
+	pub fn id(&self) -> u32 {
+		self.0
+	}
+
+	/// The block this is a state of. States are only ever made from known
+	/// identifiers, and the registry has no gaps, so there always is one.
+	pub fn block(&self) -> &'static Block {
+		let index = match BLOCKS.binary_search_by_key(&self.0, |block| block.base) {
+			Ok(index) => index,
+			Err(index) => index - 1
+		};
+		&BLOCKS[index]
+	}
+
+	/// How this state gets in the way of players. Players swim in waterlogged
+	/// blocks, so those that don't stop them are climbable.
+	pub fn collision(&self) -> Collision {
+		match self.block().collision {
+			Collision::None if self.get("waterlogged") == Some("true") =>
+				Collision::Climbable,
+			collision => collision
+		}
+	}
+
+	/// Returns the value of `property`, as written in the data report.
+	pub fn get(&self, property: &str) -> Option<&'static str> {
+		let block = self.block();
+		let index = block.properties.iter().position(|(name, _)| *name == property)?;
+		let values = block.properties[index].1;
+		let value = (self.0 - block.base) / block.stride(index) % values.len() as u32;
+		Some(values[value as usize])
+	}
+
+	/// Returns the value of `property`, parsed into a type such as `bool`, `u8`
+	/// or `Facing`.
+	pub fn property<T>(&self, property: &str) -> Option<T>
+			where T: FromStr {
+		self.get(property)?.parse().ok()
+	}
+
+	/// Returns this state with `property` changed to `value`, or `None` if this
+	/// block doesn't have that property or value.
+	pub fn with(&self, property: &str, value: &str) -> Option<Self> {
+		let block = self.block();
+		let index = block.properties.iter().position(|(name, _)| *name == property)?;
+		let values = block.properties[index].1;
+		let new = values.iter().position(|other| *other == value)? as u32;
+		let stride = block.stride(index);
+		let old = (self.0 - block.base) / stride % values.len() as u32;
+		Some(Self(self.0 - old * stride + new * stride))
+	}
+
+	/// Returns all of this state's properties and their values.
+	pub fn properties(&self) -> Vec<(&'static str, &'static str)> {
+		self.block().properties.iter()
+			.map(|(name, _)| (*name, self.get(name).unwrap()))
+			.collect()
+	}
+}
+
+impl Display for BlockState {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		write!(f, "{}", self.block().name)?;
+
+		let properties = self.properties();
+		if !properties.is_empty() {
+			let properties = properties.iter()
+				.map(|(name, value)| format!("{}={}", name, value))
+				.collect::<Vec<_>>();
+			write!(f, "[{}]", properties.join(","))?;
+		}
+		Ok(())
+	}
+}
+
+impl FromStr for BlockState {
+	type Err = BlockStateError;
+
+	/// Parses a block state such as `minecraft:oak_stairs[facing=north,half=top]`.
+	/// Properties that aren't specified take their default value.
+	fn from_str(string: &str) -> Result<Self, BlockStateError> {
+		let (name, properties) = match string.find('[') {
+			Some(index) if string.ends_with(']') =>
+				(&string[..index], &string[index + 1..string.len() - 1]),
+			Some(_) => return Err(BlockStateError::Malformed(string.into())),
+			None => (string, "")
+		};
+
+		let name = name.trim();
+		let block = Block::by_name(name)
+			.ok_or_else(|| BlockStateError::UnknownBlock(name.into()))?;
+
+		properties.split(',')
+			.map(str::trim)
+			.filter(|property| !property.is_empty())
+			.try_fold(block.default_state(), |state, property| {
+				let mut parts = property.splitn(2, '=');
+				let (name, value) = match (parts.next(), parts.next()) {
+					(Some(name), Some(value)) => (name.trim(), value.trim()),
+					_ => return Err(BlockStateError::Malformed(property.into()))
+				};
+
+				state.with(name, value).ok_or_else(|| BlockStateError::UnknownProperty(
+					block.name, format!("{}={}", name, value).into_boxed_str()))
+			})
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BlockStateError {
+	Malformed(Box<str>),
+	UnknownBlock(Box<str>),
+	UnknownProperty(&'static str, Box<str>)
+}
+
+impl Display for BlockStateError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Malformed(string) => write!(f, "Malformed block state {:?}.", string),
+			Self::UnknownBlock(block) => write!(f, "Unknown block {:?}.", block),
+			Self::UnknownProperty(block, property) =>
+				write!(f, "{} has no property {:?}.", block, property)
+		}
+	}
+}
+
+impl Error for BlockStateError {}
+
+/// The value of `facing` properties, for typed access through
+/// `BlockState::property`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Facing {
+	North,
+	South,
+	West,
+	East,
+	Up,
+	Down
+}
+
+impl FromStr for Facing {
+	type Err = ();
+
+	fn from_str(string: &str) -> Result<Self, ()> {
+		Ok(match string {
+			"north" => Self::North,
+			"south" => Self::South,
+			"west" => Self::West,
+			"east" => Self::East,
+			"up" => Self::Up,
+			"down" => Self::Down,
+			_ => return Err(())
+		})
+	}
+}
+
+/// The value of `axis` properties, for typed access through
+/// `BlockState::property`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Axis {
+	X,
+	Y,
+	Z
+}
+
+impl FromStr for Axis {
+	type Err = ();
+
+	fn from_str(string: &str) -> Result<Self, ()> {
+		Ok(match string {
+			"x" => Self::X,
+			"y" => Self::Y,
+			"z" => Self::Z,
+			_ => return Err(())
+		})
+	}
+}
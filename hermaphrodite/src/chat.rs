@@ -0,0 +1,59 @@
+use std::{error::Error, fmt::{Display, Formatter, Result as FMTResult}};
+
+/// The longest chat message a player may send, in characters.
+pub const MAX_MESSAGE_LENGTH: usize = 256;
+
+/// Where on a player's screen a message shows up.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ChatPosition {
+	/// The chat box, as something a player said.
+	Chat,
+	/// The chat box, as something the server said.
+	System,
+	/// Above the hotbar.
+	ActionBar
+}
+
+/// Checks that a message a player sent is something they could have typed,
+/// returning it without surrounding whitespace.
+pub fn validate(message: &str) -> Result<&str, ChatError> {
+	let message = message.trim();
+	if message.is_empty() {return Err(ChatError::Empty)}
+
+	let length = message.chars().count();
+	if length > MAX_MESSAGE_LENGTH {return Err(ChatError::TooLong(length))}
+
+	// Formatting codes and control characters can't be typed in vanilla.
+	match message.chars().find(|character| *character == '§'
+			|| character.is_control()) {
+		Some(character) => Err(ChatError::IllegalCharacter(character)),
+		None => Ok(message)
+	}
+}
+
+/// Formats a message a player sent the way vanilla does.
+pub fn format(username: &str, message: &str) -> String {
+	format!("<{}> {}", username, message)
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ChatError {
+	Empty,
+	TooLong(usize),
+	IllegalCharacter(char)
+}
+
+impl Display for ChatError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Empty => write!(f, "Chat messages can't be empty."),
+			Self::TooLong(length) => write!(f,
+				"Chat messages can't be longer than {} characters, not {}.",
+				MAX_MESSAGE_LENGTH, length),
+			Self::IllegalCharacter(character) =>
+				write!(f, "Illegal character in chat {:?}.", character)
+		}
+	}
+}
+
+impl Error for ChatError {}
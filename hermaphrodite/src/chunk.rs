@@ -0,0 +1,158 @@
+use self::super::{
+	block::BlockState,
+	generator::{BIOMES, SECTION_COUNT, GeneratedChunk},
+	palette::PalettedContainer,
+	position::{BlockPos, ChunkPos}
+};
+use std::{borrow::Borrow, hash::{Hash, Hasher}};
+
+/// A full height column of 16x16 blocks, made of sections stacked on top of
+/// each other.
+#[derive(Clone, Debug)]
+pub struct Chunk {
+	sections: Vec<Option<PalettedContainer>>,
+	biomes: PalettedContainer,
+	diff_source: DiffSource,
+	position: ChunkPos
+}
+
+impl Chunk {
+	/// Creates a chunk made entirely out of one block.
+	pub fn solid(position: ChunkPos, identifier: u16) -> Self {
+		let sections = (0..SECTION_COUNT)
+			.map(|_| match identifier {
+				0 => None,
+				_ => Some(PalettedContainer::blocks(identifier as u32))
+			})
+			.collect();
+
+		Self {
+			sections,
+			biomes: PalettedContainer::biomes(0, biome_bits()),
+			diff_source: DiffSource::Solid(identifier),
+			position
+		}
+	}
+
+	pub fn generated(chunk: GeneratedChunk, seed: Option<u64>) -> Self {
+		let sections = chunk.sections.into_iter()
+			.map(|section| section.map(|section| {
+				let mut container = PalettedContainer::blocks(0);
+				section.iter().enumerate()
+					.for_each(|(index, block)| {container.set(index, *block as u32);});
+				container
+			}))
+			.collect::<Vec<_>>();
+
+		Self {
+			sections,
+			biomes: PalettedContainer::from_values(&chunk.biomes, 1, 3, biome_bits()),
+			diff_source: DiffSource::Generator(seed),
+			position: chunk.position
+		}
+	}
+
+	pub fn position(&self) -> ChunkPos {
+		self.position
+	}
+
+	pub fn diff_source(&self) -> &DiffSource {
+		&self.diff_source
+	}
+
+	/// All sections of this chunk, from bottom to top. Sections that are
+	/// completely air are `None`.
+	pub fn sections(&self) -> &[Option<PalettedContainer>] {
+		&self.sections
+	}
+
+	pub fn biomes(&self) -> &PalettedContainer {
+		&self.biomes
+	}
+
+	/// A bit mask of which sections aren't completely air, where the lowest bit
+	/// is the bottom section.
+	pub fn layer_mask(&self) -> u16 {
+		self.sections.iter().enumerate()
+			.filter(|(_, section)| section.is_some())
+			.fold(0, |mask, (index, _)| mask | 1 << index)
+	}
+
+	/// Returns the block at `position`, which is in world coordinates, but must
+	/// be inside this chunk.
+	pub fn block(&self, position: BlockPos) -> BlockState {
+		let section = match position.y >> 4 {
+			y if y < 0 => return BlockState::AIR,
+			y => self.sections.get(y as usize)
+		};
+
+		match section {
+			Some(Some(section)) => BlockState::from_id(
+				section.get(position.section_index())).unwrap_or(BlockState::AIR),
+			_ => BlockState::AIR
+		}
+	}
+
+	/// Changes the block at `position`, which must be inside this chunk,
+	/// returning the block that was there before.
+	pub fn set_block(&mut self, position: BlockPos, state: BlockState)
+			-> BlockState {
+		let y = match position.y >> 4 {
+			y if y < 0 || y as usize >= SECTION_COUNT => return BlockState::AIR,
+			y => y as usize
+		};
+
+		let section = self.sections[y]
+			.get_or_insert_with(|| PalettedContainer::blocks(0));
+		let old = section.set(position.section_index(), state.id());
+		if section.count_not(0) == 0 {self.sections[y] = None}
+
+		BlockState::from_id(old).unwrap_or(BlockState::AIR)
+	}
+
+	/// Returns one above the highest non-air block of every column, indexed by
+	/// `z << 4 | x`. Empty columns have a height of zero.
+	pub fn heights(&self) -> Vec<u16> {
+		(0..256).map(|column| {
+			self.sections.iter().enumerate().rev()
+				.filter_map(|(y, section)| section.as_ref().map(|section| (y, section)))
+				.find_map(|(y, section)| (0..16).rev()
+					.find(|height| section.get(height << 8 | column) != 0)
+					.map(|height| (y * 16 + height + 1) as u16))
+				.unwrap_or(0)
+		}).collect()
+	}
+}
+
+/// The amount of bits needed to address every biome directly.
+fn biome_bits() -> u8 {
+	(BIOMES.len() as f32).log2().ceil().max(1.) as u8
+}
+
+impl Hash for Chunk {
+	fn hash<H>(&self, hasher: &mut H)
+			where H: Hasher {
+		self.position.hash(hasher)
+	}
+}
+
+impl PartialEq for Chunk {
+	fn eq(&self, other: &Self) -> bool {
+		self.position == other.position
+	}
+}
+
+impl Eq for Chunk {}
+
+impl Borrow<ChunkPos> for Chunk {
+	fn borrow(&self) -> &ChunkPos {
+		&self.position
+	}
+}
+
+/// Where the unmodified contents of a chunk came from.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DiffSource {
+	Generator(Option<u64>),
+	Solid(u16)
+}
@@ -0,0 +1,684 @@
+use self::super::position::{BlockPos, EntityPos};
+use std::{
+	error::Error, fmt::{Display, Formatter, Result as FMTResult}, str::FromStr,
+	sync::Arc
+};
+
+/// The permission level the console, and anything else that may run any
+/// command, has.
+pub const MAX_PERMISSION_LEVEL: u8 = 4;
+
+/// Runs a command once it's been parsed, returning an error to show whoever
+/// ran it if it failed.
+pub type Executor<S> =
+	Arc<dyn Fn(&S, &CommandContext) -> Result<(), CommandError> + Send + Sync>;
+
+/// What a source needs to use part of a command.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Requirement {
+	pub level: u8,
+	/// The permission node that, if it's decided for the source, replaces the
+	/// permission level.
+	pub permission: Option<Box<str>>
+}
+
+/// Whoever ran a command.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum CommandSource {
+	Console,
+	Player(Box<str>),
+	/// A console connected over the network, such as by RCON, told apart by
+	/// it's connection.
+	Remote(u32)
+}
+
+/// A command that was parsed, ready to be executed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandContext {
+	pub source: CommandSource,
+	/// Where the source was when it ran the command, if anywhere.
+	pub position: Option<EntityPos>,
+	pub arguments: Vec<(Box<str>, Argument)>
+}
+
+impl CommandContext {
+	pub fn get(&self, name: &str) -> Option<&Argument> {
+		self.arguments.iter()
+			.find(|(argument, _)| &**argument == name)
+			.map(|(_, value)| value)
+	}
+
+	pub fn bool(&self, name: &str) -> Option<bool> {
+		match self.get(name)? {
+			Argument::Bool(value) => Some(*value),
+			_ => None
+		}
+	}
+
+	pub fn integer(&self, name: &str) -> Option<i32> {
+		match self.get(name)? {
+			Argument::Integer(value) => Some(*value),
+			_ => None
+		}
+	}
+
+	pub fn double(&self, name: &str) -> Option<f64> {
+		match self.get(name)? {
+			Argument::Double(value) => Some(*value),
+			_ => None
+		}
+	}
+
+	/// Returns the value of a string or message argument.
+	pub fn string(&self, name: &str) -> Option<&str> {
+		match self.get(name)? {
+			Argument::String(value) | Argument::Message(value) => Some(value),
+			_ => None
+		}
+	}
+
+	pub fn entity(&self, name: &str) -> Option<&Selector> {
+		match self.get(name)? {
+			Argument::Entity(value) => Some(value),
+			_ => None
+		}
+	}
+
+	/// Returns a block position argument, with relative coordinates resolved
+	/// against where the source is.
+	pub fn block_pos(&self, name: &str) -> Option<BlockPos> {
+		match self.get(name)? {
+			Argument::BlockPos(coordinates) =>
+				Some(resolve(coordinates, self.position.unwrap_or_default())),
+			_ => None
+		}
+	}
+}
+
+/// Turns coordinates relative to `origin` into a block position.
+fn resolve(coordinates: &[Coordinate; 3], origin: EntityPos) -> BlockPos {
+	let axis = |coordinate: &Coordinate, origin: f64| match coordinate {
+		Coordinate::Absolute(value) => *value,
+		Coordinate::Relative(value) => origin.floor() as i32 + value
+	};
+
+	BlockPos::new(axis(&coordinates[0], origin.x), axis(&coordinates[1], origin.y),
+		axis(&coordinates[2], origin.z))
+}
+
+/// How an argument is read from a command, mirroring Brigadier's parsers so
+/// that clients can check syntax by themselves.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Parser {
+	Bool,
+	Integer {
+		min: Option<i32>,
+		max: Option<i32>
+	},
+	Double {
+		min: Option<f64>,
+		max: Option<f64>
+	},
+	String(StringKind),
+	Entity {
+		/// Only one entity may be selected.
+		single: bool,
+		/// Only players may be selected.
+		players: bool
+	},
+	BlockPos,
+	/// The rest of the command, as is.
+	Message
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum StringKind {
+	/// A single word.
+	Word,
+	/// A single word, or a phrase in double quotes.
+	Quotable,
+	/// The rest of the command.
+	Greedy
+}
+
+impl Parser {
+	/// Reads an argument from the start of `input`, returning it along with
+	/// whatever's left after it.
+	pub fn parse<'i>(&self, input: &'i str)
+			-> Result<(Argument, &'i str), CommandError> {
+		let (word, rest) = match input.find(' ') {
+			Some(index) => (&input[..index], &input[index..]),
+			None => (input, "")
+		};
+		let invalid = |expected: &str|
+			Err(CommandError::InvalidArgument(word.into(), expected.into()));
+
+		match self {
+			Self::Bool => match word {
+				"true" => Ok((Argument::Bool(true), rest)),
+				"false" => Ok((Argument::Bool(false), rest)),
+				_ => invalid("true or false")
+			},
+			Self::Integer {min, max} => match word.parse::<i32>() {
+				Ok(value) if min.map(|min| value < min).unwrap_or(false) =>
+					invalid(&format!("an integer of at least {}", min.unwrap())),
+				Ok(value) if max.map(|max| value > max).unwrap_or(false) =>
+					invalid(&format!("an integer of at most {}", max.unwrap())),
+				Ok(value) => Ok((Argument::Integer(value), rest)),
+				Err(_) => invalid("an integer")
+			},
+			Self::Double {min, max} => match word.parse::<f64>() {
+				Ok(value) if min.map(|min| value < min).unwrap_or(false) =>
+					invalid(&format!("a number of at least {}", min.unwrap())),
+				Ok(value) if max.map(|max| value > max).unwrap_or(false) =>
+					invalid(&format!("a number of at most {}", max.unwrap())),
+				Ok(value) if value.is_finite() =>
+					Ok((Argument::Double(value), rest)),
+				_ => invalid("a number")
+			},
+			Self::String(StringKind::Word) | Self::String(StringKind::Quotable)
+					if !input.starts_with('"') => match word.is_empty() {
+				true => invalid("a word"),
+				false => Ok((Argument::String(word.into()), rest))
+			},
+			Self::String(StringKind::Word) => invalid("a word"),
+			Self::String(StringKind::Quotable) => quoted(input),
+			Self::String(StringKind::Greedy) | Self::Message =>
+					match input.is_empty() {
+				true => invalid("a message"),
+				false => Ok((match self {
+					Self::Message => Argument::Message(input.into()),
+					_ => Argument::String(input.into())
+				}, ""))
+			},
+			Self::Entity {single, players} => {
+				let selector = word.parse::<Selector>()?;
+				match selector {
+					Selector::AllPlayers | Selector::AllEntities if *single =>
+						invalid("a single entity"),
+					Selector::AllEntities if *players => invalid("a player"),
+					selector => Ok((Argument::Entity(selector), rest))
+				}
+			},
+			Self::BlockPos => {
+				let mut coordinates = [Coordinate::Absolute(0); 3];
+				let mut rest = input;
+				for coordinate in coordinates.iter_mut() {
+					let trimmed = rest.trim_start_matches(' ');
+					let (word, after) =
+						trimmed.split_at(trimmed.find(' ').unwrap_or(trimmed.len()));
+					*coordinate = word.parse()?;
+					rest = after;
+				}
+				Ok((Argument::BlockPos(coordinates), rest))
+			}
+		}
+	}
+
+	/// Suggests ways to finish `partial` as this kind of argument. `players`
+	/// are the names of every player online.
+	pub fn suggest(&self, partial: &str, players: &[Box<str>]) -> Vec<Box<str>> {
+		let options: Vec<Box<str>> = match self {
+			Self::Bool => vec!["true".into(), "false".into()],
+			Self::Entity {single, players: only_players} => {
+				let selectors: &[&str] = match (single, only_players) {
+					(true, _) => &["@p", "@r", "@s"],
+					(false, true) => &["@a", "@p", "@r", "@s"],
+					(false, false) => &["@a", "@e", "@p", "@r", "@s"]
+				};
+				selectors.iter().map(|selector| (*selector).into())
+					.chain(players.iter().cloned())
+					.collect()
+			},
+			_ => vec![]
+		};
+
+		options.into_iter()
+			.filter(|option| option.starts_with(partial))
+			.collect()
+	}
+}
+
+/// Reads a phrase in double quotes, where quotes and backslashes may be
+/// escaped with a backslash.
+fn quoted(input: &str) -> Result<(Argument, &str), CommandError> {
+	let mut value = String::new();
+	let mut escaped = false;
+	for (index, character) in input.char_indices().skip(1) {
+		match (escaped, character) {
+			(false, '\\') => escaped = true,
+			(false, '"') =>
+				return Ok((Argument::String(value.into()), &input[index + 1..])),
+			(_, character) => {
+				value.push(character);
+				escaped = false
+			}
+		}
+	}
+
+	Err(CommandError::InvalidArgument(input.into(), "a closing quote".into()))
+}
+
+/// The value of an argument in a parsed command.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Argument {
+	Bool(bool),
+	Integer(i32),
+	Double(f64),
+	String(Box<str>),
+	Entity(Selector),
+	BlockPos([Coordinate; 3]),
+	Message(Box<str>)
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Coordinate {
+	Absolute(i32),
+	/// Relative to the source's position, written with a `~`.
+	Relative(i32)
+}
+
+impl FromStr for Coordinate {
+	type Err = CommandError;
+
+	fn from_str(string: &str) -> Result<Self, CommandError> {
+		let invalid =
+			|| CommandError::InvalidArgument(string.into(), "a coordinate".into());
+		match string.strip_prefix('~') {
+			Some("") => Ok(Self::Relative(0)),
+			Some(offset) => offset.parse().map(Self::Relative).map_err(|_| invalid()),
+			None => string.parse().map(Self::Absolute).map_err(|_| invalid())
+		}
+	}
+}
+
+/// Which entities an entity argument refers to. Selector arguments in square
+/// brackets aren't supported.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Selector {
+	Name(Box<str>),
+	/// `@a`
+	AllPlayers,
+	/// `@e`
+	AllEntities,
+	/// `@p`
+	Nearest,
+	/// `@r`
+	Random,
+	/// `@s`
+	Source
+}
+
+impl FromStr for Selector {
+	type Err = CommandError;
+
+	fn from_str(string: &str) -> Result<Self, CommandError> {
+		Ok(match string {
+			"@a" => Self::AllPlayers,
+			"@e" => Self::AllEntities,
+			"@p" => Self::Nearest,
+			"@r" => Self::Random,
+			"@s" => Self::Source,
+			name if (1..=16).contains(&name.len()) && name.chars().all(|character|
+					character.is_ascii_alphanumeric() || character == '_') =>
+				Self::Name(name.into()),
+			_ => return Err(CommandError::InvalidArgument(
+				string.into(), "a player name or selector".into()))
+		})
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeKind {
+	Root,
+	Literal(Box<str>),
+	Argument {
+		name: Box<str>,
+		parser: Parser
+	}
+}
+
+/// One node of the command graph, as sent to clients. Children are indices
+/// into the graph, where the root is always first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphNode {
+	pub kind: NodeKind,
+	pub children: Vec<usize>,
+	pub executable: bool
+}
+
+struct Node<S> {
+	kind: NodeKind,
+	children: Vec<usize>,
+	executor: Option<Executor<S>>,
+	requirement: Requirement
+}
+
+impl<S> Node<S> {
+	fn parse<'i>(&self, input: &'i str)
+			-> Result<(Option<(Box<str>, Argument)>, &'i str), CommandError> {
+		match &self.kind {
+			NodeKind::Root => Ok((None, input)),
+			NodeKind::Literal(literal) => match input.strip_prefix(&**literal) {
+				Some(rest) if rest.is_empty() || rest.starts_with(' ') => Ok((None, rest)),
+				_ => Err(CommandError::Unknown(input.into()))
+			},
+			NodeKind::Argument {name, parser} => match parser.parse(input)? {
+				(argument, rest) if rest.is_empty() || rest.starts_with(' ') =>
+					Ok((Some((name.clone(), argument)), rest)),
+				(_, rest) => Err(CommandError::InvalidArgument(
+					rest.into(), "a space".into()))
+			}
+		}
+	}
+
+	fn suggest(&self, partial: &str, players: &[Box<str>]) -> Vec<Box<str>> {
+		match &self.kind {
+			NodeKind::Root => vec![],
+			NodeKind::Literal(literal) => match literal.starts_with(partial) {
+				true => vec![literal.clone()],
+				false => vec![]
+			},
+			NodeKind::Argument {parser, ..} => parser.suggest(partial, players)
+		}
+	}
+}
+
+/// Builds up a command, much like Brigadier's argument builders.
+pub struct CommandBuilder<S> {
+	kind: NodeKind,
+	children: Vec<CommandBuilder<S>>,
+	executor: Option<Executor<S>>,
+	/// Left unset so merging into an existing command keeps it's level.
+	level: Option<u8>,
+	permission: Option<Box<str>>
+}
+
+/// Starts building a command, or part of one, that's a fixed word.
+pub fn literal<S>(name: &str) -> CommandBuilder<S> {
+	CommandBuilder {
+		kind: NodeKind::Literal(name.into()),
+		children: Vec::new(),
+		executor: None,
+		level: None,
+		permission: None
+	}
+}
+
+/// Starts building part of a command that's an argument.
+pub fn argument<S>(name: &str, parser: Parser) -> CommandBuilder<S> {
+	CommandBuilder {
+		kind: NodeKind::Argument {name: name.into(), parser},
+		children: Vec::new(),
+		executor: None,
+		level: None,
+		permission: None
+	}
+}
+
+impl<S> CommandBuilder<S> {
+	pub fn then(mut self, child: CommandBuilder<S>) -> Self {
+		self.children.push(child);
+		self
+	}
+
+	/// Makes the command complete at this point, running `executor`.
+	pub fn executes<F>(mut self, executor: F) -> Self
+			where F: Fn(&S, &CommandContext) -> Result<(), CommandError>
+				+ Send + Sync + 'static {
+		self.executor = Some(Arc::new(executor));
+		self
+	}
+
+	/// Only lets sources with a permission level of at least `level` use this
+	/// part of the command, and anything after it.
+	pub fn requires(mut self, level: u8) -> Self {
+		self.level = Some(level);
+		self
+	}
+
+	/// Lets the permission node `node` decide who may use this part of the
+	/// command, and anything after it, falling back to the permission level.
+	pub fn permission(mut self, node: &str) -> Self {
+		self.permission = Some(node.into());
+		self
+	}
+}
+
+/// Every registered command, as a graph of literals and arguments.
+pub struct Dispatcher<S> {
+	nodes: Vec<Node<S>>
+}
+
+impl<S> Dispatcher<S> {
+	pub fn new() -> Self {
+		Self {nodes: vec![Node {
+			kind: NodeKind::Root,
+			children: Vec::new(),
+			executor: None,
+			requirement: Requirement::default()
+		}]}
+	}
+
+	/// Adds a command. Commands sharing their first words with ones registered
+	/// before are merged into them.
+	pub fn register(&mut self, command: CommandBuilder<S>) {
+		self.insert(0, command)
+	}
+
+	fn insert(&mut self, parent: usize, builder: CommandBuilder<S>) {
+		let existing = self.nodes[parent].children.iter().copied()
+			.find(|child| self.nodes[*child].kind == builder.kind);
+
+		let node = match existing {
+			Some(node) => {
+				let node_ref = &mut self.nodes[node];
+				if builder.executor.is_some() {node_ref.executor = builder.executor}
+				let requirement = &mut node_ref.requirement;
+				if let Some(level) = builder.level {requirement.level = level}
+				if builder.permission.is_some() {
+					requirement.permission = builder.permission
+				}
+				node
+			},
+			None => {
+				self.nodes.push(Node {
+					kind: builder.kind,
+					children: Vec::new(),
+					executor: builder.executor,
+					requirement: Requirement {
+						level: builder.level.unwrap_or_default(),
+						permission: builder.permission
+					}
+				});
+				let node = self.nodes.len() - 1;
+				self.nodes[parent].children.push(node);
+				node
+			}
+		};
+
+		builder.children.into_iter().for_each(|child| self.insert(node, child))
+	}
+
+	/// Parses a command, without the leading slash, for a source that may use
+	/// the parts of commands it's `permitted` to. Returns what to run along
+	/// with the arguments to run it with.
+	pub fn parse(&self, command: &str, permitted: &dyn Fn(&Requirement) -> bool)
+			-> Result<(Executor<S>, Vec<(Box<str>, Argument)>), CommandError> {
+		let mut arguments = Vec::new();
+		let node = self.walk(0, command.trim(), permitted, &mut arguments)?;
+		Ok((self.nodes[node].executor.clone().unwrap(), arguments))
+	}
+
+	/// Finds the executable node `input` leads to from `node`, trying literals
+	/// before arguments.
+	fn walk(&self, node: usize, input: &str,
+			permitted: &dyn Fn(&Requirement) -> bool,
+			arguments: &mut Vec<(Box<str>, Argument)>)
+			-> Result<usize, CommandError> {
+		if input.is_empty() {
+			return match self.nodes[node].executor {
+				Some(_) => Ok(node),
+				None => Err(CommandError::Incomplete)
+			}
+		}
+
+		let mut children = self.nodes[node].children.iter().copied()
+			.filter(|child| permitted(&self.nodes[*child].requirement))
+			.collect::<Vec<_>>();
+		children.sort_by_key(|child| match self.nodes[*child].kind {
+			NodeKind::Literal(_) => 0,
+			_ => 1
+		});
+
+		let mut error = CommandError::Unknown(input.into());
+		for child in children {
+			let (argument, rest) = match self.nodes[child].parse(input) {
+				Ok(parsed) => parsed,
+				Err(CommandError::Unknown(_)) => continue,
+				Err(other) => {error = other; continue}
+			};
+
+			let pushed = argument.is_some();
+			arguments.extend(argument);
+			match self.walk(child, rest.trim_start_matches(' '), permitted,
+					arguments) {
+				Ok(node) => return Ok(node),
+				Err(other) => {
+					if pushed {arguments.pop();}
+					// The deepest error is the most useful.
+					if let CommandError::Unknown(_) = error {error = other}
+				}
+			}
+		}
+
+		Err(error)
+	}
+
+	/// Suggests ways to finish the last word of a partly written command,
+	/// without the leading slash. Returns where in `command` the suggestions
+	/// start, along with the suggestions themselves.
+	pub fn suggest(&self, command: &str, permitted: &dyn Fn(&Requirement) -> bool,
+			players: &[Box<str>]) -> (usize, Vec<Box<str>>) {
+		let mut suggestions = (command.len(), Vec::new());
+		self.complete(0, command, 0, permitted, players, &mut suggestions);
+		suggestions.1.sort();
+		suggestions.1.dedup();
+		suggestions
+	}
+
+	fn complete(&self, node: usize, input: &str, offset: usize,
+			permitted: &dyn Fn(&Requirement) -> bool, players: &[Box<str>], suggestions: &mut (usize, Vec<Box<str>>)) {
+		self.nodes[node].children.iter().copied()
+			.filter(|child| permitted(&self.nodes[*child].requirement))
+			.for_each(|child| {
+				// This is the word being written.
+				if !input.contains(' ') {
+					let found = self.nodes[child].suggest(input, players);
+					if !found.is_empty() {
+						suggestions.0 = offset;
+						suggestions.1.extend(found);
+					}
+				}
+
+				if let Ok((_, rest)) = self.nodes[child].parse(input) {
+					if let Some(rest) = rest.strip_prefix(' ') {
+						self.complete(child, rest, offset + input.len() - rest.len(),
+							permitted, players, suggestions)
+					}
+				}
+			});
+	}
+
+	/// The part of the command graph a source may use, being the parts it's
+	/// `permitted` to.
+	pub fn graph(&self, permitted: &dyn Fn(&Requirement) -> bool)
+			-> Vec<GraphNode> {
+		// Give the nodes that are left new, contiguous indices.
+		let mut indices = vec![None; self.nodes.len()];
+		let mut order = vec![0];
+		let mut next = 0;
+		while next < order.len() {
+			let node = order[next];
+			indices[node] = Some(next);
+			self.nodes[node].children.iter().copied()
+				.filter(|child| permitted(&self.nodes[*child].requirement))
+				.for_each(|child| order.push(child));
+			next += 1;
+		}
+
+		order.iter().map(|node| {
+			let node = &self.nodes[*node];
+			GraphNode {
+				kind: node.kind.clone(),
+				children: node.children.iter()
+					.filter_map(|child| indices[*child])
+					.collect(),
+				executable: node.executor.is_some()
+			}
+		}).collect()
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CommandError {
+	/// Nothing matches the rest of the command.
+	Unknown(Box<str>),
+	/// The command ended before it could be run.
+	Incomplete,
+	/// An argument couldn't be read, and what was expected instead.
+	InvalidArgument(Box<str>, Box<str>),
+	/// The command ran, but failed.
+	Failed(Box<str>)
+}
+
+impl Display for CommandError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Unknown(input) => write!(f, "Unknown command at {:?}.", input),
+			Self::Incomplete => write!(f, "Incomplete command."),
+			Self::InvalidArgument(input, expected) =>
+				write!(f, "Expected {}, not {:?}.", expected, input),
+			Self::Failed(reason) => write!(f, "{}", reason)
+		}
+	}
+}
+
+impl Error for CommandError {}
+
+#[cfg(test)]
+mod tests {
+	use self::super::*;
+
+	#[test]
+	fn merging_keeps_the_requirement() {
+		let mut dispatcher = Dispatcher::<()>::new();
+		dispatcher.register(literal("stop").requires(MAX_PERMISSION_LEVEL)
+			.permission("minecraft.command.stop").executes(|_, _| Ok(())));
+		dispatcher.register(literal("stop")
+			.then(argument("reason", Parser::Message).executes(|_, _| Ok(()))));
+
+		let level = |level: u8| move |requirement: &Requirement|
+			requirement.level <= level;
+		assert!(dispatcher.parse("stop", &level(0)).is_err());
+		assert!(dispatcher.parse("stop now", &level(0)).is_err());
+		assert!(dispatcher.parse("stop", &level(MAX_PERMISSION_LEVEL)).is_ok());
+		assert!(dispatcher.parse("stop now", &level(MAX_PERMISSION_LEVEL)).is_ok());
+
+		let stop = dispatcher.nodes[0].children[0];
+		assert_eq!(dispatcher.nodes[stop].requirement, Requirement {
+			level: MAX_PERMISSION_LEVEL,
+			permission: Some("minecraft.command.stop".into())
+		});
+	}
+
+	#[test]
+	fn explicit_requirements_replace_existing_ones() {
+		let mut dispatcher = Dispatcher::<()>::new();
+		dispatcher.register(literal("list").requires(3).executes(|_, _| Ok(())));
+		dispatcher.register(literal("list").requires(0));
+
+		let list = dispatcher.nodes[0].children[0];
+		assert_eq!(dispatcher.nodes[list].requirement.level, 0);
+	}
+}
@@ -0,0 +1,327 @@
+use self::super::{
+	chunk::Chunk,
+	generator::Random,
+	movement::solid,
+	position::{BlockPos, ChunkPos, EntityPos}
+};
+use std::{
+	any::{Any, TypeId},
+	collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+	mem::replace
+};
+
+/// How much entities accelerate downwards every tick, in blocks per tick.
+const GRAVITY: f64 = 0.08;
+
+/// How much of an entity's velocity is kept every tick while in the air.
+const DRAG: f64 = 0.98;
+
+/// How much of an entity's horizontal velocity is kept every tick while on the
+/// ground.
+const FRICTION: f64 = 0.546;
+
+/// Every entity on the server, and the components they're made of. Entities
+/// are nothing but an identifier, shared with the protocol's entity
+/// identifiers, that components of any type can be attached to.
+pub struct Entities {
+	next_id: i32,
+	alive: BTreeSet<i32>,
+	components: HashMap<TypeId, Box<dyn Storage>>
+}
+
+impl Entities {
+	pub fn new() -> Self {
+		Self {next_id: 0, alive: BTreeSet::new(), components: HashMap::new()}
+	}
+
+	/// Creates an entity without any components.
+	pub fn spawn(&mut self) -> i32 {
+		let entity = self.next_id;
+		self.next_id = self.next_id.wrapping_add(1);
+		self.alive.insert(entity);
+		entity
+	}
+
+	/// Removes an entity along with all of it's components, returning whether
+	/// it existed.
+	pub fn despawn(&mut self, entity: i32) -> bool {
+		self.components.values_mut().for_each(|storage| storage.remove(entity));
+		self.alive.remove(&entity)
+	}
+
+	pub fn alive(&self, entity: i32) -> bool {
+		self.alive.contains(&entity)
+	}
+
+	/// Every entity, in the order they were spawned in.
+	pub fn iter(&self) -> impl Iterator<Item = i32> + '_ {
+		self.alive.iter().copied()
+	}
+
+	/// Attaches a component to an entity, returning the component of the same
+	/// type it had before. Components can't be attached to dead entities.
+	pub fn insert<T>(&mut self, entity: i32, component: T) -> Option<T>
+			where T: Any + Send {
+		if !self.alive(entity) {return None}
+		self.storage_mut().insert(entity, component)
+	}
+
+	pub fn remove<T>(&mut self, entity: i32) -> Option<T>
+			where T: Any + Send {
+		self.storage_mut().remove(&entity)
+	}
+
+	pub fn get<T>(&self, entity: i32) -> Option<&T>
+			where T: Any + Send {
+		self.storage()?.get(&entity)
+	}
+
+	pub fn get_mut<T>(&mut self, entity: i32) -> Option<&mut T>
+			where T: Any + Send {
+		self.storage_mut().get_mut(&entity)
+	}
+
+	/// Every entity with a component of type `T`, along with that component.
+	pub fn with<T>(&self) -> impl Iterator<Item = (i32, &T)>
+			where T: Any + Send {
+		self.storage().into_iter()
+			.flat_map(|storage| storage.iter())
+			.map(|(entity, component)| (*entity, component))
+	}
+
+	pub fn with_mut<T>(&mut self) -> impl Iterator<Item = (i32, &mut T)>
+			where T: Any + Send {
+		self.storage_mut().iter_mut().map(|(entity, component)| (*entity, component))
+	}
+
+	/// Calls `f` for every entity with components of both type `A` and `B`.
+	pub fn join<A, B, F>(&mut self, mut f: F)
+			where A: Any + Send, B: Any + Send, F: FnMut(i32, &mut A, &mut B) {
+		assert_ne!(TypeId::of::<A>(), TypeId::of::<B>(),
+			"Can't join a component with itself.");
+
+		// Take one storage out while borrowing the other.
+		let mut first = self.take_storage::<A>();
+		let second = self.storage_mut::<B>();
+		first.iter_mut().for_each(|(entity, a)|
+			if let Some(b) = second.get_mut(entity) {f(*entity, a, b)});
+		self.storage_mut::<A>().append(&mut first);
+	}
+
+	/// Takes every component of type `T` out of this store, leaving none
+	/// behind, so that they can be changed while looking at other components.
+	/// They should be put back with `restore`.
+	pub fn take_storage<T>(&mut self) -> BTreeMap<i32, T>
+			where T: Any + Send {
+		replace(self.storage_mut(), BTreeMap::new())
+	}
+
+	/// Puts components taken with `take_storage` back, dropping the ones that
+	/// belong to entities that died in the meantime.
+	pub fn restore<T>(&mut self, mut storage: BTreeMap<i32, T>)
+			where T: Any + Send {
+		let alive = &self.alive;
+		storage.retain(|entity, _| alive.contains(entity));
+		self.storage_mut().append(&mut storage)
+	}
+
+	fn storage<T>(&self) -> Option<&BTreeMap<i32, T>>
+			where T: Any + Send {
+		self.components.get(&TypeId::of::<T>())
+			.map(|storage| storage.as_any().downcast_ref().unwrap())
+	}
+
+	fn storage_mut<T>(&mut self) -> &mut BTreeMap<i32, T>
+			where T: Any + Send {
+		self.components.entry(TypeId::of::<T>())
+			.or_insert_with(|| Box::new(BTreeMap::<i32, T>::new()))
+			.as_any_mut().downcast_mut().unwrap()
+	}
+}
+
+/// A type erased collection of components, which can still have components
+/// removed from it when their entity dies.
+trait Storage: Send {
+	fn remove(&mut self, entity: i32);
+	fn as_any(&self) -> &dyn Any;
+	fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T> Storage for BTreeMap<i32, T>
+		where T: Any + Send {
+	fn remove(&mut self, entity: i32) {
+		BTreeMap::remove(self, &entity);
+	}
+
+	fn as_any(&self) -> &dyn Any {
+		self
+	}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any {
+		self
+	}
+}
+
+/// Simulates some part of the world every tick.
+pub trait System: Send {
+	fn run(&mut self, entities: &mut Entities, chunks: &HashSet<Chunk>);
+}
+
+impl<F> System for F
+		where F: FnMut(&mut Entities, &HashSet<Chunk>) + Send {
+	fn run(&mut self, entities: &mut Entities, chunks: &HashSet<Chunk>) {
+		self(entities, chunks)
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Position {
+	pub position: EntityPos,
+	pub rotation: (f32, f32),
+	pub grounded: bool
+}
+
+/// How far an entity moves every tick, in blocks.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Velocity {
+	pub x: f64,
+	pub y: f64,
+	pub z: f64
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Health {
+	pub current: f32,
+	pub max: f32
+}
+
+impl Health {
+	pub fn new(max: f32) -> Self {
+		Self {current: max, max}
+	}
+
+	pub fn dead(&self) -> bool {
+		self.current <= 0.
+	}
+}
+
+/// How an entity looks, beyond what kind of entity it is.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+	pub on_fire: bool,
+	pub sneaking: bool,
+	pub sprinting: bool,
+	pub invisible: bool,
+	pub glowing: bool,
+	pub custom_name: Option<Box<str>>
+}
+
+/// Marks an entity as being a player's point of view. Players are moved by
+/// their clients rather than by systems.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Username(pub Box<str>);
+
+/// Decides where an entity wants to go.
+pub trait Brain: Send {
+	/// Returns the velocity `entity` should have from now on, or `None` to keep
+	/// it's current velocity.
+	fn think(&mut self, entity: i32, entities: &Entities) -> Option<Velocity>;
+}
+
+pub struct Ai(pub Box<dyn Brain>);
+
+/// Walks in a random direction, changing it or stopping every now and then.
+pub struct Wander {
+	random: Random,
+	speed: f64,
+	direction: Option<(f64, f64)>
+}
+
+impl Wander {
+	pub fn new(seed: u64, speed: f64) -> Self {
+		Self {random: Random::new(seed), speed, direction: None}
+	}
+}
+
+impl Brain for Wander {
+	fn think(&mut self, entity: i32, entities: &Entities) -> Option<Velocity> {
+		if self.random.next_bounded(40) == 0 {
+			self.direction = match self.random.next_bounded(3) {
+				0 => None,
+				_ => {
+					let angle = self.random.next_f64() * std::f64::consts::PI * 2.;
+					Some((angle.cos(), angle.sin()))
+				}
+			}
+		}
+
+		// Entities can't walk through the air.
+		if !entities.get::<Position>(entity)?.grounded {return None}
+
+		let velocity = entities.get::<Velocity>(entity).copied().unwrap_or_default();
+		let (x, z) = self.direction.unwrap_or((0., 0.));
+		Some(Velocity {x: x * self.speed, z: z * self.speed, ..velocity})
+	}
+}
+
+/// Lets every entity with a brain decide where to go.
+pub fn think(entities: &mut Entities, _chunks: &HashSet<Chunk>) {
+	let mut brains = entities.take_storage::<Ai>();
+	let decisions = brains.iter_mut()
+		.filter_map(|(entity, Ai(brain))| Some((*entity, brain.think(*entity, entities)?)))
+		.collect::<Vec<_>>();
+	entities.restore(brains);
+
+	decisions.into_iter().for_each(|(entity, velocity)| {
+		entities.insert(entity, velocity);
+	});
+}
+
+/// Moves entities by their velocity, making them fall and stopping them at
+/// solid blocks. Entities in chunks that aren't loaded don't move.
+pub fn physics(entities: &mut Entities, chunks: &HashSet<Chunk>) {
+	entities.join(|_, position: &mut Position, velocity: &mut Velocity| {
+		let solid_at = |block: BlockPos| chunks.get(&ChunkPos::from(block))
+			.map(|chunk| solid(chunk.block(block)));
+		if solid_at(position.position.block()).is_none() {return}
+
+		velocity.y = (velocity.y - GRAVITY) * DRAG;
+		let mut next = position.position + EntityPos::new(velocity.x, 0., velocity.z);
+
+		// Walking into a wall.
+		let feet = next.block();
+		if solid_at(feet).unwrap_or(true) || solid_at(feet + BlockPos::new(0, 1, 0))
+				.unwrap_or(true) {
+			next = position.position;
+			velocity.x = 0.;
+			velocity.z = 0.;
+		}
+
+		next.y += velocity.y;
+		position.grounded = false;
+		if velocity.y < 0. && solid_at(next.block()).unwrap_or(true) {
+			next.y = next.y.floor() + 1.;
+			velocity.y = 0.;
+			position.grounded = true;
+		}
+
+		let friction = match position.grounded {
+			true => FRICTION,
+			false => DRAG
+		};
+		velocity.x *= friction;
+		velocity.z *= friction;
+		position.position = next;
+	});
+}
+
+/// Removes entities that ran out of health, other than players.
+pub fn die(entities: &mut Entities, _chunks: &HashSet<Chunk>) {
+	let dead = entities.with::<Health>()
+		.filter(|(entity, health)| health.dead()
+			&& entities.get::<Username>(*entity).is_none())
+		.map(|(entity, _)| entity)
+		.collect::<Vec<_>>();
+
+	dead.into_iter().for_each(|entity| {entities.despawn(entity);});
+}
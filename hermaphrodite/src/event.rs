@@ -0,0 +1,182 @@
+use self::super::{
+	chat::{ChatPosition, format},
+	command::CommandSource,
+	interface::{Event, MinecraftServer},
+	movement::Violation,
+	permission::CHAT_PERMISSION,
+	position::EntityPos,
+	settings::Settings
+};
+use std::cell::RefCell;
+
+/// What players without permission to chat are told when they try to.
+const NO_CHAT_PERMISSION: &str = "You don't have permission to chat.";
+
+/// Dispatched when a player tries to move or look around. Listeners may cancel
+/// the movement, sending the player back to where they came from, or redirect
+/// them somewhere else entirely.
+pub struct PlayerMoveEvent {
+	pub username: Box<str>,
+	pub from: EntityPos,
+	pub to: EntityPos,
+	pub rotation: (f32, f32),
+	pub grounded: bool,
+	intent: RefCell<Option<PlayerMoveIntent>>
+}
+
+impl PlayerMoveEvent {
+	pub fn new(username: Box<str>, from: EntityPos, to: EntityPos,
+			rotation: (f32, f32), grounded: bool) -> Self {
+		Self {username, from, to, rotation, grounded, intent: RefCell::new(None)}
+	}
+
+	/// Whether any listener asked for this movement to be cancelled.
+	pub fn cancelled(&self) -> bool {
+		match *self.intent.borrow() {
+			Some(PlayerMoveIntent::Cancel) => true,
+			_ => false
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlayerMoveIntent {
+	Cancel,
+	Redirect(EntityPos)
+}
+
+impl Event for PlayerMoveEvent {
+	type Intent = PlayerMoveIntent;
+
+	/// Replaces any intent pushed before, so the last listener has the final say.
+	fn push_intent(&self, intent: PlayerMoveIntent) {
+		*self.intent.borrow_mut() = Some(intent)
+	}
+
+	fn handle<'l, S>(self, server: &S)
+			where S: MinecraftServer<'l> {
+		match self.intent.into_inner() {
+			None => server.pov_position_set(
+				&self.username, self.to, self.rotation, self.grounded),
+			Some(PlayerMoveIntent::Cancel) =>
+				server.pov_teleport(&self.username, self.from, self.rotation),
+			Some(PlayerMoveIntent::Redirect(position)) =>
+				server.pov_teleport(&self.username, position, self.rotation)
+		}
+	}
+}
+
+/// Dispatched when a player moves in a way they shouldn't be able to. Unless a
+/// listener allows the movement, the player is sent back to where they came
+/// from.
+pub struct PlayerViolationEvent {
+	pub username: Box<str>,
+	pub violation: Violation,
+	pub from: EntityPos,
+	pub to: EntityPos,
+	pub rotation: (f32, f32),
+	pub grounded: bool,
+	intent: RefCell<Option<PlayerViolationIntent>>
+}
+
+impl PlayerViolationEvent {
+	pub fn new(username: Box<str>, violation: Violation, from: EntityPos,
+			to: EntityPos, rotation: (f32, f32), grounded: bool) -> Self {
+		Self {username, violation, from, to, rotation, grounded,
+			intent: RefCell::new(None)}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PlayerViolationIntent {
+	/// Let the movement through, as if it was never checked.
+	Allow
+}
+
+impl Event for PlayerViolationEvent {
+	type Intent = PlayerViolationIntent;
+
+	fn push_intent(&self, intent: PlayerViolationIntent) {
+		*self.intent.borrow_mut() = Some(intent)
+	}
+
+	fn handle<'l, S>(self, server: &S)
+			where S: MinecraftServer<'l> {
+		match self.intent.into_inner() {
+			None => server.pov_teleport(&self.username, self.from, self.rotation),
+			Some(PlayerViolationIntent::Allow) => server.event_dispatch(
+				PlayerMoveEvent::new(self.username, self.from, self.to,
+					self.rotation, self.grounded))
+		}
+	}
+}
+
+/// Dispatched when a player says something in chat. Listeners may cancel the
+/// message, or change what it says before it's formatted and broadcast. It's
+/// only broadcast if the player has permission to chat.
+pub struct PlayerChatEvent {
+	pub username: Box<str>,
+	pub message: Box<str>,
+	intent: RefCell<Option<PlayerChatIntent>>
+}
+
+impl PlayerChatEvent {
+	pub fn new(username: Box<str>, message: Box<str>) -> Self {
+		Self {username, message, intent: RefCell::new(None)}
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PlayerChatIntent {
+	Cancel,
+	/// Says something else instead.
+	Modify(Box<str>)
+}
+
+impl Event for PlayerChatEvent {
+	type Intent = PlayerChatIntent;
+
+	fn push_intent(&self, intent: PlayerChatIntent) {
+		*self.intent.borrow_mut() = Some(intent)
+	}
+
+	fn handle<'l, S>(self, server: &S)
+			where S: MinecraftServer<'l> {
+		let source = CommandSource::Player(self.username.clone());
+		if !server.has_permission(&source, CHAT_PERMISSION, 0) {
+			return server.command_reply(&source, NO_CHAT_PERMISSION)
+		}
+
+		let message = match self.intent.into_inner() {
+			None => self.message,
+			Some(PlayerChatIntent::Cancel) => return,
+			Some(PlayerChatIntent::Modify(message)) => message
+		};
+
+		println!("{}", format(&self.username, &message));
+		server.chat_broadcast(&format(&self.username, &message),
+			ChatPosition::Chat, Some(&self.username))
+	}
+}
+
+/// Dispatched after the server's settings were changed while it runs, such as
+/// by reloading it's configuration. There's nothing for listeners to decide.
+pub struct SettingsChangeEvent {
+	pub previous: Settings,
+	pub current: Settings
+}
+
+impl SettingsChangeEvent {
+	pub fn new(previous: Settings, current: Settings) -> Self {
+		Self {previous, current}
+	}
+}
+
+impl Event for SettingsChangeEvent {
+	type Intent = ();
+
+	fn push_intent(&self, _intent: ()) {}
+
+	fn handle<'l, S>(self, _server: &S)
+			where S: MinecraftServer<'l> {}
+}
@@ -0,0 +1,289 @@
+mod noise;
+
+pub use self::noise::{NoiseGenerator, Octaves, Perlin, Random};
+use self::super::{block::BlockState, position::ChunkPos};
+use std::{
+	collections::HashSet, error::Error,
+	fmt::{Display, Formatter, Result as FMTResult}, str::FromStr,
+	sync::{
+		Arc, Mutex, atomic::{AtomicBool, Ordering}, mpsc::{Receiver, Sender, channel}
+	},
+	thread::{JoinHandle, spawn as thread}
+};
+
+/// The amount of blocks in a single chunk section, which is a 16x16x16 cube.
+pub const SECTION_VOLUME: usize = 16 * 16 * 16;
+
+/// The amount of sections stacked on top of each other in a chunk.
+pub const SECTION_COUNT: usize = 16;
+
+/// The preset used when a flat world is requested without specifying layers,
+/// the same as vanilla's "Classic Flat".
+pub const DEFAULT_FLAT_PRESET: &str =
+	"minecraft:bedrock,2*minecraft:dirt,minecraft:grass_block;minecraft:plains";
+
+/// A section's block identifiers, indexed by `y << 8 | z << 4 | x`.
+pub type Section = Box<[u16]>;
+
+/// Something that can create the initial contents of chunks, before any
+/// changes are applied on top of it.
+pub trait Generator: Send + Sync {
+	/// The seed of this generator, if it uses one. This is what ends up in a
+	/// chunk's `DiffSource::Generator`.
+	fn seed(&self) -> Option<u64>;
+
+	/// Whether this generates a superflat world, which clients draw with a
+	/// lower horizon.
+	fn flat(&self) -> bool {
+		false
+	}
+
+	/// Generates all sections of the chunk at `position`, from bottom to top.
+	/// Sections that are completely air are `None`.
+	fn generate(&self, position: ChunkPos) -> Vec<Option<Section>>;
+
+	/// Generates the biome of every 4x4x4 cell of the chunk at `position`,
+	/// indexed by `y << 4 | z << 2 | x`.
+	fn biomes(&self, position: ChunkPos) -> Box<[u32]>;
+}
+
+/// A chunk that has finished generating on a worker thread.
+pub struct GeneratedChunk {
+	pub position: ChunkPos,
+	pub sections: Vec<Option<Section>>,
+	pub biomes: Box<[u32]>
+}
+
+/// Runs a generator on a set of worker threads, so that generating chunks
+/// never holds up the tick loop.
+///
+/// Chunks are requested with `request`, and collected with `finished` once
+/// they're done. Workers exit once the pool is dropped or shut down.
+pub struct GeneratorPool {
+	seed: Option<u64>,
+	flat: bool,
+	requests: Mutex<Option<Sender<ChunkPos>>>,
+	results: Mutex<Receiver<GeneratedChunk>>,
+	pending: Mutex<HashSet<ChunkPos>>,
+	workers: Mutex<Vec<JoinHandle<()>>>,
+	closed: Arc<AtomicBool>
+}
+
+impl GeneratorPool {
+	pub fn new(generator: impl Generator + 'static, workers: usize) -> Self {
+		let (seed, flat) = (generator.seed(), generator.flat());
+		let generator = Arc::new(generator);
+		let (requests, incoming) = channel::<ChunkPos>();
+		let (outgoing, results) = channel();
+		let incoming = Arc::new(Mutex::new(incoming));
+		let closed = Arc::new(AtomicBool::new(false));
+
+		let workers = (0..workers.max(1)).map(|_| {
+			let (generator, incoming, outgoing, closed) = (generator.clone(),
+				incoming.clone(), outgoing.clone(), closed.clone());
+
+			thread(move || loop {
+				let position = match incoming.lock().unwrap().recv() {
+					Ok(position) => position,
+					Err(_) => break // The pool was dropped.
+				};
+				// Requests still queued up when the pool shuts down are skipped.
+				if closed.load(Ordering::SeqCst) {break}
+
+				let chunk = GeneratedChunk {
+					position,
+					sections: generator.generate(position),
+					biomes: generator.biomes(position)
+				};
+				if outgoing.send(chunk).is_err() {break}
+			})
+		}).collect();
+
+		Self {
+			seed,
+			flat,
+			requests: Mutex::new(Some(requests)),
+			results: Mutex::new(results),
+			pending: Mutex::new(HashSet::new()),
+			workers: Mutex::new(workers),
+			closed
+		}
+	}
+
+	pub fn seed(&self) -> Option<u64> {
+		self.seed
+	}
+
+	pub fn flat(&self) -> bool {
+		self.flat
+	}
+
+	/// Queues the chunk at `position` for generation, unless it already is.
+	pub fn request(&self, position: ChunkPos) {
+		let mut pending = self.pending.lock().unwrap();
+		if let Some(requests) = &*self.requests.lock().unwrap() {
+			if pending.insert(position) {requests.send(position).unwrap()}
+		}
+	}
+
+	/// Stops taking requests, and waits for the workers to finish the ones
+	/// they already took.
+	pub fn shutdown(&self) {
+		self.closed.store(true, Ordering::SeqCst);
+		self.requests.lock().unwrap().take();
+		self.pending.lock().unwrap().clear();
+		self.workers.lock().unwrap().drain(..)
+			.for_each(|worker| worker.join().unwrap());
+	}
+
+	/// Collects all chunks that finished generating since the last call.
+	pub fn finished(&self) -> Vec<GeneratedChunk> {
+		let chunks = self.results.lock().unwrap().try_iter().collect::<Vec<_>>();
+		let mut pending = self.pending.lock().unwrap();
+		chunks.iter().for_each(|chunk| {pending.remove(&chunk.position);});
+		chunks
+	}
+}
+
+/// A superflat world generator, creating the same column of blocks everywhere.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FlatGenerator {
+	layers: Vec<(u16, usize)>,
+	biome: u32
+}
+
+impl FlatGenerator {
+	/// Returns the block at height `y`, starting from the bottom of the world.
+	pub fn block_at(&self, y: usize) -> u16 {
+		let mut bottom = 0;
+		self.layers.iter()
+			.find(|(_, count)| {
+				bottom = bottom + count;
+				y < bottom
+			})
+			.map(|(block, _)| *block)
+			.unwrap_or(0)
+	}
+
+	/// Returns the total height of all layers.
+	pub fn height(&self) -> usize {
+		self.layers.iter().map(|(_, count)| count).sum()
+	}
+}
+
+impl Default for FlatGenerator {
+	fn default() -> Self {
+		DEFAULT_FLAT_PRESET.parse().unwrap()
+	}
+}
+
+impl FromStr for FlatGenerator {
+	type Err = PresetError;
+
+	/// Parses a vanilla layer preset, such as
+	/// `minecraft:bedrock,2*minecraft:dirt,minecraft:grass_block;minecraft:plains`.
+	/// Layers are listed bottom to top, and the biome may be omitted.
+	fn from_str(preset: &str) -> Result<Self, PresetError> {
+		let mut parts = preset.split(';');
+		let layers = parts.next().unwrap_or("");
+		let biome = parts.next().map(str::trim).unwrap_or("minecraft:plains");
+
+		let layers = layers.split(',')
+			.map(str::trim)
+			.filter(|layer| !layer.is_empty())
+			.map(|layer| {
+				let (count, block) = match layer.find('*') {
+					Some(index) => {
+						let count = &layer[..index];
+						(count.trim().parse().map_err(|_|
+							PresetError::BadCount(count.into()))?, &layer[index + 1..])
+					},
+					None => (1, layer)
+				};
+
+				let block = block.trim();
+				let block = block.parse::<BlockState>()
+					.map_err(|_| PresetError::UnknownBlock(block.into()))?;
+				Ok((block.id() as u16, count))
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let height: usize = layers.iter().map(|(_, count)| count).sum();
+		if height > SECTION_COUNT * 16 {return Err(PresetError::TooTall(height))}
+
+		Ok(Self {
+			layers,
+			biome: biome_id(biome)
+				.ok_or_else(|| PresetError::UnknownBiome(biome.into()))?
+		})
+	}
+}
+
+impl Generator for FlatGenerator {
+	fn seed(&self) -> Option<u64> {
+		None
+	}
+
+	fn flat(&self) -> bool {
+		true
+	}
+
+	fn generate(&self, _position: ChunkPos) -> Vec<Option<Section>> {
+		(0..SECTION_COUNT).map(|section| {
+			let bottom = section * 16;
+			let blocks = (0..16).map(|y| self.block_at(bottom + y))
+				.collect::<Vec<_>>();
+			if blocks.iter().all(|block| *block == 0) {return None}
+
+			Some((0..SECTION_VOLUME).map(|index| blocks[index >> 8])
+				.collect::<Vec<_>>().into_boxed_slice())
+		}).collect()
+	}
+
+	fn biomes(&self, _position: ChunkPos) -> Box<[u32]> {
+		vec![self.biome; 1024].into_boxed_slice()
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PresetError {
+	BadCount(Box<str>),
+	UnknownBlock(Box<str>),
+	UnknownBiome(Box<str>),
+	TooTall(usize)
+}
+
+impl Display for PresetError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::BadCount(count) =>
+				write!(f, "Expected a layer count, found {:?}.", count),
+			Self::UnknownBlock(block) => write!(f, "Unknown block {:?}.", block),
+			Self::UnknownBiome(biome) => write!(f, "Unknown biome {:?}.", biome),
+			Self::TooTall(height) => write!(f,
+				"Layers are {} blocks tall, but the world is only {}.",
+				height, SECTION_COUNT * 16)
+		}
+	}
+}
+
+impl Error for PresetError {}
+
+/// Looks up the numeric identifier of a biome, by it's namespaced name. The
+/// namespace may be omitted.
+pub fn biome_id(name: &str) -> Option<u32> {
+	BIOMES.iter()
+		.position(|biome| *biome == name.trim_start_matches("minecraft:"))
+		.map(|id| id as u32)
+}
+
+/// All biomes known to the server, where a biome's position is it's identifier.
+pub const BIOMES: &[&str] = &[
+	"plains",
+	"ocean",
+	"beach",
+	"desert",
+	"forest",
+	"mountains",
+	"snowy_tundra"
+];
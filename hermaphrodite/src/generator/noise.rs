@@ -0,0 +1,341 @@
+use self::super::{
+	SECTION_COUNT, SECTION_VOLUME, BlockState, ChunkPos, Generator, Section,
+	biome_id
+};
+
+const SEA_LEVEL: usize = 62;
+
+/// A small, fast and most importantly deterministic pseudo random number
+/// generator (SplitMix64). The same seed always yields the same sequence,
+/// regardless of platform.
+#[derive(Clone, Debug)]
+pub struct Random(u64);
+
+impl Random {
+	pub fn new(seed: u64) -> Self {
+		Self(seed)
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+		let mut value = self.0;
+		value = (value ^ value >> 30).wrapping_mul(0xBF58476D1CE4E5B9);
+		value = (value ^ value >> 27).wrapping_mul(0x94D049BB133111EB);
+		value ^ value >> 31
+	}
+
+	/// Returns a number in the range `0..bound`.
+	pub fn next_bounded(&mut self, bound: u64) -> u64 {
+		self.next_u64() % bound
+	}
+
+	/// Returns a number in the range `0.0..1.0`.
+	pub fn next_f64(&mut self) -> f64 {
+		(self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+	}
+}
+
+/// Ken Perlin's improved gradient noise, with a permutation table shuffled by
+/// a seed.
+#[derive(Clone)]
+pub struct Perlin {
+	permutation: Box<[u8]>
+}
+
+impl Perlin {
+	pub fn new(seed: u64) -> Self {
+		let mut random = Random::new(seed);
+		let mut permutation = (0..=255).collect::<Vec<u8>>();
+		(1..permutation.len()).rev().for_each(|index| {
+			let other = random.next_bounded(index as u64 + 1) as usize;
+			permutation.swap(index, other);
+		});
+
+		let permutation = permutation.iter().chain(permutation.iter())
+			.copied().collect::<Vec<_>>().into_boxed_slice();
+		Self {permutation}
+	}
+
+	/// Samples this noise at a point, returning a value roughly within
+	/// `-1.0..1.0`.
+	pub fn sample(&self, x: f64, y: f64, z: f64) -> f64 {
+		let (cell_x, cell_y, cell_z) = (
+			x.floor() as i64 & 255, y.floor() as i64 & 255, z.floor() as i64 & 255);
+		let (x, y, z) = (x - x.floor(), y - y.floor(), z - z.floor());
+		let (u, v, w) = (fade(x), fade(y), fade(z));
+
+		let p = |index: i64| self.permutation[index as usize] as i64;
+		let a = p(cell_x) + cell_y;
+		let (aa, ab) = (p(a) + cell_z, p(a + 1) + cell_z);
+		let b = p(cell_x + 1) + cell_y;
+		let (ba, bb) = (p(b) + cell_z, p(b + 1) + cell_z);
+
+		lerp(w,
+			lerp(v,
+				lerp(u, gradient(p(aa), x, y, z), gradient(p(ba), x - 1., y, z)),
+				lerp(u, gradient(p(ab), x, y - 1., z),
+					gradient(p(bb), x - 1., y - 1., z))),
+			lerp(v,
+				lerp(u, gradient(p(aa + 1), x, y, z - 1.),
+					gradient(p(ba + 1), x - 1., y, z - 1.)),
+				lerp(u, gradient(p(ab + 1), x, y - 1., z - 1.),
+					gradient(p(bb + 1), x - 1., y - 1., z - 1.))))
+	}
+}
+
+fn fade(t: f64) -> f64 {
+	t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+	a + t * (b - a)
+}
+
+fn gradient(hash: i64, x: f64, y: f64, z: f64) -> f64 {
+	let hash = hash & 15;
+	let u = if hash < 8 {x} else {y};
+	let v = if hash < 4 {y} else if hash == 12 || hash == 14 {x} else {z};
+	(if hash & 1 == 0 {u} else {-u}) + (if hash & 2 == 0 {v} else {-v})
+}
+
+/// Several layers of Perlin noise, each with double the frequency and half the
+/// amplitude of the last, normalized back into roughly `-1.0..1.0`.
+#[derive(Clone)]
+pub struct Octaves(Vec<Perlin>);
+
+impl Octaves {
+	pub fn new(random: &mut Random, octaves: usize) -> Self {
+		Self((0..octaves).map(|_| Perlin::new(random.next_u64())).collect())
+	}
+
+	pub fn sample(&self, x: f64, y: f64, z: f64) -> f64 {
+		let (total, max, _) = self.0.iter()
+			.fold((0., 0., 1.), |(total, max, scale), octave| (
+				total + octave.sample(x * scale, y * scale, z * scale) / scale,
+				max + 1. / scale,
+				scale * 2.
+			));
+		total / max
+	}
+}
+
+/// A seeded terrain generator, shaping land with noise heightmaps, carving
+/// caves and scattering ores. Identical seeds always produce identical chunks.
+#[derive(Clone)]
+pub struct NoiseGenerator {
+	seed: u64,
+	height: Octaves,
+	roughness: Octaves,
+	temperature: Octaves,
+	humidity: Octaves,
+	caves: (Perlin, Perlin)
+}
+
+impl NoiseGenerator {
+	pub fn new(seed: u64) -> Self {
+		let mut random = Random::new(seed);
+
+		Self {
+			seed,
+			height: Octaves::new(&mut random, 6),
+			roughness: Octaves::new(&mut random, 4),
+			temperature: Octaves::new(&mut random, 4),
+			humidity: Octaves::new(&mut random, 4),
+			caves: (Perlin::new(random.next_u64()), Perlin::new(random.next_u64()))
+		}
+	}
+
+	/// Returns the height of the topmost solid block of the column at `x`, `z`.
+	pub fn height_at(&self, x: f64, z: f64) -> usize {
+		let base = self.height.sample(x / 256., 0., z / 256.);
+		let roughness = (self.roughness.sample(x / 128., 0., z / 128.) + 1.) / 2.;
+		let height = 66. + base * 32. + base.max(0.) * roughness * 64.;
+		height.max(8.).min((SECTION_COUNT * 16 - 1) as f64) as usize
+	}
+
+	/// Chooses the biome of the column at `x`, `z`, which has the surface height
+	/// `height`.
+	pub fn biome_at(&self, x: f64, z: f64, height: usize) -> &'static str {
+		let temperature = self.temperature.sample(x / 512., 0., z / 512.);
+		let humidity = self.humidity.sample(x / 512., 0., z / 512.);
+
+		match height {
+			height if height < SEA_LEVEL => "ocean",
+			height if height < SEA_LEVEL + 3 => "beach",
+			height if height > 100 => "mountains",
+			_ if temperature < -0.25 => "snowy_tundra",
+			_ if temperature > 0.3 && humidity < 0. => "desert",
+			_ if humidity > 0.15 => "forest",
+			_ => "plains"
+		}
+	}
+
+	fn is_cave(&self, x: f64, y: f64, z: f64) -> bool {
+		let first = self.caves.0.sample(x / 48., y / 24., z / 48.);
+		let second = self.caves.1.sample(x / 48., y / 24., z / 48.);
+		first * first + second * second < 0.003
+	}
+
+	fn column(&self, x: f64, z: f64, random: &mut Random) -> [u16; 256] {
+		let height = self.height_at(x, z);
+		let (top, filler) = match self.biome_at(x, z, height) {
+			"desert" | "beach" => ("sand", "sand"),
+			"ocean" => ("gravel", "dirt"),
+			"mountains" => ("stone", "stone"),
+			"snowy_tundra" => ("snow_block", "dirt"),
+			_ => ("grass_block", "dirt")
+		};
+		let (top, filler) = (block(top), block(filler));
+		let (bedrock, stone, water) = (block("bedrock"), block("stone"), block("water"));
+
+		let mut column = [0; 256];
+		column.iter_mut().enumerate().for_each(|(y, block)| *block = match y {
+			0 => bedrock,
+			y if y < 5 && random.next_bounded(5) >= y as u64 => bedrock,
+			y if y == height => top,
+			y if y < height && y + 4 > height => filler,
+			y if y < height => stone,
+			y if y <= SEA_LEVEL => water,
+			_ => 0
+		});
+
+		(5..height.saturating_sub(4))
+			.filter(|y| self.is_cave(x, *y as f64, z))
+			.for_each(|y| column[y] = 0);
+		column
+	}
+
+	/// Creates a random number generator unique to a chunk of this world.
+	fn chunk_random(&self, position: ChunkPos) -> Random {
+		Random::new(self.seed
+			^ (position.x as i64 as u64).wrapping_mul(341873128712)
+			^ (position.z as i64 as u64).wrapping_mul(132897987541))
+	}
+}
+
+/// Returns the default state of a block this generator places.
+fn block(name: &str) -> u16 {
+	BlockState::default_of(name).unwrap().id() as u16
+}
+
+/// Ore veins placed in each chunk, as `(block, veins, vein size, max height)`.
+const ORES: &[(&str, usize, usize, usize)] = &[
+	("coal_ore", 20, 16, 128),
+	("iron_ore", 20, 8, 64),
+	("gold_ore", 2, 8, 32),
+	("redstone_ore", 8, 7, 16),
+	("diamond_ore", 1, 7, 16),
+	("lapis_ore", 1, 6, 32)
+];
+
+impl Generator for NoiseGenerator {
+	fn seed(&self) -> Option<u64> {
+		Some(self.seed)
+	}
+
+	fn generate(&self, position: ChunkPos) -> Vec<Option<Section>> {
+		let mut random = self.chunk_random(position);
+		let origin = position.origin();
+		let mut blocks = vec![0u16; SECTION_VOLUME * SECTION_COUNT];
+		let index = |x: usize, y: usize, z: usize| y << 8 | z << 4 | x;
+
+		(0..16).for_each(|z| (0..16).for_each(|x| {
+			let column = self.column(
+				(origin.x + x as i32) as f64, (origin.z + z as i32) as f64, &mut random);
+			column.iter().enumerate()
+				.for_each(|(y, block)| blocks[index(x, y, z)] = *block);
+		}));
+
+		let stone = block("stone");
+		ORES.iter().for_each(|(ore, veins, size, max_height)| {
+			let ore = block(ore);
+			(0..*veins).for_each(|_| {
+				let (mut x, mut z) =
+					(random.next_bounded(16) as usize, random.next_bounded(16) as usize);
+				let mut y = random.next_bounded(*max_height as u64) as usize;
+
+				(0..*size).for_each(|_| {
+					let block = &mut blocks[index(x, y, z)];
+					if *block == stone {*block = ore}
+
+					match random.next_bounded(6) {
+						0 => x = (x + 1).min(15),
+						1 => x = x.saturating_sub(1),
+						2 => y = (y + 1).min(SECTION_COUNT * 16 - 1),
+						3 => y = y.saturating_sub(1),
+						4 => z = (z + 1).min(15),
+						_ => z = z.saturating_sub(1)
+					}
+				});
+			});
+		});
+
+		blocks.chunks(SECTION_VOLUME)
+			.map(|section| match section.iter().all(|block| *block == 0) {
+				true => None,
+				false => Some(section.to_vec().into_boxed_slice())
+			})
+			.collect()
+	}
+
+	fn biomes(&self, position: ChunkPos) -> Box<[u32]> {
+		let origin = position.origin();
+		let columns = (0..16).map(|cell: i32| {
+			let x = (origin.x + (cell & 3) * 4 + 2) as f64;
+			let z = (origin.z + (cell >> 2) * 4 + 2) as f64;
+			biome_id(self.biome_at(x, z, self.height_at(x, z))).unwrap()
+		}).collect::<Vec<_>>();
+
+		(0..1024).map(|cell| columns[cell & 15]).collect::<Vec<_>>()
+			.into_boxed_slice()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use self::super::*;
+
+	const SEED: u64 = 8_675_309;
+
+	/// The chunks compared against the snapshot.
+	const CHUNKS: &[(i32, i32)] = &[(0, 0), (1, -1), (-7, 12), (40, -33), (-100, -100),
+		(250, -310)];
+
+	/// What the chunks above generated like for `SEED`, one line per chunk, with
+	/// a digest for each section, `-` for sections of air, and one for biomes.
+	const SNAPSHOT: &str = include_str!("noise.snapshot");
+
+	/// Hashes numbers with 64 bit FNV-1a, which is the same on every platform.
+	fn digest(values: impl Iterator<Item = u64>) -> String {
+		let hash = values.fold(0xCBF29CE484222325u64, |hash, value|
+			(hash ^ value).wrapping_mul(0x100000001B3));
+		format!("{:016x}", hash)
+	}
+
+	fn snapshot(generator: &NoiseGenerator) -> String {
+		CHUNKS.iter().map(|(x, z)| {
+			let position = ChunkPos::new(*x, *z);
+			let sections = generator.generate(position).iter()
+				.map(|section| match section {
+					Some(section) => digest(section.iter().map(|block| *block as u64)),
+					None => "-".to_owned()
+				})
+				.collect::<Vec<_>>().join(" ");
+			let biomes = digest(generator.biomes(position).iter().map(|id| *id as u64));
+			format!("{} {}: {} | {}\n", x, z, sections, biomes)
+		}).collect()
+	}
+
+	#[test]
+	fn same_seed_generates_the_same_chunks() {
+		let first = snapshot(&NoiseGenerator::new(SEED));
+		assert_eq!(first, snapshot(&NoiseGenerator::new(SEED)));
+		assert_eq!(first, SNAPSHOT);
+	}
+
+	#[test]
+	fn different_seeds_generate_different_chunks() {
+		assert_ne!(snapshot(&NoiseGenerator::new(SEED)),
+			snapshot(&NoiseGenerator::new(SEED + 1)));
+	}
+}
@@ -1,16 +1,273 @@
 //use crate::server::Player;
+use self::super::{
+	chat::ChatPosition, chunk::Chunk, command::{CommandSource, GraphNode},
+	entity::Metadata,
+	position::{ChunkPos, EntityPos}, settings::{Difficulty, Gamemode}
+};
+use std::{mem::take, net::IpAddr, sync::{Arc, Mutex}};
 
 pub trait MinecraftServer<'l>: Send + Sync {
 	/// Retrieves the message of the day.
 	fn message_of_the_day(&self) -> String;
 
+	/// How many players may be online at once.
+	fn max_players(&self) -> usize;
+
+	/// How many players are online.
+	fn player_count(&self) -> usize;
+
+	/// The game mode players join in.
+	fn gamemode(&self) -> Gamemode;
+
+	fn difficulty(&self) -> Difficulty;
+
+	/// Whether the server is still running. Once it stops, every point of view
+	/// has been told to disconnect, and intakes should send them what's left
+	/// before exiting.
+	fn running(&self) -> bool;
+
+	/// The furthest any player may see, in chunks.
+	fn view_distance(&self) -> u8;
+
+	/// Whether the world is superflat, rather than shaped by terrain.
+	fn world_flat(&self) -> bool;
+
 	fn event_listener_register<E>(&self, listener: &'l dyn Fn(&E, &Self))
 		where Self: Sized, E: Event + 'static;
 
 	fn event_dispatch<E>(&self, event: E)
 		where Self: Sized, E: Event + 'static;
 
-	fn new_pov(&self, name: Box<str>);
+	/// Parses and runs a command, without the leading slash. Errors are sent
+	/// back to `source`.
+	fn command_execute(&self, source: CommandSource, command: &str);
+
+	/// Whether a source has a permission node. If nothing decides the node,
+	/// sources with a permission level of at least `level` have it.
+	fn has_permission(&self, source: &CommandSource, node: &str, level: u8)
+		-> bool;
+
+	/// Sends whoever ran a command a message.
+	fn command_reply(&self, source: &CommandSource, message: &str);
+
+	/// Runs a command for the remote console `id`, returning every message it
+	/// was sent, on a line each.
+	fn command_execute_remote(&self, id: u32, command: &str) -> String;
+
+	/// Sends a message to every point of view. `sender` is the player that said
+	/// it, if any.
+	fn chat_broadcast(&self, message: &str, position: ChatPosition,
+		sender: Option<&str>);
+
+	/// Gives a player a point of view. Everything it needs to know about is
+	/// pushed through `handle`, whichever intake the player joined through.
+	/// Players that can't join are given nothing, and the intake is told why
+	/// to tell them.
+	fn new_pov(&self, name: Box<str>, handle: Box<dyn PlayerHandle>)
+		-> Result<(), String>;
+
+	/// Forgets about a point of view, such as when it's player disconnects.
+	fn remove_pov(&self, name: &str);
+
+	/// The identifier of the entity a point of view is, unique among all the
+	/// entities on the server.
+	fn pov_entity_id(&self, name: &str) -> Option<i32>;
+
+	/// Sets how far a point of view would like to see, in chunks. It will never
+	/// see further than the server's view distance.
+	fn pov_view_distance(&self, name: &str, distance: u8);
+
+	/// Which edition the player behind a point of view is playing.
+	fn pov_edition(&self, name: &str) -> Option<Edition>;
+
+	/// Pushes an update straight to a point of view, without the server taking
+	/// note of it. Updates that change what the server knows about a point of
+	/// view, such as teleports, should go through their own methods instead.
+	fn pov_send(&self, name: &str, update: PovUpdate);
+
+	/// Handles a point of view trying to move. Movement is ignored while a
+	/// teleport hasn't been confirmed yet. Otherwise it is checked against the
+	/// point of view's abilities, dispatching a `PlayerViolationEvent` if it
+	/// fails, and goes through a `PlayerMoveEvent` before being applied.
+	fn pov_move(&self, name: &str, movement: Movement);
+
+	/// Sets whether a point of view is flying. Flying is only started if it's
+	/// allowed to fly.
+	fn pov_flying(&self, name: &str, flying: bool);
+
+	fn pov_sneaking(&self, name: &str, sneaking: bool);
+
+	fn pov_sprinting(&self, name: &str, sprinting: bool);
+
+	/// Handles a point of view saying something. Messages that fail validation
+	/// are answered with why, and others go through a `PlayerChatEvent` before
+	/// being broadcast.
+	fn pov_chat(&self, name: &str, message: &str);
+
+	/// Suggests ways to finish the last word of a partly written command,
+	/// without the leading slash. Returns where in `command` the suggestions
+	/// start, along with the suggestions themselves.
+	fn pov_tab_complete(&self, name: &str, command: &str)
+		-> (usize, Vec<Box<str>>);
+
+	/// Sends a message to a single point of view, from the server.
+	fn pov_message(&self, name: &str, message: &str, position: ChatPosition);
+
+	/// Moves a point of view, without any checks or events.
+	fn pov_position_set(&self, name: &str, position: EntityPos,
+		rotation: (f32, f32), grounded: bool);
+
+	/// Forcefully moves a point of view somewhere, ignoring any movement from it
+	/// until it confirms the teleport.
+	fn pov_teleport(&self, name: &str, position: EntityPos, rotation: (f32, f32));
+
+	/// Confirms that a point of view has been teleported. Confirmations with the
+	/// wrong identifier are ignored.
+	fn pov_teleport_confirm(&self, name: &str, teleport_id: i32);
+
+	/// Disconnects a point of view, showing it `reason`. It's only forgotten
+	/// about once it's actually disconnected, through `remove_pov`.
+	fn pov_kick(&self, name: &str, reason: &str);
+}
+
+/// The edition of the game a player is playing.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Edition {
+	Java,
+	Bedrock
+}
+
+/// A player's connection, through whichever intake they joined with, which
+/// turns updates into whatever it's edition understands.
+///
+/// Handles are sent updates while the server holds it's own locks, so they
+/// must not call back into the server.
+pub trait PlayerHandle: Send {
+	fn edition(&self) -> Edition;
+
+	/// The address the player connected from, if it has one.
+	fn address(&self) -> Option<IpAddr> {
+		None
+	}
+
+	/// Sends the player an update, or queues it to be sent.
+	fn send(&self, update: PovUpdate);
+
+	/// Sends the player a message, from the server.
+	fn chat(&self, message: &str, position: ChatPosition) {
+		self.send(PovUpdate::Chat {message: message.into(), position, sender: None})
+	}
+
+	/// Tells the player it was kicked, for `reason`.
+	fn kick(&self, reason: &str) {
+		self.send(PovUpdate::Disconnect(reason.into()))
+	}
+}
+
+/// A handle that keeps updates until the intake takes them, for intakes that
+/// poll their connections. Clones share the same updates.
+#[derive(Clone)]
+pub struct UpdateQueue {
+	edition: Edition,
+	address: Option<IpAddr>,
+	updates: Arc<Mutex<Vec<PovUpdate>>>
+}
+
+impl UpdateQueue {
+	pub fn new(edition: Edition, address: Option<IpAddr>) -> Self {
+		Self {edition, address, updates: Arc::new(Mutex::new(Vec::new()))}
+	}
+
+	/// Takes everything sent since the last time this was called.
+	pub fn take(&self) -> Vec<PovUpdate> {
+		take(&mut *self.updates.lock().unwrap())
+	}
+}
+
+impl PlayerHandle for UpdateQueue {
+	fn edition(&self) -> Edition {
+		self.edition
+	}
+
+	fn address(&self) -> Option<IpAddr> {
+		self.address
+	}
+
+	fn send(&self, update: PovUpdate) {
+		self.updates.lock().unwrap().push(update)
+	}
+}
+
+/// Where a point of view is trying to move to. Fields that are `None` haven't
+/// changed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Movement {
+	pub position: Option<EntityPos>,
+	pub rotation: Option<(f32, f32)>,
+	pub grounded: bool
+}
+
+/// Something a point of view needs to be told about.
+pub enum PovUpdate {
+	/// A chunk came into view.
+	ChunkLoad(Chunk),
+	/// A chunk went out of view.
+	ChunkUnload(ChunkPos),
+	/// The chunk the point of view is centered on changed.
+	ViewCenter(ChunkPos),
+	/// The furthest the server lets anyone see changed.
+	ViewDistance(u8),
+	/// The point of view was teleported, and must confirm it with
+	/// `teleport_id`.
+	Teleport {
+		position: EntityPos,
+		rotation: (f32, f32),
+		teleport_id: i32
+	},
+	/// A player joined the server, and should be listed.
+	PlayerJoin(Box<str>),
+	/// A player left the server.
+	PlayerLeave(Box<str>),
+	/// A player came into tracking range.
+	EntitySpawn {
+		entity_id: i32,
+		username: Box<str>,
+		position: EntityPos,
+		rotation: (f32, f32)
+	},
+	/// A tracked entity moved.
+	EntityMove {
+		entity_id: i32,
+		from: EntityPos,
+		to: EntityPos,
+		rotation: (f32, f32),
+		grounded: bool
+	},
+	/// A tracked entity looked around.
+	EntityLook {
+		entity_id: i32,
+		rotation: (f32, f32),
+		grounded: bool
+	},
+	/// How a tracked entity looks changed.
+	EntityMetadata {
+		entity_id: i32,
+		metadata: Metadata
+	},
+	/// A message to show, in plain text. `sender` is the player that said it,
+	/// if any.
+	Chat {
+		message: Box<str>,
+		position: ChatPosition,
+		sender: Option<Box<str>>
+	},
+	/// The commands the point of view may use changed.
+	Commands(Vec<GraphNode>),
+	/// Entities went out of tracking range, or stopped existing.
+	EntityDestroy(Vec<i32>),
+	/// The point of view was kicked, for this reason, and should be
+	/// disconnected.
+	Disconnect(Box<str>)
 }
 
 pub trait ChunkFetcher {
@@ -1,7 +1,21 @@
 #![feature(decl_macro, hash_set_entry, raw, try_blocks)]
 
+pub mod access;
+pub mod block;
+pub mod chat;
+pub mod chunk;
+pub mod command;
+pub mod entity;
+pub mod event;
+pub mod generator;
 pub mod interface;
+pub mod movement;
+pub mod palette;
+pub mod permission;
+pub mod position;
 pub mod server;
+pub mod settings;
+pub mod translation;
 pub mod util;
 
 
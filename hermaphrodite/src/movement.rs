@@ -0,0 +1,168 @@
+use self::super::{
+	block::{BlockState, Collision},
+	chunk::Chunk,
+	position::{BlockPos, ChunkPos, EntityPos}
+};
+use std::collections::HashSet;
+
+/// How wide a player is, along both horizontal axes.
+const PLAYER_WIDTH: f64 = 0.6;
+
+/// How tall a standing player is.
+const PLAYER_HEIGHT: f64 = 1.8;
+
+/// The fastest players fall, in blocks per tick.
+const FALLING_SPEED: f64 = 3.92;
+
+/// How far above a block a player may be while still standing on it.
+const GROUND_MARGIN: f64 = 0.05;
+
+/// What a player is allowed to do, and how fast they may do it. Speeds are in
+/// blocks per tick, like vanilla's abilities.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Abilities {
+	pub flight_allowed: bool,
+	pub flying: bool,
+	pub walking_speed: f64,
+	pub flying_speed: f64,
+	/// How fast a player rises as they jump.
+	pub jumping_speed: f64
+}
+
+impl Default for Abilities {
+	/// Creative mode abilities, which is what players join in.
+	fn default() -> Self {
+		Self {
+			flight_allowed: true,
+			flying: false,
+			walking_speed: 0.1,
+			flying_speed: 0.05,
+			jumping_speed: 0.42
+		}
+	}
+}
+
+/// How forgiving movement checks are.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerances {
+	/// How many times faster than their speed ability a player may move in one
+	/// movement. Sprint jumping already covers around six times the walking
+	/// speed, so this shouldn't be set too low.
+	pub speed_factor: f64,
+	/// Blocks a player may move on top of what the speed factor allows, to make
+	/// up for lag and knockback.
+	pub speed_leeway: f64,
+	/// How many movements a player may rise without standing on anything in
+	/// between, while not being allowed to fly. A jump takes around six.
+	pub rising_moves: u32,
+	/// Whether players may move into solid blocks.
+	pub no_clip: bool
+}
+
+impl Default for Tolerances {
+	fn default() -> Self {
+		Self {
+			speed_factor: 10.,
+			speed_leeway: 0.5,
+			rising_moves: 8,
+			no_clip: false
+		}
+	}
+}
+
+/// Why a movement was rejected.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Violation {
+	/// Moved `distance` blocks horizontally, when only `allowed` were.
+	Speed {distance: f64, allowed: f64},
+	/// Moved `distance` blocks up, or down if it's negative, when only
+	/// `allowed` were.
+	Vertical {distance: f64, allowed: f64},
+	/// Kept rising through the air without being allowed to fly.
+	Flight,
+	/// Moved into the solid block at this position.
+	NoClip(BlockPos)
+}
+
+/// Checks a movement from `from` to `to` against a player's abilities.
+/// `rising_moves` counts the movements the player has risen since they last
+/// stood on something, and is updated for this movement.
+pub fn check(from: EntityPos, to: EntityPos, abilities: &Abilities,
+		tolerances: &Tolerances, rising_moves: &mut u32,
+		chunks: &HashSet<Chunk>) -> Option<Violation> {
+	let (x, z) = (to.x - from.x, to.z - from.z);
+	let distance = (x * x + z * z).sqrt();
+	let speed = match abilities.flying {
+		true => abilities.flying_speed * 2.,
+		false => abilities.walking_speed
+	};
+	let allowed = speed * tolerances.speed_factor + tolerances.speed_leeway;
+	if distance > allowed {
+		return Some(Violation::Speed {distance, allowed})
+	}
+
+	// Flying players rise at three times their flying speed, like vanilla, and
+	// everyone else as fast as they jump. Anyone may fall.
+	let distance = to.y - from.y;
+	let speed = match (distance > 0., abilities.flying) {
+		(true, true) => abilities.flying_speed * 3.,
+		(true, false) => abilities.jumping_speed,
+		(false, _) => FALLING_SPEED
+	};
+	let allowed = speed * tolerances.speed_factor + tolerances.speed_leeway;
+	if distance.abs() > allowed {
+		return Some(Violation::Vertical {distance, allowed})
+	}
+
+	// Whatever the client says, players only stop rising through the air once
+	// they stand on something, or swim or climb.
+	let block = |position: BlockPos| chunks.get(&ChunkPos::from(position))
+		.map_or(Collision::None, |chunk| chunk.block(position).collision());
+	let below = EntityPos::new(to.x, to.y - GROUND_MARGIN, to.z);
+	let supported = touching(below, GROUND_MARGIN).into_iter()
+			.any(|position| block(position) != Collision::None)
+		|| touching(to, PLAYER_HEIGHT).into_iter()
+			.any(|position| block(position) == Collision::Climbable);
+	*rising_moves = match (supported, to.y > from.y) {
+		(true, _) => 0,
+		(false, true) => *rising_moves + 1,
+		(false, false) => *rising_moves
+	};
+	if !abilities.flight_allowed && *rising_moves > tolerances.rising_moves {
+		return Some(Violation::Flight)
+	}
+
+	if tolerances.no_clip {return None}
+
+	// Blocks the player was already stuck in don't count, so they can get out.
+	let before = touching(from, PLAYER_HEIGHT);
+	touching(to, PLAYER_HEIGHT).into_iter()
+		.filter(|position| !before.contains(position))
+		.find(|position| chunks.get(&ChunkPos::from(*position))
+			.map(|chunk| solid(chunk.block(*position)))
+			.unwrap_or(false))
+		.map(Violation::NoClip)
+}
+
+/// Lists every block a player standing at `position` overlaps with, counting
+/// only the lowest `height` blocks of them.
+fn touching(position: EntityPos, height: f64) -> Vec<BlockPos> {
+	let half = PLAYER_WIDTH / 2.;
+	let low = EntityPos::new(position.x - half, position.y, position.z - half).block();
+	// Just touching the next block over isn't being inside of it.
+	let high = EntityPos::new(position.x + half, position.y + height,
+		position.z + half) - EntityPos::new(1e-7, 1e-7, 1e-7);
+	let high = high.block();
+
+	(low.x..=high.x)
+		.flat_map(|x| (low.y..=high.y).map(move |y| (x, y)))
+		.flat_map(|(x, y)| (low.z..=high.z).map(move |z| BlockPos::new(x, y, z)))
+		.collect()
+}
+
+/// Whether a block is a full cube players can't walk through. Blocks that are
+/// only partly solid count as passable, rather than rubber-banding players
+/// walking up stairs.
+pub fn solid(state: BlockState) -> bool {
+	state.collision() == Collision::Full
+}
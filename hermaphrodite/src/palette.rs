@@ -0,0 +1,266 @@
+/// The amount of bits used by the global block palette, enough to address
+/// every block state.
+pub const GLOBAL_BLOCK_BITS: u8 = 15;
+
+/// A fixed size array of values, stored as indices into a palette of the
+/// values actually present, bit packed into longs.
+///
+/// While few distinct values are present, indices point into a local
+/// palette, using as few bits as possible. Once more than `max_indirect_bits`
+/// would be needed, the local palette is dropped and values are stored
+/// directly, using `global_bits`. The container resizes automatically as
+/// values are set.
+///
+/// Values are packed the way 1.16 expects them; entries never span across two
+/// longs, and any leftover bits at the top of a long are left unused.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PalettedContainer {
+	size: usize,
+	min_bits: u8,
+	max_indirect_bits: u8,
+	global_bits: u8,
+
+	bits: u8,
+	palette: Option<Vec<u32>>,
+	data: Vec<u64>
+}
+
+impl PalettedContainer {
+	/// Creates a container of `size` values, all set to `value`.
+	pub fn new(size: usize, value: u32, min_bits: u8, max_indirect_bits: u8,
+			global_bits: u8) -> Self {
+		let mut container = Self {
+			size,
+			min_bits,
+			max_indirect_bits,
+			global_bits,
+
+			bits: min_bits,
+			palette: None,
+			data: Vec::new()
+		};
+		container.fill(value);
+		container
+	}
+
+	/// Creates a container for the 4096 blocks of a chunk section, all set to
+	/// `block`.
+	pub fn blocks(block: u32) -> Self {
+		Self::new(4096, block, 4, 8, GLOBAL_BLOCK_BITS)
+	}
+
+	/// Creates a container for the 1024 4x4x4 biome cells of a chunk, all set to
+	/// `biome`.
+	pub fn biomes(biome: u32, global_bits: u8) -> Self {
+		Self::new(1024, biome, 1, 3, global_bits)
+	}
+
+	/// Creates a container holding `values`, in the same order.
+	pub fn from_values(values: &[u32], min_bits: u8, max_indirect_bits: u8,
+			global_bits: u8) -> Self {
+		let mut container = Self::new(values.len(),
+			values.first().copied().unwrap_or(0), min_bits, max_indirect_bits,
+			global_bits);
+		values.iter().enumerate()
+			.for_each(|(index, value)| {container.set(index, *value);});
+		container
+	}
+
+	/// The amount of values this container holds.
+	pub fn len(&self) -> usize {
+		self.size
+	}
+
+	/// The amount of bits each value is currently stored with.
+	pub fn bits_per_entry(&self) -> u8 {
+		self.bits
+	}
+
+	/// The local palette, or `None` if values are stored directly.
+	pub fn palette(&self) -> Option<&[u32]> {
+		self.palette.as_deref()
+	}
+
+	/// The packed longs backing this container.
+	pub fn data(&self) -> &[u64] {
+		&self.data
+	}
+
+	pub fn get(&self, index: usize) -> u32 {
+		let raw = self.raw(index);
+		match &self.palette {
+			Some(palette) => palette[raw as usize],
+			None => raw
+		}
+	}
+
+	/// Sets the value at `index`, returning the value that was there before.
+	pub fn set(&mut self, index: usize, value: u32) -> u32 {
+		let old = self.get(index);
+		if old == value {return old}
+
+		let raw = self.raw_for(value);
+		self.set_raw(index, raw);
+		old
+	}
+
+	/// Sets every value of this container to `value`, shrinking it back down to
+	/// a single entry palette.
+	pub fn fill(&mut self, value: u32) {
+		self.bits = self.min_bits;
+		self.palette = Some(vec![value]);
+		self.data = vec![0; longs_needed(self.size, self.bits)];
+	}
+
+	/// Counts the values that aren't `value`, which for blocks is how many
+	/// aren't air.
+	pub fn count_not(&self, value: u32) -> usize {
+		(0..self.size).filter(|index| self.get(*index) != value).count()
+	}
+
+	/// Returns all values of this container, in order.
+	pub fn values(&self) -> Vec<u32> {
+		(0..self.size).map(|index| self.get(index)).collect()
+	}
+
+	/// Returns the index of `value` in the local palette, adding it and
+	/// resizing if needed. If values are stored directly, `value` is returned.
+	fn raw_for(&mut self, value: u32) -> u32 {
+		let palette = match &mut self.palette {
+			Some(palette) => palette,
+			None => return value
+		};
+
+		if let Some(index) = palette.iter().position(|entry| *entry == value) {
+			return index as u32
+		}
+
+		if palette.len() < 1 << self.bits {
+			palette.push(value);
+			return palette.len() as u32 - 1
+		}
+
+		// The palette is full, grow into more bits, or give up on having a palette.
+		let values = self.values();
+		let bits = self.bits + 1;
+		if bits > self.max_indirect_bits {
+			self.bits = self.global_bits;
+			self.palette = None;
+		} else {
+			self.bits = bits;
+			let mut palette = self.palette.take().unwrap();
+			palette.push(value);
+			self.palette = Some(palette);
+		}
+
+		self.data = vec![0; longs_needed(self.size, self.bits)];
+		values.iter().enumerate().for_each(|(index, value)| {
+			let raw = match &self.palette {
+				Some(palette) =>
+					palette.iter().position(|entry| entry == value).unwrap() as u32,
+				None => *value
+			};
+			self.set_raw(index, raw);
+		});
+
+		self.raw_for(value)
+	}
+
+	fn raw(&self, index: usize) -> u32 {
+		let per_long = 64 / self.bits as usize;
+		let offset = (index % per_long) * self.bits as usize;
+		(self.data[index / per_long] >> offset & mask(self.bits)) as u32
+	}
+
+	fn set_raw(&mut self, index: usize, raw: u32) {
+		let per_long = 64 / self.bits as usize;
+		let offset = (index % per_long) * self.bits as usize;
+		let long = &mut self.data[index / per_long];
+		*long = *long & !(mask(self.bits) << offset)
+			| (raw as u64 & mask(self.bits)) << offset;
+	}
+}
+
+fn mask(bits: u8) -> u64 {
+	(1 << bits) - 1
+}
+
+fn longs_needed(size: usize, bits: u8) -> usize {
+	let per_long = 64 / bits as usize;
+	(size + per_long - 1) / per_long
+}
+
+#[cfg(test)]
+mod tests {
+	use self::super::*;
+
+	/// Picks block states all over the global palette, without repeating.
+	fn state(index: usize) -> u32 {
+		(index * 97 % (1 << GLOBAL_BLOCK_BITS)) as u32
+	}
+
+	#[test]
+	fn resizes_from_indirect_to_global() {
+		let mut container = PalettedContainer::blocks(0);
+		assert_eq!(container.bits_per_entry(), 4);
+
+		for index in 1..256 {
+			container.set(index, state(index));
+			// The initial air counts towards the palette too.
+			let distinct = index + 1;
+			let mut bits = 4;
+			while 1 << bits < distinct {bits += 1}
+			assert_eq!(container.bits_per_entry(), bits, "{} values", distinct);
+			assert_eq!(container.palette().map(|palette| palette.len()),
+				Some(distinct));
+		}
+
+		container.set(256, state(256));
+		assert_eq!(container.bits_per_entry(), GLOBAL_BLOCK_BITS);
+		assert_eq!(container.palette(), None);
+		assert_eq!(container.data().len(), longs_needed(4096, GLOBAL_BLOCK_BITS));
+	}
+
+	#[test]
+	fn keeps_values_across_resizes() {
+		let mut container = PalettedContainer::blocks(0);
+		let mut expected = vec![0; 4096];
+		let mut bits = container.bits_per_entry();
+
+		for step in 0..4096 {
+			// Spreads the writes over the section, and reuses some states.
+			let (index, value) = (step * 1031 % 4096, state(step % 300));
+			assert_eq!(container.set(index, value), expected[index]);
+			expected[index] = value;
+
+			// Everything's copied over whenever the container resizes.
+			if container.bits_per_entry() != bits {
+				bits = container.bits_per_entry();
+				assert_eq!(container.values(), expected, "at {} bits", bits);
+			}
+		}
+		assert_eq!(bits, GLOBAL_BLOCK_BITS);
+		(0..4096).for_each(|index| assert_eq!(container.get(index), expected[index]));
+	}
+
+	#[test]
+	fn packs_entries_without_spanning_longs() {
+		let mut container = PalettedContainer::new(24, 0, 5, 8, GLOBAL_BLOCK_BITS);
+		// Every value is new, so it's palette index is one past it's position.
+		(0..24).for_each(|index| {container.set(index, 100 + index as u32);});
+		assert_eq!(container.bits_per_entry(), 5);
+		assert_eq!(container.data().len(), 2);
+
+		// 12 entries fit in a long, and the 13th starts the next one.
+		let long = |first: u64| (0..12)
+			.fold(0u64, |long, entry| long | (first + entry) << (entry * 5));
+		assert_eq!(container.data(), &[long(1), long(13)]);
+		container.data().iter()
+			.for_each(|long| assert_eq!(long >> 60, 0, "top 4 bits are unused"));
+
+		let mut section = PalettedContainer::blocks(0);
+		(1..=16).for_each(|index| {section.set(index, state(index));});
+		assert_eq!(section.bits_per_entry(), 5);
+		assert_eq!(section.data().len(), 342);
+	}
+}
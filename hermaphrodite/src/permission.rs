@@ -0,0 +1,176 @@
+use serde::Deserialize;
+use std::{
+	collections::HashMap, error::Error, fmt::{Display, Formatter, Result as FMTResult},
+	fs::read_to_string, io::{Error as IOError, ErrorKind}, path::PathBuf
+};
+
+pub const PERMISSIONS_FILE: &str = "permissions.json";
+
+/// The group every player is in, whether or not they're listed in any others.
+pub const DEFAULT_GROUP: &str = "default";
+
+/// Lets players chat, unless it's taken away from them.
+pub const CHAT_PERMISSION: &str = "minecraft.chat";
+
+/// Decides which permission nodes players have. Nodes are words separated by
+/// dots, like `minecraft.command.ban`. Plugins may replace the provider the
+/// server starts with by their own.
+pub trait PermissionProvider: Send {
+	/// Whether a player has a permission node, or `None` if nothing decides
+	/// either way, leaving it up to their permission level.
+	fn permission(&self, player: &str, node: &str) -> Option<bool>;
+
+	/// Reads the permissions again from wherever they're kept, if anywhere.
+	fn reload(&mut self) -> Result<(), PermissionError> {
+		Ok(())
+	}
+}
+
+/// Leaves every decision to the permission levels.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoPermissions;
+
+impl PermissionProvider for NoPermissions {
+	fn permission(&self, _player: &str, _node: &str) -> Option<bool> {
+		None
+	}
+}
+
+/// Permissions granted to groups, and to players directly, as read from
+/// `permissions.json`. Nodes may end in a `*` wildcard, granting or denying
+/// everything under them, with the most specific node deciding. Whatever a
+/// player is granted directly overrides their groups, which are asked in the
+/// order they're listed, followed by the default group. Groups fall back to
+/// the groups they inherit from in the same way.
+#[derive(Clone, Debug, Default)]
+pub struct Permissions {
+	/// The directory the permissions are kept in.
+	directory: PathBuf,
+	groups: HashMap<String, Group>,
+	/// Players by their name in lowercase, as they're told apart ignoring case.
+	players: HashMap<String, PlayerPermissions>
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+struct PermissionsFile {
+	groups: HashMap<String, Group>,
+	players: HashMap<String, PlayerPermissions>
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Group {
+	pub inherits: Vec<String>,
+	pub permissions: HashMap<String, bool>
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PlayerPermissions {
+	pub groups: Vec<String>,
+	pub permissions: HashMap<String, bool>
+}
+
+impl Permissions {
+	/// Reads the permissions from `directory`. If there aren't any yet,
+	/// nothing is decided by them.
+	pub fn load(directory: impl Into<PathBuf>) -> Result<Self, PermissionError> {
+		let directory = directory.into();
+		let file = match read_to_string(directory.join(PERMISSIONS_FILE)) {
+			Ok(contents) if contents.trim().is_empty() => PermissionsFile::default(),
+			Ok(contents) => serde_json::from_str(&contents)
+				.map_err(PermissionError::Malformed)?,
+			Err(error) if error.kind() == ErrorKind::NotFound =>
+				PermissionsFile::default(),
+			Err(error) => return Err(PermissionError::Io(error))
+		};
+
+		let players = file.players.into_iter()
+			.map(|(name, player)| (name.to_ascii_lowercase(), player))
+			.collect();
+		Ok(Self {directory, groups: file.groups, players})
+	}
+
+	pub fn group(&self, name: &str) -> Option<&Group> {
+		self.groups.get(name)
+	}
+
+	pub fn player(&self, name: &str) -> Option<&PlayerPermissions> {
+		self.players.get(&name.to_ascii_lowercase())
+	}
+
+	/// Whether a group, or one it inherits from, decides a node. Groups already
+	/// asked are skipped, so groups inheriting from each other don't loop.
+	fn group_permission<'g>(&'g self, name: &'g str, node: &str,
+			asked: &mut Vec<&'g str>) -> Option<bool> {
+		if asked.contains(&name) {return None}
+		asked.push(name);
+
+		let group = self.groups.get(name)?;
+		decide(&group.permissions, node).or_else(|| group.inherits.iter()
+			.find_map(|parent| self.group_permission(parent, node, asked)))
+	}
+}
+
+impl PermissionProvider for Permissions {
+	fn permission(&self, player: &str, node: &str) -> Option<bool> {
+		let player = self.player(player);
+		if let Some(decided) =
+				player.and_then(|player| decide(&player.permissions, node)) {
+			return Some(decided)
+		}
+
+		let mut asked = Vec::new();
+		player.iter().flat_map(|player| player.groups.iter().map(String::as_str))
+			.chain(Some(DEFAULT_GROUP))
+			.find_map(|group| self.group_permission(group, node, &mut asked))
+	}
+
+	fn reload(&mut self) -> Result<(), PermissionError> {
+		*self = Self::load(self.directory.clone())?;
+		Ok(())
+	}
+}
+
+/// Whether `pattern` covers `node`, either being it or ending in a wildcard
+/// that covers everything under it. How specific the match is is returned,
+/// as the amount of words before the wildcard.
+pub fn matches(pattern: &str, node: &str) -> Option<usize> {
+	if pattern == node {return Some(usize::MAX)}
+	if pattern == "*" {return Some(0)}
+
+	let prefix = pattern.strip_suffix(".*")?;
+	match node.strip_prefix(prefix) {
+		Some(rest) if rest.starts_with('.') => Some(prefix.split('.').count()),
+		_ => None
+	}
+}
+
+/// What the most specific of `permissions` covering `node` says, if any do.
+fn decide(permissions: &HashMap<String, bool>, node: &str) -> Option<bool> {
+	permissions.iter()
+		.filter_map(|(pattern, granted)|
+			matches(pattern, node).map(|specificity| (specificity, *granted)))
+		.max_by_key(|(specificity, _)| *specificity)
+		.map(|(_, granted)| granted)
+}
+
+#[derive(Debug)]
+pub enum PermissionError {
+	Io(IOError),
+	Malformed(serde_json::Error)
+}
+
+impl Display for PermissionError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		match self {
+			Self::Io(error) =>
+				write!(f, "Couldn't access {}: {}", PERMISSIONS_FILE, error),
+			Self::Malformed(error) =>
+				write!(f, "{} is malformed: {}", PERMISSIONS_FILE, error)
+		}
+	}
+}
+
+impl Error for PermissionError {}
@@ -0,0 +1,184 @@
+use std::ops::{Add, Sub};
+
+/// The position of a single block in the world.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct BlockPos {
+	pub x: i32,
+	pub y: i32,
+	pub z: i32
+}
+
+impl BlockPos {
+	pub const fn new(x: i32, y: i32, z: i32) -> Self {
+		Self {x, y, z}
+	}
+
+	pub fn chunk(&self) -> ChunkPos {
+		ChunkPos::new(self.x >> 4, self.z >> 4)
+	}
+
+	pub fn section(&self) -> SectionPos {
+		SectionPos::new(self.x >> 4, self.y >> 4, self.z >> 4)
+	}
+
+	/// The index of this block within it's section, `y << 8 | z << 4 | x`.
+	pub fn section_index(&self) -> usize {
+		((self.y & 15) << 8 | (self.z & 15) << 4 | self.x & 15) as usize
+	}
+
+	/// Returns the position of the bottom north west corner of this block.
+	pub fn entity(&self) -> EntityPos {
+		EntityPos::new(self.x as f64, self.y as f64, self.z as f64)
+	}
+}
+
+impl Add for BlockPos {
+	type Output = Self;
+
+	fn add(self, other: Self) -> Self {
+		Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
+	}
+}
+
+impl Sub for BlockPos {
+	type Output = Self;
+
+	fn sub(self, other: Self) -> Self {
+		Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
+	}
+}
+
+/// The position of a chunk, a full height column of 16x16 blocks.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ChunkPos {
+	pub x: i32,
+	pub z: i32
+}
+
+impl ChunkPos {
+	pub const fn new(x: i32, z: i32) -> Self {
+		Self {x, z}
+	}
+
+	/// Returns the position of this chunk's bottom north west block.
+	pub fn origin(&self) -> BlockPos {
+		BlockPos::new(self.x << 4, 0, self.z << 4)
+	}
+
+	/// Returns the section of this chunk at section height `y`.
+	pub fn section(&self, y: i32) -> SectionPos {
+		SectionPos::new(self.x, y, self.z)
+	}
+
+	/// The distance between two chunks, in chunks, measured the way vanilla
+	/// measures view distance; the largest distance along either axis.
+	pub fn distance(&self, other: ChunkPos) -> i32 {
+		(self.x - other.x).abs().max((self.z - other.z).abs())
+	}
+
+	/// Lists every chunk within `radius` of this one, spiraling outwards from
+	/// this chunk, so that the closest chunks come first.
+	pub fn spiral(&self, radius: i32) -> Vec<ChunkPos> {
+		let mut chunks = vec![*self];
+		(1..=radius).for_each(|ring| {
+			// Walk each side of the ring, stopping short of the next corner.
+			let sides = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+			let mut position = (self.x - ring, self.z - ring);
+			sides.iter().for_each(|(x, z)| (0..ring * 2).for_each(|_| {
+				chunks.push(ChunkPos::new(position.0, position.1));
+				position = (position.0 + x, position.1 + z);
+			}));
+		});
+		chunks
+	}
+}
+
+/// The position of a 16x16x16 section of a chunk.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct SectionPos {
+	pub x: i32,
+	pub y: i32,
+	pub z: i32
+}
+
+impl SectionPos {
+	pub const fn new(x: i32, y: i32, z: i32) -> Self {
+		Self {x, y, z}
+	}
+
+	pub fn chunk(&self) -> ChunkPos {
+		ChunkPos::new(self.x, self.z)
+	}
+
+	/// Returns the position of this section's bottom north west block.
+	pub fn origin(&self) -> BlockPos {
+		BlockPos::new(self.x << 4, self.y << 4, self.z << 4)
+	}
+}
+
+/// The precise position of an entity in the world.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EntityPos {
+	pub x: f64,
+	pub y: f64,
+	pub z: f64
+}
+
+impl EntityPos {
+	pub const fn new(x: f64, y: f64, z: f64) -> Self {
+		Self {x, y, z}
+	}
+
+	/// Returns the block this position is inside of.
+	pub fn block(&self) -> BlockPos {
+		BlockPos::new(
+			self.x.floor() as i32, self.y.floor() as i32, self.z.floor() as i32)
+	}
+
+	pub fn chunk(&self) -> ChunkPos {
+		self.block().chunk()
+	}
+
+	pub fn distance_squared(&self, other: EntityPos) -> f64 {
+		let (x, y, z) = (self.x - other.x, self.y - other.y, self.z - other.z);
+		x * x + y * y + z * z
+	}
+
+	pub fn distance(&self, other: EntityPos) -> f64 {
+		self.distance_squared(other).sqrt()
+	}
+}
+
+impl Add for EntityPos {
+	type Output = Self;
+
+	fn add(self, other: Self) -> Self {
+		Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
+	}
+}
+
+impl Sub for EntityPos {
+	type Output = Self;
+
+	fn sub(self, other: Self) -> Self {
+		Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
+	}
+}
+
+impl From<BlockPos> for ChunkPos {
+	fn from(position: BlockPos) -> Self {
+		position.chunk()
+	}
+}
+
+impl From<EntityPos> for BlockPos {
+	fn from(position: EntityPos) -> Self {
+		position.block()
+	}
+}
+
+impl From<EntityPos> for ChunkPos {
+	fn from(position: EntityPos) -> Self {
+		position.chunk()
+	}
+}
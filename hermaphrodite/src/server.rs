@@ -1,90 +1,880 @@
 use self::super::{
-	interface::{Event, MinecraftServer},
+	access::{AccessError, AccessLists, Ban, parse_duration},
+	chat::{ChatPosition, validate},
+	chunk::Chunk,
+	command::{
+		CommandBuilder, CommandContext, CommandError, CommandSource, Dispatcher,
+		MAX_PERMISSION_LEVEL, Parser, Requirement, Selector, StringKind, argument,
+		literal
+	},
+	entity::{
+		Entities, Health, Metadata, Position, System, Username, die, physics, think
+	},
+	event::{
+		PlayerChatEvent, PlayerMoveEvent, PlayerViolationEvent, SettingsChangeEvent
+	},
+	generator::{FlatGenerator, Generator, GeneratorPool, Random},
+	interface::{
+		Edition, Event, MinecraftServer, Movement, PlayerHandle, PovUpdate
+	},
+	movement::{Abilities, Tolerances, check},
+	permission::{NoPermissions, PermissionError, PermissionProvider},
+	position::{ChunkPos, EntityPos},
+	settings::{Difficulty, Gamemode, Settings},
 	util::{GenericTraitObject, generic_trait_downcast, generic_trait}
 };
-use bit_range::BitRange;
 use std::{
-	any::TypeId, borrow::Borrow, collections::{HashSet, HashMap},
-	hash::{Hash, Hasher}, sync::Mutex, thread::sleep, time::{Duration, Instant}
+	any::TypeId, collections::{HashSet, HashMap, VecDeque}, mem::replace,
+	net::IpAddr, sync::{Mutex, MutexGuard, atomic::{AtomicBool, Ordering}},
+	thread::sleep, time::{Duration, Instant, SystemTime, UNIX_EPOCH}
 };
 
+/// The amount of threads chunks are generated on.
+const GENERATOR_WORKERS: usize = 4;
+
+/// Where players appear when they join.
+const SPAWN: EntityPos = EntityPos::new(8.5, 100., 8.5);
+
+/// How close players have to be to each other horizontally, in blocks, to see
+/// each other.
+const TRACKING_RANGE: f64 = 64.;
+
+/// How many times the server ticks every second.
+const TICKS_PER_SECOND: u64 = 20;
+
+/// How many of the latest ticks the tick rate is measured over.
+const TICK_SAMPLES: usize = 20;
+
+/// What players are shown when they're disconnected by the server stopping.
+const SHUTDOWN_REASON: &str = "Server closed.";
+
+/// What players are shown when they join while the server is full.
+const FULL_REASON: &str = "The server is full.";
+
+/// What players are shown when someone with their name is already playing.
+const DUPLICATE_REASON: &str = "Someone with your name is already playing.";
+
+/// Why players are banned, unless whoever bans them says otherwise.
+const BAN_REASON: &str = "Banned by an operator.";
+
+/// How `tempban` expects durations to be written.
+const DURATION_FORMAT: &str = "a duration like 30m, 12h or 7d";
+
 pub struct Server<'l> {
 	event_listeners: Mutex<HashMap<TypeId, Vec<GenericTraitObject<'l>>>>,
-	entities: Mutex<HashSet<Player>>,
-	chunks: Mutex<HashSet<Chunk>>
+	players: Mutex<HashMap<Box<str>, Player>>,
+	entities: Mutex<Entities>,
+	systems: Mutex<Vec<Box<dyn System>>>,
+	commands: Mutex<Dispatcher<Self>>,
+	chunks: Mutex<HashSet<Chunk>>,
+	generator: GeneratorPool,
+	settings: Mutex<Settings>,
+	/// The whitelist, bans and operators. Nothing else is locked while it is.
+	access: Mutex<AccessLists>,
+	/// Decides which permission nodes players have. Nothing else is locked
+	/// while it is.
+	permissions: Mutex<Box<dyn PermissionProvider>>,
+	/// What remote consoles were told by the commands they're running.
+	remote_output: Mutex<HashMap<u32, String>>,
+	tolerances: Tolerances,
+	stopping: AtomicBool,
+	running: AtomicBool,
+	/// How long the latest ticks took, and how long it was since the tick
+	/// before them started.
+	tick_times: Mutex<VecDeque<(Duration, Duration)>>
 	//orphanned_connections: Vec<()>,
 }
 
-#[derive(Eq, PartialEq)]
 pub struct Player {
 	username: Box<str>,
-	x: (u64, u16),
-	y: (u64, u16),
-	z: (u64, u16)
-}
+	entity_id: i32,
+	position: EntityPos,
+	rotation: (f32, f32),
+	grounded: bool,
+	abilities: Abilities,
+	rising_moves: u32,
 
-impl Hash for Player {
-	fn hash<H>(&self, hasher: &mut H)
-			where H: Hasher {
-		self.username.hash(hasher)
-	}
+	teleport_pending: Option<i32>,
+	teleport_count: i32,
+
+	view_distance: u8,
+	view_center: Option<ChunkPos>,
+	loaded_chunks: HashSet<ChunkPos>,
+	/// Every entity this player can see, and where it last saw them.
+	tracked: HashMap<i32, (EntityPos, (f32, f32), Metadata)>,
+	handle: Box<dyn PlayerHandle>
 }
 
 impl<'l> Server<'l> {
 	pub fn new() -> Self {
+		Self::with_generator(FlatGenerator::default())
+	}
+
+	pub fn with_generator(generator: impl Generator + 'static) -> Self {
+		let mut commands = Dispatcher::new();
+		Self::builtin_commands().into_iter()
+			.for_each(|command| commands.register(command));
+
 		Self {
 			event_listeners: Mutex::new(HashMap::new()),
-			entities: Mutex::new(HashSet::new()),
-			chunks: Mutex::new(HashSet::new())
+			players: Mutex::new(HashMap::new()),
+			entities: Mutex::new(Entities::new()),
+			systems: Mutex::new(vec![Box::new(think), Box::new(physics), Box::new(die)]),
+			commands: Mutex::new(commands),
+			chunks: Mutex::new(HashSet::new()),
+			generator: GeneratorPool::new(generator, GENERATOR_WORKERS),
+			settings: Mutex::new(Settings::default()),
+			access: Mutex::new(AccessLists::default()),
+			permissions: Mutex::new(Box::new(NoPermissions)),
+			remote_output: Mutex::new(HashMap::new()),
+			tolerances: Tolerances::default(),
+			stopping: AtomicBool::new(false),
+			running: AtomicBool::new(true),
+			tick_times: Mutex::new(VecDeque::with_capacity(TICK_SAMPLES))
+		}
+	}
+
+	pub fn with_settings(self, settings: Settings) -> Self {
+		*self.settings.lock().unwrap() = settings;
+		self
+	}
+
+	pub fn settings(&self) -> Settings {
+		self.settings.lock().unwrap().clone()
+	}
+
+	pub fn with_access(self, access: AccessLists) -> Self {
+		*self.access.lock().unwrap() = access;
+		self
+	}
+
+	/// Gives access to the whitelist, bans and operators. Players that are no
+	/// longer allowed on after they change aren't kicked until `access_enforce`
+	/// is called.
+	pub fn access(&self) -> MutexGuard<'_, AccessLists> {
+		self.access.lock().unwrap()
+	}
+
+	/// Reads the whitelist, bans and operators again, and applies them to
+	/// players already online.
+	pub fn access_reload(&self) -> Result<(), AccessError> {
+		self.access.lock().unwrap().reload()?;
+		self.commands_update();
+		self.access_enforce();
+		Ok(())
+	}
+
+	/// Kicks every player that isn't allowed on anymore, such as after they're
+	/// banned. The whitelist only applies to players already online while it's
+	/// enforced.
+	pub fn access_enforce(&self) {
+		let settings = self.settings();
+		let whitelist = settings.whitelist && settings.enforce_whitelist;
+
+		let players = self.players.lock().unwrap();
+		let denied = {
+			let access = self.access.lock().unwrap();
+			players.values()
+				.filter_map(|player| access.denial(&player.username,
+					player.handle.address(), whitelist).map(|reason| (player, reason)))
+				.collect::<Vec<_>>()
+		};
+		denied.into_iter().for_each(|(player, reason)| player.handle.kick(&reason))
+	}
+
+	pub fn with_permissions(self, provider: impl PermissionProvider + 'static)
+			-> Self {
+		*self.permissions.lock().unwrap() = Box::new(provider);
+		self
+	}
+
+	/// Replaces whatever decides which permission nodes players have, such as
+	/// by a plugin's own provider, and tells players which commands they may
+	/// use now.
+	pub fn permissions_set(&self, provider: Box<dyn PermissionProvider>) {
+		*self.permissions.lock().unwrap() = provider;
+		self.commands_update()
+	}
+
+	/// Reads the permissions again, and tells players which commands they may
+	/// use now.
+	pub fn permissions_reload(&self) -> Result<(), PermissionError> {
+		self.permissions.lock().unwrap().reload()?;
+		self.commands_update();
+		Ok(())
+	}
+
+	/// Changes the server's settings while it runs, telling players about the
+	/// changes they can see, and dispatching a `SettingsChangeEvent`. Players
+	/// already online stay, even if there are now too many of them.
+	pub fn settings_update(&self, settings: Settings) {
+		let previous =
+			replace(&mut *self.settings.lock().unwrap(), settings.clone());
+		if previous == settings {return}
+
+		if previous.view_distance != settings.view_distance {
+			let update = || PovUpdate::ViewDistance(settings.view_distance);
+			self.players.lock().unwrap().values()
+				.for_each(|player| player.handle.send(update()));
 		}
+		if (previous.whitelist, previous.enforce_whitelist)
+				!= (settings.whitelist, settings.enforce_whitelist) {
+			self.access_enforce()
+		}
+
+		self.event_dispatch(SettingsChangeEvent::new(previous, settings))
+	}
+
+	/// Changes how forgiving the checks players' movement goes through are.
+	pub fn with_tolerances(mut self, tolerances: Tolerances) -> Self {
+		self.tolerances = tolerances;
+		self
 	}
 
 	pub fn run(&self) {
-		let duration = Duration::from_nanos(1_000_000_000 / 1);
+		let duration = Duration::from_nanos(1_000_000_000 / TICKS_PER_SECOND);
 		println!("Running @{:?}/Tick", duration);
 
-		loop {
+		let mut last = None;
+		while !self.stopping.load(Ordering::SeqCst) {
 			let then = Instant::now();
 
 			self.tick();
 
 			let elapsed = Instant::now() - then;
-			match duration.checked_sub(elapsed) {
-				Some(time) => {
-					// We're on time.
+			if let Some(last) = last {
+				let mut tick_times = self.tick_times.lock().unwrap();
+				if tick_times.len() == TICK_SAMPLES {tick_times.pop_front();}
+				tick_times.push_back((elapsed, then - last));
+			}
+			last = Some(then);
 
-					println!("Tick completed in {:?}.", elapsed);
-					sleep(time);
-				},
-				None => panic!()
+			match duration.checked_sub(elapsed) {
+				// We're on time.
+				Some(time) => sleep(time),
+				None => println!("Can't keep up, tick took {:?}.", elapsed)
 			}
 		}
+
+		self.shutdown()
+	}
+
+	/// Makes `run` shut the server down after the tick it's in.
+	pub fn stop(&self) {
+		self.stopping.store(true, Ordering::SeqCst)
+	}
+
+	/// Disconnects every player and stops generating chunks, then marks the
+	/// server as no longer running so intakes can send the disconnects and
+	/// exit. Chunks aren't saved anywhere yet, so there's nothing to flush.
+	fn shutdown(&self) {
+		println!("Stopping the server.");
+
+		self.players.lock().unwrap().values()
+			.for_each(|player| player.handle.kick(SHUTDOWN_REASON));
+		self.generator.shutdown();
+
+		self.running.store(false, Ordering::SeqCst)
+	}
+
+	/// How many ticks ran every second, and how long they took on average,
+	/// over the latest ticks.
+	pub fn tick_rate(&self) -> (f64, Duration) {
+		let tick_times = self.tick_times.lock().unwrap();
+		if tick_times.is_empty() {return (0., Duration::default())}
+
+		let (busy, total) = tick_times.iter().fold(
+			(Duration::default(), Duration::default()),
+			|(busy, total), (elapsed, interval)| (busy + *elapsed, total + *interval));
+		let count = tick_times.len() as u32;
+		let rate = match total.as_secs_f64() {
+			total if total > 0. => count as f64 / total,
+			_ => 0.
+		};
+		(rate, busy / count)
+	}
+
+	/// The commands every server has.
+	fn builtin_commands() -> Vec<CommandBuilder<Self>> {
+		vec![
+			literal("stop").requires(MAX_PERMISSION_LEVEL)
+				.permission("minecraft.command.stop")
+				.executes(|server: &Self, _| {
+					server.stop();
+					Ok(())
+				}),
+			literal("say").requires(2).permission("minecraft.command.say")
+				.then(argument("message", Parser::Message)
+					.executes(|server: &Self, context| {
+						let name = source_name(&context.source);
+						let message = context.string("message").unwrap_or_default();
+						server.chat_broadcast(&format!("[{}] {}", name, message),
+							ChatPosition::System, None);
+						Ok(())
+					})),
+			literal("kick").requires(3).permission("minecraft.command.kick")
+				.then(player_argument()
+					.executes(|server: &Self, context| server.kick(context, None))
+					.then(argument("reason", Parser::Message)
+						.executes(|server: &Self, context|
+							server.kick(context, context.string("reason"))))),
+			literal("list").permission("minecraft.command.list")
+				.executes(|server: &Self, context| {
+					let mut names = server.players.lock().unwrap().keys()
+						.cloned().collect::<Vec<_>>();
+					names.sort();
+					server.command_reply(&context.source, &format!(
+						"There are {} players online: {}", names.len(), names.join(", ")));
+					Ok(())
+				}),
+			literal("whitelist").requires(3).permission("minecraft.command.whitelist")
+				.then(literal("on").executes(|server: &Self, context|
+					server.whitelist_set(context, true)))
+				.then(literal("off").executes(|server: &Self, context|
+					server.whitelist_set(context, false)))
+				.then(literal("add").then(player_argument()
+					.executes(|server: &Self, context|
+						server.whitelist_change(context, true))))
+				.then(literal("remove").then(player_argument()
+					.executes(|server: &Self, context|
+						server.whitelist_change(context, false))))
+				.then(literal("list")
+					.executes(|server: &Self, context| {
+						let names = server.access().whitelist().join(", ");
+						server.command_reply(&context.source, &match names.as_str() {
+							"" => "There are no whitelisted players.".to_owned(),
+							names => format!("Whitelisted players: {}", names)
+						});
+						Ok(())
+					}))
+				.then(literal("reload")
+					.executes(|server: &Self, context| {
+						server.access_reload().map_err(failed)?;
+						server.command_reply(&context.source, "Reloaded the whitelist.");
+						Ok(())
+					})),
+			literal("op").requires(3).permission("minecraft.command.op")
+				.then(player_argument()
+					.executes(|server: &Self, context| server.op(context, true))),
+			literal("deop").requires(3).permission("minecraft.command.deop")
+				.then(player_argument()
+					.executes(|server: &Self, context| server.op(context, false))),
+			literal("ban").requires(3).permission("minecraft.command.ban")
+				.then(player_argument()
+					.executes(|server: &Self, context| server.ban(context))
+					.then(argument("reason", Parser::Message)
+						.executes(|server: &Self, context| server.ban(context)))),
+			literal("tempban").requires(3).permission("minecraft.command.tempban")
+				.then(player_argument()
+					.then(argument("duration", Parser::String(StringKind::Word))
+						.executes(|server: &Self, context| server.ban(context))
+						.then(argument("reason", Parser::Message)
+							.executes(|server: &Self, context| server.ban(context))))),
+			literal("ban-ip").requires(3).permission("minecraft.command.ban-ip")
+				.then(argument("target", Parser::String(StringKind::Word))
+					.executes(|server: &Self, context| server.ban_ip(context))
+					.then(argument("reason", Parser::Message)
+						.executes(|server: &Self, context| server.ban_ip(context)))),
+			literal("pardon").requires(3).permission("minecraft.command.pardon")
+				.then(player_argument().executes(|server: &Self, context| {
+					let name = server.selected_name(context, "player")?;
+					let pardoned = server.access().pardon(&name).map_err(failed)?;
+					match pardoned {
+						true => server.command_reply(&context.source,
+							&format!("Unbanned {}.", name)),
+						false => return Err(CommandError::Failed(
+							format!("{} isn't banned.", name).into()))
+					}
+					Ok(())
+				})),
+			literal("pardon-ip").requires(3).permission("minecraft.command.pardon-ip")
+				.then(argument("target", Parser::String(StringKind::Word))
+					.executes(|server: &Self, context| {
+						let ip = ip_argument(context, "target")?;
+						let pardoned = server.access().pardon_ip(ip).map_err(failed)?;
+						match pardoned {
+							true => server.command_reply(&context.source,
+								&format!("Unbanned {}.", ip)),
+							false => return Err(CommandError::Failed(
+								format!("{} isn't banned.", ip).into()))
+						}
+						Ok(())
+					})),
+			literal("banlist").requires(3).permission("minecraft.command.banlist")
+				.executes(|server: &Self, context| server.banlist(context, true, true))
+				.then(literal("players").executes(|server: &Self, context|
+					server.banlist(context, true, false)))
+				.then(literal("ips").executes(|server: &Self, context|
+					server.banlist(context, false, true))),
+			literal("tps").requires(2).permission("minecraft.command.tps")
+				.executes(|server: &Self, context| {
+					let (rate, time) = server.tick_rate();
+					server.command_reply(&context.source, &format!(
+						"{:.2} ticks per second, taking {:.2}ms each.", rate,
+						time.as_secs_f64() * 1000.));
+					Ok(())
+				})
+		]
+	}
+
+	/// The name of the single player an argument selects, who may be offline
+	/// if they're selected by name.
+	fn selected_name(&self, context: &CommandContext, argument: &str)
+			-> Result<Box<str>, CommandError> {
+		let missing = || CommandError::Failed("No player was found.".into());
+		match context.entity(argument) {
+			Some(Selector::Name(name)) => Ok(name.clone()),
+			Some(Selector::Source) => match &context.source {
+				CommandSource::Player(name) => Ok(name.clone()),
+				CommandSource::Console | CommandSource::Remote(_) =>
+					Err(CommandError::Failed("The console isn't a player.".into()))
+			},
+			Some(Selector::Nearest) => {
+				// Sources without a position measure from the world's origin.
+				let from = context.position.unwrap_or(EntityPos::new(0., 0., 0.));
+				let players = self.players.lock().unwrap();
+				let nearest = players.iter().fold(None, |nearest, (name, player)| {
+					let distance = player.position.distance_squared(from);
+					match nearest {
+						Some((_, closest)) if closest <= distance => nearest,
+						_ => Some((name, distance))
+					}
+				});
+				nearest.map(|(name, _)| name.clone()).ok_or_else(missing)
+			},
+			Some(Selector::Random) => {
+				let seed = SystemTime::now().duration_since(UNIX_EPOCH)
+					.map_or(0, |time| time.as_nanos() as u64);
+				let players = self.players.lock().unwrap();
+				let index = match players.len() {
+					0 => return Err(missing()),
+					count => Random::new(seed).next_bounded(count as u64) as usize
+				};
+				players.keys().nth(index).cloned().ok_or_else(missing)
+			},
+			Some(Selector::AllPlayers) | Some(Selector::AllEntities) | None =>
+				Err(CommandError::Failed("Only one player can be selected.".into()))
+		}
+	}
+
+	/// Kicks the player selected by the `player` argument.
+	fn kick(&self, context: &CommandContext, reason: Option<&str>)
+			-> Result<(), CommandError> {
+		let name = self.selected_name(context, "player")?;
+		if !self.players.lock().unwrap().contains_key(&name) {
+			return Err(CommandError::Failed(format!("{} isn't online.", name).into()))
+		}
+
+		let reason = reason.unwrap_or("Kicked by an operator.");
+		self.pov_kick(&name, reason);
+		self.command_reply(&context.source, &format!("Kicked {}: {}", name, reason));
+		Ok(())
+	}
+
+	/// Turns the whitelist on or off, until the configuration is reloaded.
+	fn whitelist_set(&self, context: &CommandContext, whitelist: bool)
+			-> Result<(), CommandError> {
+		self.settings_update(Settings {whitelist, ..self.settings()});
+		self.command_reply(&context.source, match whitelist {
+			true => "Turned the whitelist on.",
+			false => "Turned the whitelist off."
+		});
+		Ok(())
+	}
+
+	/// Adds the player selected by the `player` argument to the whitelist, or
+	/// removes them from it.
+	fn whitelist_change(&self, context: &CommandContext, add: bool)
+			-> Result<(), CommandError> {
+		let name = self.selected_name(context, "player")?;
+		let changed = match add {
+			true => self.access().whitelist_add(&name),
+			false => self.access().whitelist_remove(&name)
+		}.map_err(failed)?;
+
+		let message = match (add, changed) {
+			(true, true) => format!("Added {} to the whitelist.", name),
+			(false, true) => format!("Removed {} from the whitelist.", name),
+			(true, false) => format!("{} is already whitelisted.", name),
+			(false, false) => format!("{} isn't whitelisted.", name)
+		};
+		if !changed {return Err(CommandError::Failed(message.into()))}
+
+		self.access_enforce();
+		self.command_reply(&context.source, &message);
+		Ok(())
+	}
+
+	/// Makes the player selected by the `player` argument an operator, or stops
+	/// them being one.
+	fn op(&self, context: &CommandContext, op: bool) -> Result<(), CommandError> {
+		let name = self.selected_name(context, "player")?;
+		let changed = match op {
+			true => self.access().op(&name, MAX_PERMISSION_LEVEL),
+			false => self.access().deop(&name)
+		}.map_err(failed)?;
+
+		let message = match (op, changed) {
+			(true, true) => format!("Made {} a server operator.", name),
+			(false, true) => format!("Made {} no longer a server operator.", name),
+			(true, false) => format!("{} is already an operator.", name),
+			(false, false) => format!("{} isn't an operator.", name)
+		};
+		if !changed {return Err(CommandError::Failed(message.into()))}
+
+		self.commands_update();
+		self.access_enforce();
+		self.command_reply(&context.source, &message);
+		Ok(())
+	}
+
+	/// Bans the player selected by the `player` argument, for as long as the
+	/// `duration` argument says, or forever if there isn't one.
+	fn ban(&self, context: &CommandContext) -> Result<(), CommandError> {
+		let name = self.selected_name(context, "player")?;
+		let duration = match context.string("duration") {
+			Some(duration) => Some(parse_duration(duration).ok_or_else(||
+				CommandError::InvalidArgument(duration.into(), DURATION_FORMAT.into()))?),
+			None => None
+		};
+		let reason = context.string("reason").unwrap_or(BAN_REASON);
+
+		let ban = Ban::new(source_name(&context.source), reason, duration);
+		self.access().ban(&name, ban).map_err(failed)?;
+		self.access_enforce();
+		self.command_reply(&context.source, &format!("Banned {}: {}", name, reason));
+		Ok(())
+	}
+
+	/// Bans the address in the `target` argument, or that of the online player
+	/// it names.
+	fn ban_ip(&self, context: &CommandContext) -> Result<(), CommandError> {
+		let target = context.string("target").unwrap_or_default();
+		let ip = match target.parse() {
+			Ok(ip) => ip,
+			Err(_) => self.players.lock().unwrap().values()
+				.find(|player| player.username.eq_ignore_ascii_case(target))
+				.and_then(|player| player.handle.address())
+				.ok_or_else(|| CommandError::InvalidArgument(target.into(),
+					"an IP address or an online player".into()))?
+		};
+		let reason = context.string("reason").unwrap_or(BAN_REASON);
+
+		let ban = Ban::new(source_name(&context.source), reason, None);
+		self.access().ban_ip(ip, ban).map_err(failed)?;
+		self.access_enforce();
+		self.command_reply(&context.source, &format!("Banned {}: {}", ip, reason));
+		Ok(())
+	}
+
+	/// Lists the bans that haven't expired, of players, addresses or both.
+	fn banlist(&self, context: &CommandContext, players: bool, ips: bool)
+			-> Result<(), CommandError> {
+		let describe = |target: String, ban: &Ban| format!(
+			"{} was banned by {}: {}", target, ban.source, ban.reason);
+		let lines = {
+			let access = self.access();
+			let players = access.banned_players().into_iter()
+				.filter(|(_, ban)| players && ban.active())
+				.map(|(name, ban)| describe(name.to_owned(), ban));
+			let ips = access.banned_ips().into_iter()
+				.filter(|(_, ban)| ips && ban.active())
+				.map(|(ip, ban)| describe(ip.to_string(), ban));
+			players.chain(ips).collect::<Vec<_>>()
+		};
+
+		self.command_reply(&context.source, &match lines.len() {
+			0 => "There are no bans.".to_owned(),
+			count => format!("There are {} bans:", count)
+		});
+		lines.iter().for_each(|line| self.command_reply(&context.source, line));
+		Ok(())
+	}
+
+	/// Adds a system to run every tick, after the ones added before it.
+	pub fn system_register(&self, system: impl System + 'static) {
+		self.systems.lock().unwrap().push(Box::new(system))
+	}
+
+	/// Adds a command, and tells every player about it.
+	pub fn command_register(&self, command: CommandBuilder<Self>) {
+		self.commands.lock().unwrap().register(command);
+		self.commands_update()
+	}
+
+	/// Tells every player which commands they may use, such as after their
+	/// permission level changes.
+	fn commands_update(&self) {
+		// Players are always locked before commands.
+		let players = self.players.lock().unwrap();
+		let commands = self.commands.lock().unwrap();
+		players.values().for_each(|player| {
+			let source = CommandSource::Player(player.username.clone());
+			let graph =
+				commands.graph(&|requirement| self.permitted(&source, requirement));
+			player.handle.send(PovUpdate::Commands(graph))
+		});
+	}
+
+	/// Whether a source may use the part of a command with `requirement`.
+	fn permitted(&self, source: &CommandSource, requirement: &Requirement) -> bool {
+		match &requirement.permission {
+			Some(node) => self.has_permission(source, node, requirement.level),
+			None => self.permission_level(source) >= requirement.level
+		}
+	}
+
+	/// The permission level of whoever runs a command, deciding which commands
+	/// they may use.
+	fn permission_level(&self, source: &CommandSource) -> u8 {
+		match source {
+			CommandSource::Console | CommandSource::Remote(_) => MAX_PERMISSION_LEVEL,
+			CommandSource::Player(name) => self.access.lock().unwrap().operator(name)
+				.map_or(0, |operator| operator.level.min(MAX_PERMISSION_LEVEL))
+		}
+	}
+
+	/// Gives access to every entity and their components, such as to spawn
+	/// new ones.
+	pub fn entities(&self) -> MutexGuard<'_, Entities> {
+		self.entities.lock().unwrap()
 	}
 
 	fn tick(&self) {
 		self.manage_chunks();
+		self.sync_players();
+		self.run_systems();
+		self.track_entities();
+	}
+
+	/// Copies where players are into their entities, so systems can see them.
+	fn sync_players(&self) {
+		let players = self.players.lock().unwrap();
+		let mut entities = self.entities.lock().unwrap();
+
+		players.values().for_each(|player| {
+			entities.insert(player.entity_id, Position {
+				position: player.position,
+				rotation: player.rotation,
+				grounded: player.grounded
+			});
+		});
+	}
+
+	/// Changes the metadata of a player's entity.
+	fn pov_metadata(&self, name: &str, change: impl FnOnce(&mut Metadata)) {
+		let entity_id = match self.players.lock().unwrap().get(name) {
+			Some(player) => player.entity_id,
+			None => return
+		};
+
+		if let Some(metadata) = self.entities.lock().unwrap().get_mut(entity_id) {
+			change(metadata)
+		}
+	}
+
+	fn run_systems(&self) {
+		let mut entities = self.entities.lock().unwrap();
+		let chunks = self.chunks.lock().unwrap();
+
+		self.systems.lock().unwrap().iter_mut()
+			.for_each(|system| system.run(&mut entities, &chunks));
 	}
 
 	fn manage_chunks(&self) {
-		let players = self.entities.lock().unwrap();
+		let view_distance = self.view_distance();
+		let mut players = self.players.lock().unwrap();
 		let mut chunks = self.chunks.lock().unwrap();
 
-		//let render_distance = 2;
-		//let load_max = 10000;
-		players.iter().for_each(|player| {
-			let chunk_pos = (player.x.0 / 16, player.z.0 / 16);
+		let seed = self.generator.seed();
+		self.generator.finished().into_iter().for_each(|chunk| {
+			println!("Loaded chunk @{},{}.", chunk.position.x, chunk.position.z);
+			chunks.insert(Chunk::generated(chunk, seed));
+		});
+
+		let mut visible = HashSet::new();
+		players.values_mut().for_each(|player| {
+			let center = player.position.chunk();
+			let distance = player.view_distance.min(view_distance) as i32;
+
+			if player.view_center != Some(center) {
+				player.view_center = Some(center);
+				player.handle.send(PovUpdate::ViewCenter(center));
+			}
+
+			// Forget about chunks that are now out of view.
+			let handle = &player.handle;
+			player.loaded_chunks.retain(|position| {
+				let keep = position.distance(center) <= distance;
+				if !keep {handle.send(PovUpdate::ChunkUnload(*position))}
+				keep
+			});
+
+			// Send chunks closest to the player first.
+			center.spiral(distance).into_iter().for_each(|position| {
+				visible.insert(position);
+				if player.loaded_chunks.contains(&position) {return}
+
+				match chunks.get(&position) {
+					Some(chunk) => {
+						player.loaded_chunks.insert(position);
+						player.handle.send(PovUpdate::ChunkLoad(chunk.clone()));
+					},
+					None => self.generator.request(position)
+				}
+			});
+		});
+
+		// Unload chunks no player can see.
+		chunks.retain(|chunk| {
+			let keep = visible.contains(&chunk.position());
+			if !keep {println!("Unloaded chunk @{},{}.",
+				chunk.position().x, chunk.position().z)}
+			keep
+		});
+	}
+
+	/// Tells players about every other player that came into or went out of
+	/// range, and how the ones in range moved or changed since last tick.
+	fn track_entities(&self) {
+		let mut players = self.players.lock().unwrap();
+		let store = self.entities.lock().unwrap();
+
+		let entities = players.values()
+			.map(|player| (player.entity_id, player.username.clone(),
+				player.position, player.rotation, player.grounded,
+				store.get::<Metadata>(player.entity_id).cloned().unwrap_or_default()))
+			.collect::<Vec<_>>();
+		drop(store);
+
+		players.values_mut().for_each(|viewer| {
+			let mut destroyed = Vec::new();
+			let viewer_id = viewer.entity_id;
+
+			entities.iter()
+				.filter(|(entity_id, ..)| *entity_id != viewer_id)
+				.for_each(|(entity_id, username, position, rotation, grounded, metadata)| {
+					let (x, z) = (position.x - viewer.position.x,
+						position.z - viewer.position.z);
+					let in_range = x * x + z * z <= TRACKING_RANGE * TRACKING_RANGE;
+					let entity_id = *entity_id;
+
+					match (viewer.tracked.get_mut(&entity_id), in_range) {
+						(None, true) => {
+							viewer.tracked.insert(entity_id,
+								(*position, *rotation, metadata.clone()));
+							viewer.handle.send(PovUpdate::EntitySpawn {
+								entity_id,
+								username: username.clone(),
+								position: *position,
+								rotation: *rotation
+							});
+							if *metadata != Metadata::default() {
+								viewer.handle.send(PovUpdate::EntityMetadata {
+									entity_id, metadata: metadata.clone()
+								});
+							}
+						},
+						(Some((from, last_rotation, last_metadata)), true) => {
+							if from != position {
+								viewer.handle.send(PovUpdate::EntityMove {
+									entity_id, from: *from, to: *position,
+									rotation: *rotation, grounded: *grounded
+								});
+								*from = *position;
+							}
+							if last_rotation != rotation {
+								viewer.handle.send(PovUpdate::EntityLook {
+									entity_id, rotation: *rotation, grounded: *grounded
+								});
+								*last_rotation = *rotation;
+							}
+							if last_metadata != metadata {
+								viewer.handle.send(PovUpdate::EntityMetadata {
+									entity_id, metadata: metadata.clone()
+								});
+								*last_metadata = metadata.clone();
+							}
+						},
+						(Some(_), false) => {
+							viewer.tracked.remove(&entity_id);
+							destroyed.push(entity_id);
+						},
+						(None, false) => ()
+					}
+				});
 
-			chunks.get_or_insert_with(&chunk_pos, |_| {
-				println!("Loaded chunk @{},{}.", chunk_pos.0, chunk_pos.1);
-				Chunk::solid(chunk_pos, 0)
+			// Entities that don't exist anymore.
+			viewer.tracked.retain(|entity_id, _| {
+				let keep = entities.iter().any(|(other, ..)| other == entity_id);
+				if !keep {destroyed.push(*entity_id)}
+				keep
 			});
+
+			if !destroyed.is_empty() {
+				viewer.handle.send(PovUpdate::EntityDestroy(destroyed))
+			}
 		});
 	}
 }
 
+/// An argument selecting a single player, named `player`.
+fn player_argument<S>() -> CommandBuilder<S> {
+	argument("player", Parser::Entity {single: true, players: true})
+}
+
+fn ip_argument(context: &CommandContext, argument: &str)
+		-> Result<IpAddr, CommandError> {
+	let ip = context.string(argument).unwrap_or_default();
+	ip.parse().map_err(|_|
+		CommandError::InvalidArgument(ip.into(), "an IP address".into()))
+}
+
+/// What whoever ran a command is called in messages, such as who banned
+/// someone.
+fn source_name(source: &CommandSource) -> &str {
+	match source {
+		CommandSource::Console => "Server",
+		CommandSource::Player(name) => name,
+		CommandSource::Remote(_) => "Rcon"
+	}
+}
+
+fn failed(error: AccessError) -> CommandError {
+	CommandError::Failed(error.to_string().into())
+}
+
 impl<'l> MinecraftServer<'l> for Server<'l> {
 	fn message_of_the_day(&self) -> String {
-		"Hello, world!".to_owned()
+		self.settings.lock().unwrap().message_of_the_day.to_string()
+	}
+
+	fn max_players(&self) -> usize {
+		self.settings.lock().unwrap().max_players
+	}
+
+	fn player_count(&self) -> usize {
+		self.players.lock().unwrap().len()
+	}
+
+	fn gamemode(&self) -> Gamemode {
+		self.settings.lock().unwrap().gamemode
+	}
+
+	fn difficulty(&self) -> Difficulty {
+		self.settings.lock().unwrap().difficulty
+	}
+
+	fn running(&self) -> bool {
+		self.running.load(Ordering::SeqCst)
+	}
+
+	fn view_distance(&self) -> u8 {
+		self.settings.lock().unwrap().view_distance
+	}
+
+	fn world_flat(&self) -> bool {
+		self.generator.flat()
 	}
 
 	fn event_listener_register<E>(&self, listener: &'l dyn Fn(&E, &Self))
@@ -107,72 +897,276 @@ impl<'l> MinecraftServer<'l> for Server<'l> {
 					let func: &dyn Fn(&E, &Self) = generic_trait_downcast!(listener);
 					func(&event, self);
 				}));
+		drop(event_listeners); // Let the event dispatch more events.
 
 		event.handle(self);
 	}
+
+	fn command_execute(&self, source: CommandSource, command: &str) {
+		let parsed = self.commands.lock().unwrap()
+			.parse(command, &|requirement| self.permitted(&source, requirement));
+		let (executor, arguments) = match parsed {
+			Ok(parsed) => parsed,
+			Err(error) => return self.command_reply(&source, &error.to_string())
+		};
+
+		let position = match &source {
+			CommandSource::Player(name) =>
+				self.players.lock().unwrap().get(name).map(|player| player.position),
+			CommandSource::Console | CommandSource::Remote(_) => None
+		};
+
+		let context = CommandContext {source, position, arguments};
+		if let Err(error) = executor(self, &context) {
+			self.command_reply(&context.source, &error.to_string())
+		}
+	}
+
+	fn has_permission(&self, source: &CommandSource, node: &str, level: u8)
+			-> bool {
+		let name = match source {
+			CommandSource::Console | CommandSource::Remote(_) => return true,
+			CommandSource::Player(name) => name
+		};
+
+		let decided = self.permissions.lock().unwrap().permission(name, node);
+		decided.unwrap_or_else(|| self.permission_level(source) >= level)
+	}
+
+	fn command_reply(&self, source: &CommandSource, message: &str) {
+		match source {
+			CommandSource::Console => println!("{}", message),
+			CommandSource::Player(name) =>
+				self.pov_message(name, message, ChatPosition::System),
+			CommandSource::Remote(id) =>
+				if let Some(output) = self.remote_output.lock().unwrap().get_mut(id) {
+					if !output.is_empty() {output.push('\n')}
+					output.push_str(message)
+				}
+		}
+	}
+
+	fn command_execute_remote(&self, id: u32, command: &str) -> String {
+		self.remote_output.lock().unwrap().insert(id, String::new());
+		self.command_execute(CommandSource::Remote(id), command);
+		let output = self.remote_output.lock().unwrap().remove(&id);
+		output.unwrap_or_default()
+	}
+
+	fn chat_broadcast(&self, message: &str, position: ChatPosition,
+			sender: Option<&str>) {
+		let players = self.players.lock().unwrap();
+		players.values().for_each(|player| player.handle.send(PovUpdate::Chat {
+			message: message.into(),
+			position,
+			sender: sender.map(Into::into)
+		}));
+	}
 	
-	fn new_pov(&self, name: Box<str>) {
+	fn new_pov(&self, name: Box<str>, handle: Box<dyn PlayerHandle>)
+			-> Result<(), String> {
+		let mut players = self.players.lock().unwrap();
+
+		// Players that can't join are turned away before anyone hears of them.
+		let whitelist = self.settings.lock().unwrap().whitelist;
+		let access = self.access.lock().unwrap();
+		let denial = access.denial(&name, handle.address(), whitelist);
+		let bypasses_limit = access.operator(&name)
+			.map_or(false, |operator| operator.bypasses_player_limit);
+		drop(access);
+		let full = players.len() >= self.max_players() && !bypasses_limit;
+		let duplicate = players.keys().any(|other| other.eq_ignore_ascii_case(&name));
+		let rejection = match self.stopping.load(Ordering::SeqCst) {
+			true => Some(SHUTDOWN_REASON.to_owned()),
+			false if duplicate => Some(DUPLICATE_REASON.to_owned()),
+			false if denial.is_some() => denial,
+			false if full => Some(FULL_REASON.to_owned()),
+			false => None
+		};
+		if let Some(reason) = rejection {return Err(reason)}
+
+		// Everyone's listed for everyone, including themselves.
+		players.keys().for_each(|other|
+			handle.send(PovUpdate::PlayerJoin(other.clone())));
+		handle.send(PovUpdate::PlayerJoin(name.clone()));
+		players.values().for_each(|player|
+			player.handle.send(PovUpdate::PlayerJoin(name.clone())));
+		let source = CommandSource::Player(name.clone());
+		let graph = self.commands.lock().unwrap()
+			.graph(&|requirement| self.permitted(&source, requirement));
+		handle.send(PovUpdate::Commands(graph));
+
 		let mut entities = self.entities.lock().unwrap();
-		entities.insert(Player {
-			username: name,
-			x: (0, 0),
-			y: (0, 0),
-			z: (0, 0)
+		let entity_id = entities.spawn();
+		entities.insert(entity_id, Username(name.clone()));
+		entities.insert(entity_id, Position {position: SPAWN, ..Position::default()});
+		entities.insert(entity_id, Health::new(20.));
+		entities.insert(entity_id, Metadata::default());
+		drop(entities);
+
+		players.insert(name.clone(), Player {
+			username: name.clone(),
+			entity_id,
+			position: SPAWN,
+			rotation: (0., 0.),
+			grounded: false,
+			abilities: self.gamemode().abilities(),
+			rising_moves: 0,
+
+			teleport_pending: None,
+			teleport_count: 0,
+
+			view_distance: self.view_distance(),
+			view_center: None,
+			loaded_chunks: HashSet::new(),
+			tracked: HashMap::new(),
+			handle
 		});
+		drop(players);
+
+		self.pov_teleport(&name, SPAWN, (0., 0.));
+		Ok(())
 	}
-}
 
-#[derive(Eq, PartialEq)]
-struct Chunk {
-	data: Box<[u8]>,
-	diff_source: DiffSource,
-	pallette: Option<Palette>,
-	layer_mask: u16,
-	position: (u64, u64)
-}
+	fn remove_pov(&self, name: &str) {
+		let mut players = self.players.lock().unwrap();
+		if let Some(player) = players.remove(name) {
+			self.entities.lock().unwrap().despawn(player.entity_id);
 
-impl Chunk {
-	fn solid(position: (u64, u64), identifier: u16) -> Self {
-		Self {
-			data: Box::new([]),
-			diff_source: DiffSource::Solid(identifier),
-			pallette: None,
-			layer_mask: 0b0000000000000000,
-			position
+			// Other players stop tracking it on the next tick.
+			players.values_mut().for_each(|player|
+				player.handle.send(PovUpdate::PlayerLeave(name.into())));
 		}
 	}
-}
 
-impl Hash for Chunk {
-	fn hash<H>(&self, hasher: &mut H)
-			where H: Hasher {
-		self.position.hash(hasher)
+	fn pov_entity_id(&self, name: &str) -> Option<i32> {
+		self.players.lock().unwrap().get(name).map(|player| player.entity_id)
 	}
-}
 
-impl Borrow<(u64, u64)> for Chunk {
-	fn borrow(&self) -> &(u64, u64) {
-		&self.position
+	fn pov_view_distance(&self, name: &str, distance: u8) {
+		let mut players = self.players.lock().unwrap();
+		if let Some(player) = players.get_mut(name) {
+			player.view_distance = distance
+		}
 	}
-}
 
-#[derive(Eq, PartialEq)]
-struct Palette(u16, Box<[u8]>);
+	fn pov_edition(&self, name: &str) -> Option<Edition> {
+		self.players.lock().unwrap().get(name).map(|player| player.handle.edition())
+	}
 
-impl Palette {
-	fn bits_per_block(&self) -> u8 {
-		(self.0 as f32 + 1.).log2().ceil() as u8
+	fn pov_send(&self, name: &str, update: PovUpdate) {
+		if let Some(player) = self.players.lock().unwrap().get(name) {
+			player.handle.send(update)
+		}
 	}
 
-	fn global(&self, local: u16, global_bits_per_block: u8) -> u16 {
-		let global_bits_per_block = global_bits_per_block as u32 + 1;
-		let access = local as u32 * global_bits_per_block;
-		self.1.get_bit_range(access..access + global_bits_per_block) as u16
+	fn pov_move(&self, name: &str, movement: Movement) {
+		let (from, to, rotation, violation) = {
+			let mut players = self.players.lock().unwrap();
+			let player = match players.get_mut(name) {
+				Some(player) => player,
+				None => return
+			};
+
+			// The client hasn't caught up with where we put it yet.
+			if player.teleport_pending.is_some() {return}
+
+			let from = player.position;
+			let to = movement.position.unwrap_or(from);
+			let violation = match movement.position {
+				Some(_) => check(from, to, &player.abilities, &self.tolerances,
+					&mut player.rising_moves, &self.chunks.lock().unwrap()),
+				None => None
+			};
+
+			(from, to, movement.rotation.unwrap_or(player.rotation), violation)
+		};
+
+		match violation {
+			Some(violation) => self.event_dispatch(PlayerViolationEvent::new(
+				name.into(), violation, from, to, rotation, movement.grounded)),
+			None => self.event_dispatch(PlayerMoveEvent::new(
+				name.into(), from, to, rotation, movement.grounded))
+		}
 	}
-}
 
-#[derive(Eq, PartialEq)]
-enum DiffSource {
-	Generator(Option<u64>),
-	Solid(u16)
+	fn pov_flying(&self, name: &str, flying: bool) {
+		let mut players = self.players.lock().unwrap();
+		if let Some(player) = players.get_mut(name) {
+			player.abilities.flying = flying && player.abilities.flight_allowed
+		}
+	}
+
+	fn pov_position_set(&self, name: &str, position: EntityPos,
+			rotation: (f32, f32), grounded: bool) {
+		let mut players = self.players.lock().unwrap();
+		if let Some(player) = players.get_mut(name) {
+			player.position = position;
+			player.rotation = rotation;
+			player.grounded = grounded;
+		}
+	}
+
+	fn pov_teleport(&self, name: &str, position: EntityPos,
+			rotation: (f32, f32)) {
+		let mut players = self.players.lock().unwrap();
+		if let Some(player) = players.get_mut(name) {
+			let teleport_id = player.teleport_count;
+			player.teleport_count = player.teleport_count.wrapping_add(1);
+			player.teleport_pending = Some(teleport_id);
+
+			player.position = position;
+			player.rotation = rotation;
+			player.handle.send(PovUpdate::Teleport {position, rotation, teleport_id});
+		}
+	}
+
+	fn pov_sneaking(&self, name: &str, sneaking: bool) {
+		self.pov_metadata(name, |metadata| metadata.sneaking = sneaking)
+	}
+
+	fn pov_sprinting(&self, name: &str, sprinting: bool) {
+		self.pov_metadata(name, |metadata| metadata.sprinting = sprinting)
+	}
+
+	fn pov_chat(&self, name: &str, message: &str) {
+		match validate(message) {
+			Ok(message) if message.starts_with('/') => self.command_execute(
+				CommandSource::Player(name.into()), &message[1..]),
+			Ok(message) =>
+				self.event_dispatch(PlayerChatEvent::new(name.into(), message.into())),
+			Err(error) =>
+				self.pov_message(name, &error.to_string(), ChatPosition::System)
+		}
+	}
+
+	fn pov_tab_complete(&self, name: &str, command: &str)
+			-> (usize, Vec<Box<str>>) {
+		let source = CommandSource::Player(name.into());
+		let players = self.players.lock().unwrap().keys().cloned()
+			.collect::<Vec<_>>();
+		self.commands.lock().unwrap().suggest(command,
+			&|requirement| self.permitted(&source, requirement), &players)
+	}
+
+	fn pov_message(&self, name: &str, message: &str, position: ChatPosition) {
+		if let Some(player) = self.players.lock().unwrap().get(name) {
+			player.handle.chat(message, position)
+		}
+	}
+
+	fn pov_kick(&self, name: &str, reason: &str) {
+		if let Some(player) = self.players.lock().unwrap().get(name) {
+			player.handle.kick(reason)
+		}
+	}
+
+	fn pov_teleport_confirm(&self, name: &str, teleport_id: i32) {
+		let mut players = self.players.lock().unwrap();
+		if let Some(player) = players.get_mut(name) {
+			if player.teleport_pending == Some(teleport_id) {
+				player.teleport_pending = None
+			}
+		}
+	}
 }
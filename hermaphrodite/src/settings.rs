@@ -0,0 +1,151 @@
+use self::super::movement::Abilities;
+use std::{
+	error::Error, fmt::{Display, Formatter, Result as FMTResult},
+	ops::RangeInclusive, str::FromStr
+};
+
+/// The furthest any player may see, in chunks, unless configured otherwise.
+pub const DEFAULT_VIEW_DISTANCE: u8 = 10;
+
+/// The view distances clients support.
+pub const VIEW_DISTANCES: RangeInclusive<u8> = 2..=32;
+
+/// How a server plays. Settings can be changed while the server runs, with
+/// `Server::settings_update`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Settings {
+	pub message_of_the_day: Box<str>,
+	pub max_players: usize,
+	/// The furthest any player may see, in chunks.
+	pub view_distance: u8,
+	/// The game mode players join in.
+	pub gamemode: Gamemode,
+	pub difficulty: Difficulty,
+	/// Whether only players on the whitelist, and operators, may join.
+	pub whitelist: bool,
+	/// Whether players that aren't allowed on by the whitelist are kicked when
+	/// it changes.
+	pub enforce_whitelist: bool
+}
+
+impl Default for Settings {
+	fn default() -> Self {
+		Self {
+			message_of_the_day: "A Minecraft Server".into(),
+			max_players: 20,
+			view_distance: DEFAULT_VIEW_DISTANCE,
+			gamemode: Gamemode::Creative,
+			difficulty: Difficulty::Easy,
+			whitelist: false,
+			enforce_whitelist: false
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Gamemode {
+	Survival,
+	Creative,
+	Adventure,
+	Spectator
+}
+
+impl Gamemode {
+	/// The identifier of this game mode in the protocol.
+	pub fn id(&self) -> u8 {
+		match self {
+			Self::Survival => 0,
+			Self::Creative => 1,
+			Self::Adventure => 2,
+			Self::Spectator => 3
+		}
+	}
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Survival => "survival",
+			Self::Creative => "creative",
+			Self::Adventure => "adventure",
+			Self::Spectator => "spectator"
+		}
+	}
+
+	/// What players in this game mode may do.
+	pub fn abilities(&self) -> Abilities {
+		Abilities {
+			flight_allowed: matches!(self, Self::Creative | Self::Spectator),
+			flying: matches!(self, Self::Spectator),
+			..Abilities::default()
+		}
+	}
+}
+
+impl FromStr for Gamemode {
+	type Err = UnknownVariant;
+
+	/// Parses a game mode by it's name, like vanilla's `server.properties`.
+	fn from_str(name: &str) -> Result<Self, UnknownVariant> {
+		Ok(match name {
+			"survival" => Self::Survival,
+			"creative" => Self::Creative,
+			"adventure" => Self::Adventure,
+			"spectator" => Self::Spectator,
+			_ => return Err(UnknownVariant(name.into()))
+		})
+	}
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Difficulty {
+	Peaceful,
+	Easy,
+	Normal,
+	Hard
+}
+
+impl Difficulty {
+	/// The identifier of this difficulty in the protocol.
+	pub fn id(&self) -> u8 {
+		match self {
+			Self::Peaceful => 0,
+			Self::Easy => 1,
+			Self::Normal => 2,
+			Self::Hard => 3
+		}
+	}
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Peaceful => "peaceful",
+			Self::Easy => "easy",
+			Self::Normal => "normal",
+			Self::Hard => "hard"
+		}
+	}
+}
+
+impl FromStr for Difficulty {
+	type Err = UnknownVariant;
+
+	fn from_str(name: &str) -> Result<Self, UnknownVariant> {
+		Ok(match name {
+			"peaceful" => Self::Peaceful,
+			"easy" => Self::Easy,
+			"normal" => Self::Normal,
+			"hard" => Self::Hard,
+			_ => return Err(UnknownVariant(name.into()))
+		})
+	}
+}
+
+/// A name that isn't any game mode or difficulty.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnknownVariant(pub Box<str>);
+
+impl Display for UnknownVariant {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FMTResult {
+		write!(f, "Unknown name {:?}.", self.0)
+	}
+}
+
+impl Error for UnknownVariant {}
@@ -0,0 +1,375 @@
+use self::super::block::BlockState;
+
+/// A block as Bedrock Edition's legacy format knows it, by a numeric identifier
+/// and a data value.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct BedrockBlock {
+	pub id: u8,
+	pub data: u8
+}
+
+impl BedrockBlock {
+	pub const AIR: Self = Self {id: 0, data: 0};
+
+	/// What blocks missing from Bedrock Edition are shown as, so the world at
+	/// least keeps it's shape.
+	pub const FALLBACK: Self = Self {id: 1, data: 0};
+}
+
+/// An item as Bedrock Edition knows it, by a numeric identifier and a damage
+/// value.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct BedrockItem {
+	pub id: i16,
+	pub damage: i16
+}
+
+impl BedrockItem {
+	pub const AIR: Self = Self {id: 0, damage: 0};
+
+	/// What items missing from Bedrock Edition are shown as, which is the block
+	/// Bedrock Edition itself uses for things it doesn't know about, rather than
+	/// an empty slot.
+	pub const FALLBACK: Self = Self {id: 248, damage: 0};
+}
+
+/// The property of a Java Edition block that makes up the rest of it's
+/// Bedrock Edition data value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Extra {
+	None,
+	/// How far a fluid has flowed, in the lowest four bits.
+	Level,
+	/// Which way a log lies, in the third and fourth bits.
+	Axis,
+	/// How many layers of snow there are, less one.
+	Layers,
+	/// How far a plant has grown.
+	Age,
+	/// Which way stairs face in the lowest two bits, and whether they're upside
+	/// down in the third.
+	Stairs
+}
+
+impl Extra {
+	/// The bits of a data value that this property takes up.
+	fn mask(self) -> u8 {
+		match self {
+			Self::None => 0,
+			Self::Level | Self::Age => 0xF,
+			Self::Axis => 0xC,
+			Self::Layers | Self::Stairs => 0x7
+		}
+	}
+
+	fn encode(self, state: BlockState) -> u8 {
+		let bits = match self {
+			Self::None => 0,
+			Self::Level => state.property::<u8>("level").unwrap_or(0),
+			Self::Axis => match state.get("axis") {
+				Some("x") => 4,
+				Some("z") => 8,
+				_ => 0
+			},
+			Self::Layers => state.property::<u8>("layers").unwrap_or(1) - 1,
+			Self::Age => state.property::<u8>("age").unwrap_or(0),
+			Self::Stairs => {
+				let facing = STAIR_FACINGS.iter()
+					.position(|facing| state.get("facing") == Some(facing));
+				facing.unwrap_or(0) as u8 | ((state.get("half") == Some("top")) as u8) << 2
+			}
+		};
+		bits & self.mask()
+	}
+
+	fn decode(self, state: BlockState, data: u8) -> BlockState {
+		let bits = data & self.mask();
+		let changed = match self {
+			Self::None => None,
+			Self::Level => state.with("level", &bits.to_string()),
+			Self::Axis => state.with("axis", match bits {
+				4 => "x",
+				8 => "z",
+				_ => "y"
+			}),
+			Self::Layers => state.with("layers", &(bits + 1).to_string()),
+			Self::Age => state.with("age", &bits.to_string()),
+			Self::Stairs => state
+				.with("facing", STAIR_FACINGS[bits as usize & 3])
+				.and_then(|state| state.with("half", match bits & 4 {
+					0 => "bottom",
+					_ => "top"
+				}))
+		};
+		changed.unwrap_or(state)
+	}
+}
+
+/// The directions stairs face in, in the order of Bedrock Edition's data
+/// values.
+const STAIR_FACINGS: [&str; 4] = ["east", "west", "south", "north"];
+
+/// Java Edition blocks, the Bedrock Edition blocks they're shown as, and the
+/// property that fills in the rest of the data value. Where several Java
+/// Edition blocks share a Bedrock Edition block, the first one listed is what
+/// Bedrock Edition's block translates back to.
+static BLOCKS: &[(&str, u8, u8, Extra)] = &[
+	("air", 0, 0, Extra::None),
+	("cave_air", 0, 0, Extra::None),
+	("void_air", 0, 0, Extra::None),
+	("stone", 1, 0, Extra::None),
+	("granite", 1, 1, Extra::None),
+	("polished_granite", 1, 2, Extra::None),
+	("diorite", 1, 3, Extra::None),
+	("polished_diorite", 1, 4, Extra::None),
+	("andesite", 1, 5, Extra::None),
+	("polished_andesite", 1, 6, Extra::None),
+	("grass_block", 2, 0, Extra::None),
+	("dirt", 3, 0, Extra::None),
+	("coarse_dirt", 3, 1, Extra::None),
+	("cobblestone", 4, 0, Extra::None),
+	("oak_planks", 5, 0, Extra::None),
+	("spruce_planks", 5, 1, Extra::None),
+	("birch_planks", 5, 2, Extra::None),
+	("jungle_planks", 5, 3, Extra::None),
+	("acacia_planks", 5, 4, Extra::None),
+	("dark_oak_planks", 5, 5, Extra::None),
+	("bedrock", 7, 0, Extra::None),
+	("water", 9, 0, Extra::Level),
+	("lava", 11, 0, Extra::Level),
+	("sand", 12, 0, Extra::None),
+	("red_sand", 12, 1, Extra::None),
+	("gravel", 13, 0, Extra::None),
+	("gold_ore", 14, 0, Extra::None),
+	("iron_ore", 15, 0, Extra::None),
+	("coal_ore", 16, 0, Extra::None),
+	("oak_log", 17, 0, Extra::Axis),
+	("spruce_log", 17, 1, Extra::Axis),
+	("birch_log", 17, 2, Extra::Axis),
+	("jungle_log", 17, 3, Extra::Axis),
+	("oak_leaves", 18, 0, Extra::None),
+	("spruce_leaves", 18, 1, Extra::None),
+	("birch_leaves", 18, 2, Extra::None),
+	("jungle_leaves", 18, 3, Extra::None),
+	("sponge", 19, 0, Extra::None),
+	("glass", 20, 0, Extra::None),
+	("lapis_ore", 21, 0, Extra::None),
+	("lapis_block", 22, 0, Extra::None),
+	("sandstone", 24, 0, Extra::None),
+	("grass", 31, 1, Extra::None),
+	("fern", 31, 2, Extra::None),
+	("dead_bush", 32, 0, Extra::None),
+	("white_wool", 35, 0, Extra::None),
+	("orange_wool", 35, 1, Extra::None),
+	("magenta_wool", 35, 2, Extra::None),
+	("light_blue_wool", 35, 3, Extra::None),
+	("yellow_wool", 35, 4, Extra::None),
+	("lime_wool", 35, 5, Extra::None),
+	("pink_wool", 35, 6, Extra::None),
+	("gray_wool", 35, 7, Extra::None),
+	("light_gray_wool", 35, 8, Extra::None),
+	("cyan_wool", 35, 9, Extra::None),
+	("purple_wool", 35, 10, Extra::None),
+	("blue_wool", 35, 11, Extra::None),
+	("brown_wool", 35, 12, Extra::None),
+	("green_wool", 35, 13, Extra::None),
+	("red_wool", 35, 14, Extra::None),
+	("black_wool", 35, 15, Extra::None),
+	("dandelion", 37, 0, Extra::None),
+	("poppy", 38, 0, Extra::None),
+	("gold_block", 41, 0, Extra::None),
+	("iron_block", 42, 0, Extra::None),
+	("bricks", 45, 0, Extra::None),
+	("tnt", 46, 0, Extra::None),
+	("bookshelf", 47, 0, Extra::None),
+	("mossy_cobblestone", 48, 0, Extra::None),
+	("obsidian", 49, 0, Extra::None),
+	("torch", 50, 5, Extra::None),
+	("spawner", 52, 0, Extra::None),
+	("oak_stairs", 53, 0, Extra::Stairs),
+	("chest", 54, 0, Extra::None),
+	("diamond_ore", 56, 0, Extra::None),
+	("diamond_block", 57, 0, Extra::None),
+	("crafting_table", 58, 0, Extra::None),
+	("wheat", 59, 0, Extra::Age),
+	("farmland", 60, 0, Extra::None),
+	("furnace", 61, 0, Extra::None),
+	("redstone_ore", 73, 0, Extra::None),
+	("snow", 78, 0, Extra::Layers),
+	("ice", 79, 0, Extra::None),
+	("snow_block", 80, 0, Extra::None),
+	("cactus", 81, 0, Extra::Age),
+	("clay", 82, 0, Extra::None),
+	("sugar_cane", 83, 0, Extra::Age),
+	("pumpkin", 86, 0, Extra::None),
+	("netherrack", 87, 0, Extra::None),
+	("soul_sand", 88, 0, Extra::None),
+	("glowstone", 89, 0, Extra::None),
+	("stone_bricks", 98, 0, Extra::None),
+	("mossy_stone_bricks", 98, 1, Extra::None),
+	("cracked_stone_bricks", 98, 2, Extra::None),
+	("chiseled_stone_bricks", 98, 3, Extra::None),
+	("melon", 103, 0, Extra::None),
+	("mycelium", 110, 0, Extra::None),
+	("nether_bricks", 112, 0, Extra::None),
+	("end_stone", 121, 0, Extra::None),
+	("emerald_ore", 129, 0, Extra::None),
+	("emerald_block", 133, 0, Extra::None),
+	("redstone_block", 152, 0, Extra::None),
+	("nether_quartz_ore", 153, 0, Extra::None),
+	("quartz_block", 155, 0, Extra::None),
+	("acacia_leaves", 161, 0, Extra::None),
+	("dark_oak_leaves", 161, 1, Extra::None),
+	("acacia_log", 162, 0, Extra::Axis),
+	("dark_oak_log", 162, 1, Extra::Axis),
+	("terracotta", 172, 0, Extra::None),
+	("coal_block", 173, 0, Extra::None),
+	("packed_ice", 174, 0, Extra::None),
+	("podzol", 243, 0, Extra::None)
+];
+
+/// Java Edition items that aren't blocks, and the Bedrock Edition items
+/// they're shown as. Block items are translated like the blocks they place.
+static ITEMS: &[(&str, i16, i16)] = &[
+	("iron_shovel", 256, 0),
+	("iron_pickaxe", 257, 0),
+	("iron_axe", 258, 0),
+	("flint_and_steel", 259, 0),
+	("apple", 260, 0),
+	("bow", 261, 0),
+	("arrow", 262, 0),
+	("coal", 263, 0),
+	("charcoal", 263, 1),
+	("diamond", 264, 0),
+	("iron_ingot", 265, 0),
+	("gold_ingot", 266, 0),
+	("iron_sword", 267, 0),
+	("wooden_sword", 268, 0),
+	("wooden_shovel", 269, 0),
+	("wooden_pickaxe", 270, 0),
+	("wooden_axe", 271, 0),
+	("stone_sword", 272, 0),
+	("stone_shovel", 273, 0),
+	("stone_pickaxe", 274, 0),
+	("stone_axe", 275, 0),
+	("diamond_sword", 276, 0),
+	("diamond_shovel", 277, 0),
+	("diamond_pickaxe", 278, 0),
+	("diamond_axe", 279, 0),
+	("stick", 280, 0),
+	("bowl", 281, 0),
+	("mushroom_stew", 282, 0),
+	("wheat_seeds", 295, 0),
+	("wheat", 296, 0),
+	("bread", 297, 0),
+	("porkchop", 319, 0),
+	("cooked_porkchop", 320, 0),
+	("redstone", 331, 0),
+	("snowball", 332, 0),
+	("compass", 345, 0),
+	("clock", 347, 0),
+	("beef", 363, 0),
+	("cooked_beef", 364, 0),
+	("emerald", 388, 0)
+];
+
+/// Java Edition biomes and the Bedrock Edition biome identifiers they're shown
+/// as.
+static BIOMES: &[(&str, u8)] = &[
+	("ocean", 0),
+	("plains", 1),
+	("desert", 2),
+	("mountains", 3),
+	("forest", 4),
+	("snowy_tundra", 12),
+	("beach", 16)
+];
+
+/// Returns the Bedrock Edition block a Java Edition block state is shown as,
+/// or stone if Bedrock Edition doesn't have it.
+pub fn bedrock_block(state: BlockState) -> BedrockBlock {
+	let name = state.block().name.trim_start_matches("minecraft:");
+	match BLOCKS.iter().find(|(java, ..)| *java == name) {
+		Some((_, id, data, extra)) =>
+			BedrockBlock {id: *id, data: data | extra.encode(state)},
+		None => BedrockBlock::FALLBACK
+	}
+}
+
+/// Returns the Java Edition block state a Bedrock Edition block is shown as.
+/// Data values that aren't known fall back to the block's first variant, and
+/// blocks that aren't known, or aren't in the registry, to stone.
+pub fn java_block(block: BedrockBlock) -> BlockState {
+	let variants = || BLOCKS.iter()
+		.filter(|(_, id, ..)| *id == block.id)
+		.filter_map(|(java, _, data, extra)|
+			BlockState::default_of(&format!("minecraft:{}", java))
+				.map(|state| (state, *data, *extra)));
+	let variant = variants()
+		.find(|(_, data, extra)| block.data & !extra.mask() == *data)
+		.or_else(|| variants().next());
+
+	match variant {
+		Some((state, _, extra)) => extra.decode(state, block.data),
+		None => BlockState::default_of("minecraft:stone").unwrap()
+	}
+}
+
+/// Returns the Bedrock Edition item a Java Edition item, named like
+/// `minecraft:stick`, is shown as.
+pub fn bedrock_item(name: &str) -> BedrockItem {
+	let name = name.trim_start_matches("minecraft:");
+	if let Some((_, id, damage)) = ITEMS.iter().find(|(java, ..)| *java == name) {
+		return BedrockItem {id: *id, damage: *damage}
+	}
+
+	match BLOCKS.iter().find(|(java, ..)| *java == name) {
+		Some((_, 0, ..)) => BedrockItem::AIR,
+		Some((_, id, data, _)) => BedrockItem {id: *id as i16, damage: *data as i16},
+		None => BedrockItem::FALLBACK
+	}
+}
+
+/// Returns the name of the Java Edition item a Bedrock Edition item is shown
+/// as, or `None` if it's not known, since making up an item would let clients
+/// conjure things from thin air.
+pub fn java_item(item: BedrockItem) -> Option<String> {
+	if item.id == 0 {return Some("minecraft:air".to_owned())}
+
+	let name = ITEMS.iter()
+		.find(|(_, id, damage)| *id == item.id && *damage == item.damage)
+		.map(|(java, ..)| *java)
+		.or_else(|| BLOCKS.iter()
+			.find(|(_, id, data, _)| *id as i16 == item.id && *data as i16 == item.damage)
+			.map(|(java, ..)| *java))?;
+	Some(format!("minecraft:{}", name))
+}
+
+/// Returns the Bedrock Edition biome identifier a Java Edition biome, named
+/// without it's namespace, is shown as, or plains if Bedrock Edition doesn't
+/// have it.
+pub fn bedrock_biome(name: &str) -> u8 {
+	BIOMES.iter().find(|(java, _)| *java == name).map_or(1, |(_, id)| *id)
+}
+
+/// Returns the name of the Java Edition biome a Bedrock Edition biome
+/// identifier is shown as, or plains if it isn't known.
+pub fn java_biome(id: u8) -> &'static str {
+	BIOMES.iter().find(|(_, bedrock)| *bedrock == id).map_or("plains", |(java, _)| java)
+}
+
+#[cfg(test)]
+mod tests {
+	use self::super::*;
+
+	#[test]
+	fn translated_blocks_are_in_the_registry() {
+		BLOCKS.iter().for_each(|(java, id, data, _)| {
+			let state = BlockState::default_of(java)
+				.unwrap_or_else(|| panic!("{} isn't in the registry.", java));
+			let bedrock = bedrock_block(state);
+			assert_eq!(bedrock.id, *id, "{}", java);
+			assert_eq!(bedrock.data & data, *data, "{}", java);
+		});
+	}
+}
@@ -1,4 +1,8 @@
-use self::super::types::{Bound, Read, State, Write};
+use self::super::types::{Bound, MetadataValue, Read, State, Write};
+use hermaphrodite::{
+	command::{GraphNode, NodeKind, Parser, StringKind},
+	palette::PalettedContainer, position::ChunkPos
+};
 use serde::{ser::{SerializeMap, SerializeSeq, Serializer}, Serialize};
 use serde_json::to_string;
 use std::{
@@ -27,6 +31,7 @@ pub enum Packet {
 	StatusPing(StatusPing),
 	StatusPong(StatusPong),
 	LoginStart(LoginStart),
+	LoginDisconnect(LoginDisconnect),
 	LoginCompression(LoginCompression),
 	LoginSuccess(LoginSuccess),
 	PlayTeleportConfirm(PlayTeleportConfirm),
@@ -39,7 +44,25 @@ pub enum Packet {
 	PlayPlayerAbilities(PlayPlayerAbilities),
 	PlayChunkData(PlayChunkData),
 	PlayJoinGame(PlayJoinGame),
-	PlayPlayerPositionRotationServer(PlayPlayerPositionRotationServer)
+	PlayPlayerPositionRotationServer(PlayPlayerPositionRotationServer),
+	PlayUnloadChunk(PlayUnloadChunk),
+	PlayUpdateViewPosition(PlayUpdateViewPosition),
+	PlaySpawnPlayer(PlaySpawnPlayer),
+	PlayEntityPosition(PlayEntityPosition),
+	PlayEntityRotation(PlayEntityRotation),
+	PlayPlayerInfo(PlayPlayerInfo),
+	PlayDestroyEntities(PlayDestroyEntities),
+	PlayEntityHeadLook(PlayEntityHeadLook),
+	PlayEntityTeleport(PlayEntityTeleport),
+	PlayEntityAction(PlayEntityAction),
+	PlayEntityMetadata(PlayEntityMetadata),
+	PlayChatMessageServer(PlayChatMessageServer),
+	PlayTabComplete(PlayTabComplete),
+	PlayTabCompleteServer(PlayTabCompleteServer),
+	PlayDeclareCommands(PlayDeclareCommands),
+	PlayDisconnect(PlayDisconnect),
+	PlayServerDifficulty(PlayServerDifficulty),
+	PlayUpdateViewDistance(PlayUpdateViewDistance)
 }
 
 macro constant_fetcher($name:ident(), $constant:ident, $result:ident) {
@@ -62,6 +85,8 @@ macro constant_fetcher($name:ident(), $constant:ident, $result:ident) {
 			// Login
 			Self::LoginStart(_) =>
 				LoginStart::$constant,
+			Self::LoginDisconnect(_) =>
+				LoginDisconnect::$constant,
 			Self::LoginCompression(_) =>
 				LoginCompression::$constant,
 			Self::LoginSuccess(_) =>
@@ -89,7 +114,43 @@ macro constant_fetcher($name:ident(), $constant:ident, $result:ident) {
 			Self::PlayJoinGame(_) =>
 				PlayJoinGame::$constant,
 			Self::PlayPlayerPositionRotationServer(_) =>
-				PlayPlayerPositionRotationServer::$constant
+				PlayPlayerPositionRotationServer::$constant,
+			Self::PlayUnloadChunk(_) =>
+				PlayUnloadChunk::$constant,
+			Self::PlayUpdateViewPosition(_) =>
+				PlayUpdateViewPosition::$constant,
+			Self::PlaySpawnPlayer(_) =>
+				PlaySpawnPlayer::$constant,
+			Self::PlayEntityPosition(_) =>
+				PlayEntityPosition::$constant,
+			Self::PlayEntityRotation(_) =>
+				PlayEntityRotation::$constant,
+			Self::PlayPlayerInfo(_) =>
+				PlayPlayerInfo::$constant,
+			Self::PlayDestroyEntities(_) =>
+				PlayDestroyEntities::$constant,
+			Self::PlayEntityHeadLook(_) =>
+				PlayEntityHeadLook::$constant,
+			Self::PlayEntityTeleport(_) =>
+				PlayEntityTeleport::$constant,
+			Self::PlayEntityAction(_) =>
+				PlayEntityAction::$constant,
+			Self::PlayEntityMetadata(_) =>
+				PlayEntityMetadata::$constant,
+			Self::PlayChatMessageServer(_) =>
+				PlayChatMessageServer::$constant,
+			Self::PlayTabComplete(_) =>
+				PlayTabComplete::$constant,
+			Self::PlayTabCompleteServer(_) =>
+				PlayTabCompleteServer::$constant,
+			Self::PlayDeclareCommands(_) =>
+				PlayDeclareCommands::$constant,
+			Self::PlayDisconnect(_) =>
+				PlayDisconnect::$constant,
+			Self::PlayServerDifficulty(_) =>
+				PlayServerDifficulty::$constant,
+			Self::PlayUpdateViewDistance(_) =>
+				PlayUpdateViewDistance::$constant
 		}
 	}
 }
@@ -114,6 +175,8 @@ macro trait_impl($traitt:ident::$name:ident($($arg:ident: $arg_ty:ty),*), $resul
 			// Login
 			Self::LoginStart(packet) =>
 				$traitt::$name(packet, $($arg),*),
+			Self::LoginDisconnect(packet) =>
+				$traitt::$name(packet, $($arg),*),
 			Self::LoginCompression(packet) =>
 				$traitt::$name(packet, $($arg),*),
 			Self::LoginSuccess(packet) =>
@@ -141,6 +204,42 @@ macro trait_impl($traitt:ident::$name:ident($($arg:ident: $arg_ty:ty),*), $resul
 			Self::PlayJoinGame(packet) =>
 				$traitt::$name(packet, $($arg),*),
 			Self::PlayPlayerPositionRotationServer(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlayUnloadChunk(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlayUpdateViewPosition(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlaySpawnPlayer(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlayEntityPosition(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlayEntityRotation(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlayPlayerInfo(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlayDestroyEntities(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlayEntityHeadLook(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlayEntityTeleport(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlayEntityAction(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlayEntityMetadata(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlayChatMessageServer(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlayTabComplete(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlayTabCompleteServer(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlayDeclareCommands(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlayDisconnect(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlayServerDifficulty(packet) =>
+				$traitt::$name(packet, $($arg),*),
+			Self::PlayUpdateViewDistance(packet) =>
 				$traitt::$name(packet, $($arg),*)
 		}
 	}
@@ -172,6 +271,8 @@ impl Packet {
 			// Login
 			(LoginStart::PACKET_STATE, LoginStart::PACKET_BOUND, LoginStart::PACKET_ID) =>
 				LoginStart::deserialize(len, reader),
+			(LoginDisconnect::PACKET_STATE, LoginDisconnect::PACKET_BOUND, LoginDisconnect::PACKET_ID) =>
+				LoginDisconnect::deserialize(len, reader),
 			(LoginCompression::PACKET_STATE, LoginCompression::PACKET_BOUND, LoginCompression::PACKET_ID) =>
 				LoginCompression::deserialize(len, reader),
 			(LoginSuccess::PACKET_STATE, LoginSuccess::PACKET_BOUND, LoginSuccess::PACKET_ID) =>
@@ -200,6 +301,42 @@ impl Packet {
 				PlayJoinGame::deserialize(len, reader),
 			(PlayPlayerPositionRotationServer::PACKET_STATE, PlayPlayerPositionRotationServer::PACKET_BOUND, PlayPlayerPositionRotationServer::PACKET_ID) =>
 				PlayPlayerPositionRotationServer::deserialize(len, reader),
+			(PlayUnloadChunk::PACKET_STATE, PlayUnloadChunk::PACKET_BOUND, PlayUnloadChunk::PACKET_ID) =>
+				PlayUnloadChunk::deserialize(len, reader),
+			(PlayUpdateViewPosition::PACKET_STATE, PlayUpdateViewPosition::PACKET_BOUND, PlayUpdateViewPosition::PACKET_ID) =>
+				PlayUpdateViewPosition::deserialize(len, reader),
+			(PlaySpawnPlayer::PACKET_STATE, PlaySpawnPlayer::PACKET_BOUND, PlaySpawnPlayer::PACKET_ID) =>
+				PlaySpawnPlayer::deserialize(len, reader),
+			(PlayEntityPosition::PACKET_STATE, PlayEntityPosition::PACKET_BOUND, PlayEntityPosition::PACKET_ID) =>
+				PlayEntityPosition::deserialize(len, reader),
+			(PlayEntityRotation::PACKET_STATE, PlayEntityRotation::PACKET_BOUND, PlayEntityRotation::PACKET_ID) =>
+				PlayEntityRotation::deserialize(len, reader),
+			(PlayPlayerInfo::PACKET_STATE, PlayPlayerInfo::PACKET_BOUND, PlayPlayerInfo::PACKET_ID) =>
+				PlayPlayerInfo::deserialize(len, reader),
+			(PlayDestroyEntities::PACKET_STATE, PlayDestroyEntities::PACKET_BOUND, PlayDestroyEntities::PACKET_ID) =>
+				PlayDestroyEntities::deserialize(len, reader),
+			(PlayEntityHeadLook::PACKET_STATE, PlayEntityHeadLook::PACKET_BOUND, PlayEntityHeadLook::PACKET_ID) =>
+				PlayEntityHeadLook::deserialize(len, reader),
+			(PlayEntityTeleport::PACKET_STATE, PlayEntityTeleport::PACKET_BOUND, PlayEntityTeleport::PACKET_ID) =>
+				PlayEntityTeleport::deserialize(len, reader),
+			(PlayEntityAction::PACKET_STATE, PlayEntityAction::PACKET_BOUND, PlayEntityAction::PACKET_ID) =>
+				PlayEntityAction::deserialize(len, reader),
+			(PlayEntityMetadata::PACKET_STATE, PlayEntityMetadata::PACKET_BOUND, PlayEntityMetadata::PACKET_ID) =>
+				PlayEntityMetadata::deserialize(len, reader),
+			(PlayChatMessageServer::PACKET_STATE, PlayChatMessageServer::PACKET_BOUND, PlayChatMessageServer::PACKET_ID) =>
+				PlayChatMessageServer::deserialize(len, reader),
+			(PlayTabComplete::PACKET_STATE, PlayTabComplete::PACKET_BOUND, PlayTabComplete::PACKET_ID) =>
+				PlayTabComplete::deserialize(len, reader),
+			(PlayTabCompleteServer::PACKET_STATE, PlayTabCompleteServer::PACKET_BOUND, PlayTabCompleteServer::PACKET_ID) =>
+				PlayTabCompleteServer::deserialize(len, reader),
+			(PlayDeclareCommands::PACKET_STATE, PlayDeclareCommands::PACKET_BOUND, PlayDeclareCommands::PACKET_ID) =>
+				PlayDeclareCommands::deserialize(len, reader),
+			(PlayDisconnect::PACKET_STATE, PlayDisconnect::PACKET_BOUND, PlayDisconnect::PACKET_ID) =>
+				PlayDisconnect::deserialize(len, reader),
+			(PlayServerDifficulty::PACKET_STATE, PlayServerDifficulty::PACKET_BOUND, PlayServerDifficulty::PACKET_ID) =>
+				PlayServerDifficulty::deserialize(len, reader),
+			(PlayUpdateViewDistance::PACKET_STATE, PlayUpdateViewDistance::PACKET_BOUND, PlayUpdateViewDistance::PACKET_ID) =>
+				PlayUpdateViewDistance::deserialize(len, reader),
 
 			// ???
 			_ => return None
@@ -216,6 +353,7 @@ impl Debug for Packet {
 			Self::StatusPing(packet) => write!(f, "{:?}", packet),
 			Self::StatusPong(packet) => write!(f, "{:?}", packet),
 			Self::LoginStart(packet) => write!(f, "{:?}", packet),
+			Self::LoginDisconnect(packet) => write!(f, "{:?}", packet),
 			Self::LoginCompression(packet) => write!(f, "{:?}", packet),
 			Self::LoginSuccess(packet) => write!(f, "{:?}", packet),
 			Self::PlayTeleportConfirm(packet) => write!(f, "{:?}", packet),
@@ -228,7 +366,25 @@ impl Debug for Packet {
 			Self::PlayPlayerAbilities(packet) => write!(f, "{:?}", packet),
 			Self::PlayChunkData(packet) => write!(f, "{:?}", packet),
 			Self::PlayJoinGame(packet) => write!(f, "{:?}", packet),
-			Self::PlayPlayerPositionRotationServer(packet) => write!(f, "{:?}", packet)
+			Self::PlayPlayerPositionRotationServer(packet) => write!(f, "{:?}", packet),
+			Self::PlayUnloadChunk(packet) => write!(f, "{:?}", packet),
+			Self::PlayUpdateViewPosition(packet) => write!(f, "{:?}", packet),
+			Self::PlaySpawnPlayer(packet) => write!(f, "{:?}", packet),
+			Self::PlayEntityPosition(packet) => write!(f, "{:?}", packet),
+			Self::PlayEntityRotation(packet) => write!(f, "{:?}", packet),
+			Self::PlayPlayerInfo(packet) => write!(f, "{:?}", packet),
+			Self::PlayDestroyEntities(packet) => write!(f, "{:?}", packet),
+			Self::PlayEntityHeadLook(packet) => write!(f, "{:?}", packet),
+			Self::PlayEntityTeleport(packet) => write!(f, "{:?}", packet),
+			Self::PlayEntityAction(packet) => write!(f, "{:?}", packet),
+			Self::PlayEntityMetadata(packet) => write!(f, "{:?}", packet),
+			Self::PlayChatMessageServer(packet) => write!(f, "{:?}", packet),
+			Self::PlayTabComplete(packet) => write!(f, "{:?}", packet),
+			Self::PlayTabCompleteServer(packet) => write!(f, "{:?}", packet),
+			Self::PlayDeclareCommands(packet) => write!(f, "{:?}", packet),
+			Self::PlayDisconnect(packet) => write!(f, "{:?}", packet),
+			Self::PlayServerDifficulty(packet) => write!(f, "{:?}", packet),
+			Self::PlayUpdateViewDistance(packet) => write!(f, "{:?}", packet)
 		}
 	}
 }
@@ -449,6 +605,30 @@ impl Into<Packet> for LoginStart {
 	}
 }
 
+/// Why the client can't join, as a chat component.
+#[derive(Clone, Debug)]
+pub struct LoginDisconnect(pub String);
+
+impl PacketLiterate for LoginDisconnect {
+	const PACKET_STATE: State = State::Login;
+	const PACKET_BOUND: Bound = Bound::Client;
+	const PACKET_ID: u32 = 0;
+
+	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
+		writer.string(&self.0)
+	}
+
+	fn deserialize(_len: usize, _reader: &mut impl Read) -> Result<Packet> {
+		todo!()
+	}
+}
+
+impl Into<Packet> for LoginDisconnect {
+	fn into(self) -> Packet {
+		Packet::LoginDisconnect(self)
+	}
+}
+
 #[derive(Clone, Debug)]
 pub struct LoginCompression(pub u32);
 
@@ -721,7 +901,7 @@ impl Into<Packet> for PlayPlayerRotationClient {
 }
 
 #[derive(Clone, Debug)]
-pub struct PlayPlayerAbilities(u8);
+pub struct PlayPlayerAbilities(pub u8);
 
 impl PacketLiterate for PlayPlayerAbilities {
 	const PACKET_STATE: State = State::Play;
@@ -745,9 +925,10 @@ impl Into<Packet> for PlayPlayerAbilities {
 
 #[derive(Clone, Debug)]
 pub struct PlayChunkData {
-	pub position: (i64, i64),
-	//chunk_mask: u16,
-	pub height_map: HeightMap
+	pub position: ChunkPos,
+	pub height_map: HeightMap,
+	pub biomes: Vec<u32>,
+	pub sections: Vec<Option<PalettedContainer>>
 }
 
 impl PacketLiterate for PlayChunkData {
@@ -756,20 +937,37 @@ impl PacketLiterate for PlayChunkData {
 	const PACKET_ID: u32 = 32;
 
 	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
-		writer.int(self.position.0 as i32)?;
-		writer.int(self.position.1 as i32)?;
-		writer.bool(true)?;
-		writer.variable_integer(0)?;
+		writer.int(self.position.x)?;
+		writer.int(self.position.z)?;
+		writer.bool(true)?; // Full chunk.
+
+		let mask = self.sections.iter().enumerate()
+			.filter(|(_, section)| section.is_some())
+			.fold(0, |mask, (index, _)| mask | 1 << index);
+		writer.variable_integer(mask)?;
 		writer.nbt(&self.height_map, "")?;
-		writer.variable_integer(1024)?;
-		(0..1024).try_for_each(|_| {
-			writer.variable_integer(0)
+
+		writer.variable_integer(self.biomes.len() as i32)?;
+		self.biomes.iter()
+			.try_for_each(|biome| writer.variable_integer(*biome as i32))?;
+
+		let mut data = Vec::new();
+		self.sections.iter().flatten().try_for_each(|section| {
+			data.short(section.count_not(0) as i16)?;
+			data.unsigned_byte(section.bits_per_entry())?;
+			if let Some(palette) = section.palette() {
+				data.variable_integer(palette.len() as i32)?;
+				palette.iter()
+					.try_for_each(|entry| data.variable_integer(*entry as i32))?;
+			}
+
+			data.variable_integer(section.data().len() as i32)?;
+			section.data().iter().try_for_each(|long| data.long(*long as i64))
 		})?;
-		println!("bruh");
-		writer.variable_integer(0)?;
-		// no data
-		writer.variable_integer(0)
-		// no block entities
+		writer.variable_integer(data.len() as i32)?;
+		writer.bytes(&data)?;
+
+		writer.variable_integer(0) // No block entities.
 	}
 
 	fn deserialize(_len: usize, _reader: &mut impl Read) -> Result<Packet> {
@@ -877,6 +1075,638 @@ impl Into<Packet> for PlayPlayerPositionRotationServer {
 	}
 }
 
+#[derive(Clone, Debug)]
+pub struct PlayUnloadChunk(pub ChunkPos);
+
+impl PacketLiterate for PlayUnloadChunk {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Client;
+	const PACKET_ID: u32 = 28;
+
+	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
+		writer.int(self.0.x)?;
+		writer.int(self.0.z)
+	}
+
+	fn deserialize(_len: usize, reader: &mut impl Read) -> Result<Packet> {
+		Ok(Self(ChunkPos::new(reader.int()?, reader.int()?)).into())
+	}
+}
+
+impl Into<Packet> for PlayUnloadChunk {
+	fn into(self) -> Packet {
+		Packet::PlayUnloadChunk(self)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayUpdateViewPosition(pub ChunkPos);
+
+impl PacketLiterate for PlayUpdateViewPosition {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Client;
+	const PACKET_ID: u32 = 64;
+
+	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
+		writer.variable_integer(self.0.x)?;
+		writer.variable_integer(self.0.z)
+	}
+
+	fn deserialize(_len: usize, reader: &mut impl Read) -> Result<Packet> {
+		Ok(Self(ChunkPos::new(
+			reader.variable_integer()?.0, reader.variable_integer()?.0)).into())
+	}
+}
+
+impl Into<Packet> for PlayUpdateViewPosition {
+	fn into(self) -> Packet {
+		Packet::PlayUpdateViewPosition(self)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayUpdateViewDistance(pub u8);
+
+impl PacketLiterate for PlayUpdateViewDistance {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Client;
+	const PACKET_ID: u32 = 65;
+
+	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
+		writer.variable_integer(self.0 as i32)
+	}
+
+	fn deserialize(_len: usize, _reader: &mut impl Read) -> Result<Packet> {
+		todo!()
+	}
+}
+
+impl Into<Packet> for PlayUpdateViewDistance {
+	fn into(self) -> Packet {
+		Packet::PlayUpdateViewDistance(self)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct PlaySpawnPlayer {
+	pub entity_id: i32,
+	pub uuid: u128,
+	pub x: f64,
+	pub y: f64,
+	pub z: f64,
+	pub yaw: f32,
+	pub pitch: f32
+}
+
+impl PacketLiterate for PlaySpawnPlayer {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Client;
+	const PACKET_ID: u32 = 4;
+
+	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
+		writer.variable_integer(self.entity_id)?;
+		writer.uuid(self.uuid)?;
+		writer.double(self.x)?;
+		writer.double(self.y)?;
+		writer.double(self.z)?;
+		writer.angle(self.yaw)?;
+		writer.angle(self.pitch)
+	}
+
+	fn deserialize(_len: usize, _reader: &mut impl Read) -> Result<Packet> {
+		todo!()
+	}
+}
+
+impl Into<Packet> for PlaySpawnPlayer {
+	fn into(self) -> Packet {
+		Packet::PlaySpawnPlayer(self)
+	}
+}
+
+/// Moves an entity by less than eight blocks along every axis. Deltas are in
+/// 4096ths of a block.
+#[derive(Clone, Debug)]
+pub struct PlayEntityPosition {
+	pub entity_id: i32,
+	pub delta_x: i16,
+	pub delta_y: i16,
+	pub delta_z: i16,
+	pub grounded: bool
+}
+
+impl PacketLiterate for PlayEntityPosition {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Client;
+	const PACKET_ID: u32 = 39;
+
+	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
+		writer.variable_integer(self.entity_id)?;
+		writer.short(self.delta_x)?;
+		writer.short(self.delta_y)?;
+		writer.short(self.delta_z)?;
+		writer.bool(self.grounded)
+	}
+
+	fn deserialize(_len: usize, _reader: &mut impl Read) -> Result<Packet> {
+		todo!()
+	}
+}
+
+impl Into<Packet> for PlayEntityPosition {
+	fn into(self) -> Packet {
+		Packet::PlayEntityPosition(self)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayEntityRotation {
+	pub entity_id: i32,
+	pub yaw: f32,
+	pub pitch: f32,
+	pub grounded: bool
+}
+
+impl PacketLiterate for PlayEntityRotation {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Client;
+	const PACKET_ID: u32 = 41;
+
+	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
+		writer.variable_integer(self.entity_id)?;
+		writer.angle(self.yaw)?;
+		writer.angle(self.pitch)?;
+		writer.bool(self.grounded)
+	}
+
+	fn deserialize(_len: usize, _reader: &mut impl Read) -> Result<Packet> {
+		todo!()
+	}
+}
+
+impl Into<Packet> for PlayEntityRotation {
+	fn into(self) -> Packet {
+		Packet::PlayEntityRotation(self)
+	}
+}
+
+/// Changes the tab list. Every entry in one packet has to do the same thing.
+#[derive(Clone, Debug)]
+pub enum PlayPlayerInfo {
+	Add(Vec<PlayerInfoAdd>),
+	Remove(Vec<u128>)
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayerInfoAdd {
+	pub uuid: u128,
+	pub username: String,
+	pub gamemode: i32,
+	pub ping: i32
+}
+
+impl PacketLiterate for PlayPlayerInfo {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Client;
+	const PACKET_ID: u32 = 50;
+
+	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
+		match self {
+			Self::Add(players) => {
+				writer.variable_integer(0)?;
+				writer.variable_integer(players.len() as i32)?;
+				players.iter().try_for_each(|player| {
+					writer.uuid(player.uuid)?;
+					writer.string(&player.username)?;
+					writer.variable_integer(0)?; // No properties.
+					writer.variable_integer(player.gamemode)?;
+					writer.variable_integer(player.ping)?;
+					writer.bool(false) // No display name.
+				})
+			},
+			Self::Remove(players) => {
+				writer.variable_integer(4)?;
+				writer.variable_integer(players.len() as i32)?;
+				players.iter().try_for_each(|uuid| writer.uuid(*uuid))
+			}
+		}
+	}
+
+	fn deserialize(_len: usize, _reader: &mut impl Read) -> Result<Packet> {
+		todo!()
+	}
+}
+
+impl Into<Packet> for PlayPlayerInfo {
+	fn into(self) -> Packet {
+		Packet::PlayPlayerInfo(self)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayDestroyEntities(pub Vec<i32>);
+
+impl PacketLiterate for PlayDestroyEntities {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Client;
+	const PACKET_ID: u32 = 54;
+
+	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
+		writer.variable_integer(self.0.len() as i32)?;
+		self.0.iter().try_for_each(|entity_id| writer.variable_integer(*entity_id))
+	}
+
+	fn deserialize(_len: usize, _reader: &mut impl Read) -> Result<Packet> {
+		todo!()
+	}
+}
+
+impl Into<Packet> for PlayDestroyEntities {
+	fn into(self) -> Packet {
+		Packet::PlayDestroyEntities(self)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayEntityHeadLook {
+	pub entity_id: i32,
+	pub yaw: f32
+}
+
+impl PacketLiterate for PlayEntityHeadLook {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Client;
+	const PACKET_ID: u32 = 58;
+
+	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
+		writer.variable_integer(self.entity_id)?;
+		writer.angle(self.yaw)
+	}
+
+	fn deserialize(_len: usize, _reader: &mut impl Read) -> Result<Packet> {
+		todo!()
+	}
+}
+
+impl Into<Packet> for PlayEntityHeadLook {
+	fn into(self) -> Packet {
+		Packet::PlayEntityHeadLook(self)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayEntityTeleport {
+	pub entity_id: i32,
+	pub x: f64,
+	pub y: f64,
+	pub z: f64,
+	pub yaw: f32,
+	pub pitch: f32,
+	pub grounded: bool
+}
+
+impl PacketLiterate for PlayEntityTeleport {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Client;
+	const PACKET_ID: u32 = 86;
+
+	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
+		writer.variable_integer(self.entity_id)?;
+		writer.double(self.x)?;
+		writer.double(self.y)?;
+		writer.double(self.z)?;
+		writer.angle(self.yaw)?;
+		writer.angle(self.pitch)?;
+		writer.bool(self.grounded)
+	}
+
+	fn deserialize(_len: usize, _reader: &mut impl Read) -> Result<Packet> {
+		todo!()
+	}
+}
+
+impl Into<Packet> for PlayEntityTeleport {
+	fn into(self) -> Packet {
+		Packet::PlayEntityTeleport(self)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayEntityAction {
+	pub entity_id: i32,
+	pub action: EntityAction,
+	pub jump_boost: i32
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EntityAction {
+	SneakStart,
+	SneakStop,
+	LeaveBed,
+	SprintStart,
+	SprintStop,
+	HorseJumpStart,
+	HorseJumpStop,
+	HorseInventoryOpen,
+	ElytraStart
+}
+
+impl PacketLiterate for PlayEntityAction {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Server;
+	const PACKET_ID: u32 = 28;
+
+	fn serialize(&self, _writer: &mut impl Write) -> Result<()> {
+		todo!()
+	}
+
+	fn deserialize(_len: usize, reader: &mut impl Read) -> Result<Packet> {
+		let entity_id = reader.variable_integer()?.0;
+		let action = match reader.variable_integer()?.0 {
+			0 => EntityAction::SneakStart,
+			1 => EntityAction::SneakStop,
+			2 => EntityAction::LeaveBed,
+			3 => EntityAction::SprintStart,
+			4 => EntityAction::SprintStop,
+			5 => EntityAction::HorseJumpStart,
+			6 => EntityAction::HorseJumpStop,
+			7 => EntityAction::HorseInventoryOpen,
+			8 => EntityAction::ElytraStart,
+			action => return Err(Error::new(ErrorKind::InvalidData,
+				format!("Unknown entity action {}.", action)))
+		};
+
+		Ok(Self {entity_id, action, jump_boost: reader.variable_integer()?.0}.into())
+	}
+}
+
+impl Into<Packet> for PlayEntityAction {
+	fn into(self) -> Packet {
+		Packet::PlayEntityAction(self)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayEntityMetadata {
+	pub entity_id: i32,
+	pub metadata: Vec<(u8, MetadataValue)>
+}
+
+impl PacketLiterate for PlayEntityMetadata {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Client;
+	const PACKET_ID: u32 = 68;
+
+	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
+		writer.variable_integer(self.entity_id)?;
+		writer.metadata(&self.metadata)
+	}
+
+	fn deserialize(_len: usize, _reader: &mut impl Read) -> Result<Packet> {
+		todo!()
+	}
+}
+
+impl Into<Packet> for PlayEntityMetadata {
+	fn into(self) -> Packet {
+		Packet::PlayEntityMetadata(self)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayChatMessageServer {
+	/// A chat component, as JSON.
+	pub message: String,
+	/// 0 for chat, 1 for system messages, 2 for the action bar.
+	pub position: i8,
+	/// Who said it, or zero for the server.
+	pub sender: u128
+}
+
+impl PacketLiterate for PlayChatMessageServer {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Client;
+	const PACKET_ID: u32 = 14;
+
+	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
+		writer.string(&self.message)?;
+		writer.byte(self.position)?;
+		writer.uuid(self.sender)
+	}
+
+	fn deserialize(_len: usize, _reader: &mut impl Read) -> Result<Packet> {
+		todo!()
+	}
+}
+
+impl Into<Packet> for PlayChatMessageServer {
+	fn into(self) -> Packet {
+		Packet::PlayChatMessageServer(self)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayTabComplete {
+	pub transaction_id: i32,
+	/// Everything written so far, including the leading slash.
+	pub text: String
+}
+
+impl PacketLiterate for PlayTabComplete {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Server;
+	const PACKET_ID: u32 = 6;
+
+	fn serialize(&self, _writer: &mut impl Write) -> Result<()> {
+		todo!()
+	}
+
+	fn deserialize(_len: usize, reader: &mut impl Read) -> Result<Packet> {
+		Ok(Self {
+			transaction_id: reader.variable_integer()?.0,
+			text: reader.string()?.0
+		}.into())
+	}
+}
+
+impl Into<Packet> for PlayTabComplete {
+	fn into(self) -> Packet {
+		Packet::PlayTabComplete(self)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayTabCompleteServer {
+	pub transaction_id: i32,
+	/// Where in the text the suggestions replace, and how much they replace.
+	pub start: i32,
+	pub length: i32,
+	pub matches: Vec<String>
+}
+
+impl PacketLiterate for PlayTabCompleteServer {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Client;
+	const PACKET_ID: u32 = 15;
+
+	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
+		writer.variable_integer(self.transaction_id)?;
+		writer.variable_integer(self.start)?;
+		writer.variable_integer(self.length)?;
+		writer.variable_integer(self.matches.len() as i32)?;
+		self.matches.iter().try_for_each(|suggestion| {
+			writer.string(suggestion)?;
+			writer.bool(false) // No tooltip.
+		})
+	}
+
+	fn deserialize(_len: usize, _reader: &mut impl Read) -> Result<Packet> {
+		todo!()
+	}
+}
+
+impl Into<Packet> for PlayTabCompleteServer {
+	fn into(self) -> Packet {
+		Packet::PlayTabCompleteServer(self)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayServerDifficulty {
+	pub difficulty: u8,
+	/// Whether players can't change the difficulty from their settings.
+	pub locked: bool
+}
+
+impl PacketLiterate for PlayServerDifficulty {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Client;
+	const PACKET_ID: u32 = 13;
+
+	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
+		writer.unsigned_byte(self.difficulty)?;
+		writer.bool(self.locked)
+	}
+
+	fn deserialize(_len: usize, _reader: &mut impl Read) -> Result<Packet> {
+		todo!()
+	}
+}
+
+impl Into<Packet> for PlayServerDifficulty {
+	fn into(self) -> Packet {
+		Packet::PlayServerDifficulty(self)
+	}
+}
+
+/// Why the client was disconnected, as a chat component.
+#[derive(Clone, Debug)]
+pub struct PlayDisconnect(pub String);
+
+impl PacketLiterate for PlayDisconnect {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Client;
+	const PACKET_ID: u32 = 25;
+
+	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
+		writer.string(&self.0)
+	}
+
+	fn deserialize(_len: usize, _reader: &mut impl Read) -> Result<Packet> {
+		todo!()
+	}
+}
+
+impl Into<Packet> for PlayDisconnect {
+	fn into(self) -> Packet {
+		Packet::PlayDisconnect(self)
+	}
+}
+
+/// The command graph, with the root first.
+#[derive(Clone, Debug)]
+pub struct PlayDeclareCommands(pub Vec<GraphNode>);
+
+impl PacketLiterate for PlayDeclareCommands {
+	const PACKET_STATE: State = State::Play;
+	const PACKET_BOUND: Bound = Bound::Client;
+	const PACKET_ID: u32 = 16;
+
+	fn serialize(&self, writer: &mut impl Write) -> Result<()> {
+		writer.variable_integer(self.0.len() as i32)?;
+		self.0.iter().try_for_each(|node| {
+			let kind = match node.kind {
+				NodeKind::Root => 0,
+				NodeKind::Literal(_) => 1,
+				// Arguments always ask the server for suggestions.
+				NodeKind::Argument {..} => 2 | 0x10
+			};
+			writer.byte(kind | if node.executable {0x04} else {0})?;
+
+			writer.variable_integer(node.children.len() as i32)?;
+			node.children.iter()
+				.try_for_each(|child| writer.variable_integer(*child as i32))?;
+
+			match &node.kind {
+				NodeKind::Root => Ok(()),
+				NodeKind::Literal(name) => writer.string(name),
+				NodeKind::Argument {name, parser} => {
+					writer.string(name)?;
+					write_parser(writer, parser)?;
+					writer.string("minecraft:ask_server")
+				}
+			}
+		})?;
+
+		writer.variable_integer(0) // The root.
+	}
+
+	fn deserialize(_len: usize, _reader: &mut impl Read) -> Result<Packet> {
+		todo!()
+	}
+}
+
+/// Writes the identifier of a parser, and it's properties.
+fn write_parser(writer: &mut impl Write, parser: &Parser) -> Result<()> {
+	match parser {
+		Parser::Bool => writer.string("brigadier:bool"),
+		Parser::Integer {min, max} => {
+			writer.string("brigadier:integer")?;
+			writer.byte(if min.is_some() {0x01} else {0}
+				| if max.is_some() {0x02} else {0})?;
+			min.iter().try_for_each(|min| writer.int(*min))?;
+			max.iter().try_for_each(|max| writer.int(*max))
+		},
+		Parser::Double {min, max} => {
+			writer.string("brigadier:double")?;
+			writer.byte(if min.is_some() {0x01} else {0}
+				| if max.is_some() {0x02} else {0})?;
+			min.iter().try_for_each(|min| writer.double(*min))?;
+			max.iter().try_for_each(|max| writer.double(*max))
+		},
+		Parser::String(kind) => {
+			writer.string("brigadier:string")?;
+			writer.variable_integer(match kind {
+				StringKind::Word => 0,
+				StringKind::Quotable => 1,
+				StringKind::Greedy => 2
+			})
+		},
+		Parser::Entity {single, players} => {
+			writer.string("minecraft:entity")?;
+			writer.byte(if *single {0x01} else {0} | if *players {0x02} else {0})
+		},
+		Parser::BlockPos => writer.string("minecraft:block_pos"),
+		Parser::Message => writer.string("minecraft:message")
+	}
+}
+
+impl Into<Packet> for PlayDeclareCommands {
+	fn into(self) -> Packet {
+		Packet::PlayDeclareCommands(self)
+	}
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct Dimension {
 	#[serde(rename = "respawn_anchor_works")]
@@ -972,12 +1802,12 @@ impl Serialize for Biome {
 	}
 }
 
-use std::collections::HashMap;
-
+/// The registries sent when joining a game. Each entry's position in it's list
+/// is the identifier the client will know it by.
 #[derive(Clone, Debug)]
 pub struct DimensionCodec {
-	pub dimensions: HashMap<String, Dimension>,
-	pub biomes: HashMap<String, Biome>
+	pub dimensions: Vec<(String, Dimension)>,
+	pub biomes: Vec<(String, Biome)>
 }
 
 impl Serialize for DimensionCodec {
@@ -998,7 +1828,7 @@ impl Serialize for DimensionCodec {
 			}
 		}
 
-		struct Entries<'r, V>(&'r HashMap<String, V>)
+		struct Entries<'r, V>(&'r [(String, V)])
 			where V: Serialize;
 
 		impl<'r, V> Serialize for Entries<'r, V>
@@ -1012,7 +1842,7 @@ impl Serialize for DimensionCodec {
 			}
 		}
 
-		struct Category<'r, V>(&'r str, &'r HashMap<String, V>)
+		struct Category<'r, V>(&'r str, &'r [(String, V)])
 			where V: Serialize;
 
 		impl<'r, V> Serialize for Category<'r, V>
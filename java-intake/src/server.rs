@@ -4,81 +4,355 @@ use self::super::{
 		StatusResponse,
 		StatusPing,
 		StatusPong,
+		LoginCompression,
+		LoginDisconnect,
 		LoginStart,
 		LoginSuccess,
+		PlayChatMessage,
+		PlayChatMessageServer,
 		PlayChunkData,
+		PlayClientSettings,
+		PlayDeclareCommands,
+		PlayDestroyEntities,
+		PlayDisconnect,
+		PlayEntityAction,
+		PlayEntityHeadLook,
+		PlayEntityMetadata,
+		PlayEntityPosition,
+		PlayEntityRotation,
+		PlayEntityTeleport,
 		PlayJoinGame,
+		PlayPlayerAbilities,
+		PlayPlayerInfo,
+		PlayPlayerPositionClient,
+		PlayPlayerPositionRotationClient,
 		PlayPlayerPositionRotationServer,
+		PlayPlayerRotationClient,
+		PlayServerDifficulty,
+		PlaySpawnPlayer,
+		PlayTabComplete,
+		PlayTabCompleteServer,
+		PlayTeleportConfirm,
+		PlayUnloadChunk,
+		PlayUpdateViewDistance,
+		PlayUpdateViewPosition,
 
+		EntityAction,
 		HeightMap,
+		PlayerInfoAdd,
 		Dimension,
 		Biome,
 		DimensionCodec
 	},
-	socket::Socket
+	socket::Socket,
+	types::{MetadataValue, Pose}
 };
-use hermaphrodite::interface::MinecraftServer;
-use maplit::hashmap;
+use hermaphrodite::{
+	access::offline_uuid, chat::ChatPosition, chunk::Chunk, entity::Metadata,
+	generator::BIOMES,
+	interface::{Edition, MinecraftServer, Movement, PovUpdate, UpdateQueue},
+	position::EntityPos
+};
+use serde_json::{json, to_string};
 use std::{
 	io::{Error, ErrorKind, Result},
-	sync::{Arc, mpsc::{Receiver, TryRecvError, channel}},
+	sync::{Arc, mpsc::{Receiver, channel}},
 	time::Duration,
 	thread::{spawn as thread, sleep},
 	net::{TcpListener, ToSocketAddrs}
 };
 
-pub fn run_server<'s, S>(server: Arc<S>, address: impl ToSocketAddrs)
+/// How long to wait between checking for new connections.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Accepts connections until the server stops running, then returns once every
+/// client has been disconnected. Packets at least `compression` bytes long are
+/// compressed, if it's given.
+pub fn run_server<'s, S>(server: Arc<S>, address: impl ToSocketAddrs,
+		compression: Option<usize>)
 		where S: MinecraftServer<'s> + 'static {
 	let socket = TcpListener::bind(address).unwrap();
+	// Accepting can't block, so that the server stopping is noticed.
+	socket.set_nonblocking(true).unwrap();
 	let (sender, receiver) = channel();
 	let client_server = server.clone();
-	thread(move || run_clients(client_server, receiver));
+	let clients =
+		thread(move || run_clients(client_server, receiver, compression));
 
-	loop {
-		let client = socket.accept().unwrap().0;
-		sender.send(Socket::new(client)).unwrap()
+	while server.running() {
+		match socket.accept() {
+			Ok((client, _)) => sender.send(Socket::new(client)).unwrap(),
+			Err(error) if error.kind() == ErrorKind::WouldBlock =>
+				sleep(ACCEPT_INTERVAL),
+			Err(error) => eprintln!("Couldn't accept a connection: {}", error)
+		}
 	}
+
+	drop(socket);
+	clients.join().unwrap()
 }
 
-pub fn run_clients<'s, S>(server: Arc<S>, incoming: Receiver<Socket>)
+/// A connected socket, and the point of view it controls once logged in.
+pub struct Client {
+	pub socket: Socket,
+	pub username: Option<String>,
+	/// How long packets have to be to be compressed, once logged in.
+	pub compression: Option<usize>,
+	/// What the server has to tell the point of view.
+	updates: UpdateQueue
+}
+
+impl Client {
+	pub fn new(socket: Socket, compression: Option<usize>) -> Self {
+		let address = socket.address().map(|address| address.ip());
+		Self {
+			socket,
+			username: None,
+			compression,
+			updates: UpdateQueue::new(Edition::Java, address)
+		}
+	}
+}
+
+/// Handles every client's packets and updates, until the server stops running.
+pub fn run_clients<'s, S>(server: Arc<S>, incoming: Receiver<Socket>,
+		compression: Option<usize>)
 		where S: MinecraftServer<'s> + 'static {
-	let mut sockets = Vec::new();
+	let mut clients = Vec::new();
 
 	loop {
-		match incoming.try_recv() {
-			Err(TryRecvError::Disconnected) => panic!(),
-			Ok(socket) => sockets.push(socket),
-			_ => ()
-		}
+		// The server's last updates, such as disconnecting everyone, still
+		// have to be sent once it stops.
+		let stopped = !server.running();
+		clients.extend(incoming.try_iter()
+			.map(|socket| Client::new(socket, compression)));
+
+		let mut disconnected = Vec::new();
+		clients.iter_mut().enumerate().for_each(|(index, client)| {
+			let result: Result<()> = try {
+				client.socket.recv().map_err(|error| error.0)?.into_iter()
+					.try_for_each(|packet| process_packet(packet, client, &*server))?;
+				process_updates(client, &*server)?
+			};
 
-		sockets.iter_mut().try_for_each(|socket| {
-			socket.recv().map_err(|error| error.0)?.into_iter()
-				.try_for_each(|packet| process_packet(packet, socket, &*server))
-		}).unwrap();
+			if let Err(error) = result {
+				eprintln!("Disconnecting {:?}: {}", client.username, error);
+				disconnected.push(index)
+			}
+		});
 
+		disconnected.into_iter().rev().for_each(|index| {
+			if let Some(username) = clients.remove(index).username {
+				server.remove_pov(&username)
+			}
+		});
+
+		if stopped {break}
 		sleep(Duration::from_micros(1))
 	}
+
+	// Whoever's still connected is dropped along with their socket.
+	clients.into_iter().for_each(|client| {
+		if let Some(username) = client.username {server.remove_pov(&username)}
+	})
 }
 
-pub fn process_packet<'s, S>(packet: Packet, socket: &mut Socket, server: &S)
+/// Sends a client everything the server has to tell it's point of view.
+pub fn process_updates<'s, S>(client: &mut Client, server: &S) -> Result<()>
+		where S: MinecraftServer<'s> {
+	let mut kicked = None;
+	let packets = client.updates.take().into_iter()
+		.flat_map(|update| -> Vec<Packet> {match update {
+			PovUpdate::ChunkLoad(chunk) => vec![chunk_data(&chunk).into()],
+			PovUpdate::ChunkUnload(position) => vec![PlayUnloadChunk(position).into()],
+			PovUpdate::ViewCenter(position) =>
+				vec![PlayUpdateViewPosition(position).into()],
+			PovUpdate::ViewDistance(distance) =>
+				vec![PlayUpdateViewDistance(distance).into()],
+			PovUpdate::Teleport {position, rotation, teleport_id} => vec![
+				PlayPlayerPositionRotationServer {
+					x: position.x,
+					y: position.y,
+					z: position.z,
+					yaw: rotation.0,
+					pitch: rotation.1,
+					flags: 0,
+					teleport_id
+				}.into()
+			],
+			PovUpdate::PlayerJoin(username) => vec![
+				PlayPlayerInfo::Add(vec![PlayerInfoAdd {
+					uuid: offline_uuid(&username),
+					username: username.into(),
+					gamemode: server.gamemode().id() as i32,
+					ping: 0
+				}]).into()
+			],
+			PovUpdate::PlayerLeave(username) =>
+				vec![PlayPlayerInfo::Remove(vec![offline_uuid(&username)]).into()],
+			PovUpdate::EntitySpawn {entity_id, username, position, rotation} => vec![
+				PlaySpawnPlayer {
+					entity_id,
+					uuid: offline_uuid(&username),
+					x: position.x,
+					y: position.y,
+					z: position.z,
+					yaw: rotation.0,
+					pitch: rotation.1
+				}.into(),
+				PlayEntityHeadLook {entity_id, yaw: rotation.0}.into()
+			],
+			PovUpdate::EntityMove {entity_id, from, to, rotation, grounded} =>
+				vec![entity_move(entity_id, from, to, rotation, grounded)],
+			PovUpdate::EntityLook {entity_id, rotation, grounded} => vec![
+				PlayEntityRotation {
+					entity_id,
+					yaw: rotation.0,
+					pitch: rotation.1,
+					grounded
+				}.into(),
+				PlayEntityHeadLook {entity_id, yaw: rotation.0}.into()
+			],
+			PovUpdate::EntityMetadata {entity_id, metadata} => vec![
+				PlayEntityMetadata {entity_id, metadata: metadata_values(&metadata)}
+					.into()
+			],
+			PovUpdate::Chat {message, position, sender} => vec![
+				PlayChatMessageServer {
+					message: to_string(&json!({"text": &*message})).unwrap(),
+					position: match position {
+						ChatPosition::Chat => 0,
+						ChatPosition::System => 1,
+						ChatPosition::ActionBar => 2
+					},
+					sender: sender.map(|sender| offline_uuid(&sender)).unwrap_or(0)
+				}.into()
+			],
+			PovUpdate::Commands(graph) => vec![PlayDeclareCommands(graph).into()],
+			PovUpdate::EntityDestroy(entities) =>
+				vec![PlayDestroyEntities(entities).into()],
+			PovUpdate::Disconnect(reason) => {
+				let packet = PlayDisconnect(to_string(&json!({"text": &*reason}))
+					.unwrap());
+				kicked = Some(reason);
+				vec![packet.into()]
+			}
+		}})
+		.collect::<Vec<Packet>>();
+
+	if !packets.is_empty() {
+		client.socket.send(packets)?
+	}
+
+	match kicked {
+		Some(reason) => Err(Error::new(ErrorKind::ConnectionAborted,
+			format!("Kicked: {}", reason))),
+		None => Ok(())
+	}
+}
+
+pub fn process_packet<'s, S>(packet: Packet, client: &mut Client, server: &S)
 		-> Result<()> where S: MinecraftServer<'s> {
+	let socket = &mut client.socket;
+
 	match packet {
+		Packet::PlayClientSettings(PlayClientSettings {view_distance, ..}) => {
+			if let Some(username) = &client.username {
+				server.pov_view_distance(username, view_distance)
+			}
+			Ok(())
+		},
+		Packet::PlayTeleportConfirm(PlayTeleportConfirm(teleport_id)) => {
+			if let Some(username) = &client.username {
+				server.pov_teleport_confirm(username, teleport_id as i32)
+			}
+			Ok(())
+		},
+		Packet::PlayPlayerPositionClient(PlayPlayerPositionClient {
+				x, y_feet, z, grounded}) => {
+			if let Some(username) = &client.username {
+				server.pov_move(username, Movement {
+					position: Some(EntityPos::new(x, y_feet, z)),
+					rotation: None,
+					grounded
+				})
+			}
+			Ok(())
+		},
+		Packet::PlayPlayerPositionRotationClient(PlayPlayerPositionRotationClient {
+				x, y_feet, z, yaw, pitch, grounded}) => {
+			if let Some(username) = &client.username {
+				server.pov_move(username, Movement {
+					position: Some(EntityPos::new(x, y_feet, z)),
+					rotation: Some((yaw, pitch)),
+					grounded
+				})
+			}
+			Ok(())
+		},
+		Packet::PlayPlayerRotationClient(PlayPlayerRotationClient {
+				yaw, pitch, grounded}) => {
+			if let Some(username) = &client.username {
+				server.pov_move(username, Movement {
+					position: None,
+					rotation: Some((yaw, pitch)),
+					grounded
+				})
+			}
+			Ok(())
+		},
+		Packet::PlayPlayerAbilities(PlayPlayerAbilities(flags)) => {
+			if let Some(username) = &client.username {
+				server.pov_flying(username, flags & 0x02 != 0)
+			}
+			Ok(())
+		},
+		Packet::PlayEntityAction(PlayEntityAction {action, ..}) => {
+			if let Some(username) = &client.username {
+				match action {
+					EntityAction::SneakStart => server.pov_sneaking(username, true),
+					EntityAction::SneakStop => server.pov_sneaking(username, false),
+					EntityAction::SprintStart => server.pov_sprinting(username, true),
+					EntityAction::SprintStop => server.pov_sprinting(username, false),
+					_ => ()
+				}
+			}
+			Ok(())
+		},
+		Packet::PlayChatMessage(PlayChatMessage(message)) => {
+			if let Some(username) = &client.username {
+				server.pov_chat(username, &message)
+			}
+			Ok(())
+		},
+		Packet::PlayTabComplete(PlayTabComplete {transaction_id, text}) => {
+			let username = match &client.username {
+				Some(username) => username,
+				None => return Ok(())
+			};
+
+			// Commands are completed without their leading slash.
+			let command = text.strip_prefix('/').unwrap_or(&text);
+			let (start, matches) = server.pov_tab_complete(username, command);
+			let start = start + text.len() - command.len();
+			socket.send(vec![
+				PlayTabCompleteServer {
+					transaction_id,
+					start: start as i32,
+					length: (text.len() - start) as i32,
+					matches: matches.into_iter().map(Into::into).collect()
+				}.into()
+			])
+		},
 		Packet::Handshake(_)
-			| Packet::PlayClientSettings(_)
-			| Packet::PlayPluginMessageClient(_)
-			| Packet::PlayTeleportConfirm(_)
-			| Packet::PlayChatMessage(_)
-			| Packet::PlayPlayerPositionClient(_)
-			| Packet::PlayPlayerPositionRotationClient(_)
-			| Packet::PlayPlayerRotationClient(_)
-			| Packet::PlayPlayerAbilities(_) =>
+			| Packet::PlayPluginMessageClient(_) =>
 				Ok(()),
 		Packet::StatusRequest(_) => socket.send(vec![
 			StatusResponse {
 				protocol_name: "1.16.4".to_owned(),
 				protocol_version: 754,
-				players_online: 0,
-				players_max: -834904539isize as usize,
+				players_online: server.player_count(),
+				players_max: server.max_players(),
 				players_sample: vec![],
 				display_motd: server.message_of_the_day()
 			}.into()
@@ -87,50 +361,47 @@ pub fn process_packet<'s, S>(packet: Packet, socket: &mut Socket, server: &S)
 			StatusPong(nonce).into()
 		]),
 		Packet::LoginStart(LoginStart(username)) => {
-			server.new_pov(username.clone().into_boxed_str());
+			let joined = server.new_pov(username.clone().into_boxed_str(),
+				Box::new(client.updates.clone()));
+			if let Err(reason) = joined {
+				let reason_json = to_string(&json!({"text": &reason})).unwrap();
+				socket.send(vec![LoginDisconnect(reason_json).into()])?;
+				return Err(Error::new(ErrorKind::ConnectionAborted,
+					format!("Kicked: {}", reason)))
+			}
+			client.username = Some(username.clone());
 
 			let (dimension, dimension_codec) = dimension_and_codecs();
 
+			if let Some(threshold) = client.compression {
+				socket.send(vec![LoginCompression(threshold as u32).into()])?;
+				socket.set_compression(Some(threshold))
+			}
+
 			socket.send(vec![
 				LoginSuccess {
-					uuid: 200,
-					username: username
+					uuid: offline_uuid(&username),
+					username: username.clone()
 				}.into(),
 				PlayJoinGame {
-					entity_id: 0,
-					gamemode_current: 1,
+					entity_id: server.pov_entity_id(&username).unwrap_or(0) as u32,
+					gamemode_current: server.gamemode().id(),
 					gamemode_previous: 255,
 					gamemode_hardcore: false,
-					view_distance: 1,
+					view_distance: server.view_distance() as u32,
 					reduced_debug: false,
 					respawn_screen: true,
 					world_list: vec!["minecraft:overworld".to_owned()],
 					world_name: "minecraft:overworld".to_owned(),
 					seed_hashed: 0,
 					world_debug: false,
-					world_flat: true,
+					world_flat: server.world_flat(),
 					dimension,
 					dimension_codec
 				}.into(),
-				PlayPlayerPositionRotationServer {
-					x: 8.,
-					y: 1000.,
-					z: 8.,
-					yaw: 0.,
-					pitch: 0.,
-					flags: 0,
-					teleport_id: 0
-				}.into()
-			])?;
-
-
-			std::thread::sleep(std::time::Duration::from_secs(2));
-			socket.send(vec![
-				PlayChunkData {
-					position: (0, 0),
-					height_map: HeightMap {
-						height_map: vec![0; 36]
-					}
+				PlayServerDifficulty {
+					difficulty: server.difficulty().id(),
+					locked: true
 				}.into()
 			])
 		},
@@ -139,6 +410,84 @@ pub fn process_packet<'s, S>(packet: Packet, socket: &mut Socket, server: &S)
 	}
 }
 
+/// Moves an entity relatively if it's close enough to where it was, and
+/// teleports it otherwise.
+fn entity_move(entity_id: i32, from: EntityPos, to: EntityPos,
+		rotation: (f32, f32), grounded: bool) -> Packet {
+	// Relative moves are in 4096ths of a block, and must fit in a short.
+	let delta = |from: f64, to: f64| (to * 4096.).round() - (from * 4096.).round();
+	let (x, y, z) = (delta(from.x, to.x), delta(from.y, to.y), delta(from.z, to.z));
+	let range = i16::MIN as f64..=i16::MAX as f64;
+
+	match range.contains(&x) && range.contains(&y) && range.contains(&z) {
+		true => PlayEntityPosition {
+			entity_id,
+			delta_x: x as i16,
+			delta_y: y as i16,
+			delta_z: z as i16,
+			grounded
+		}.into(),
+		false => PlayEntityTeleport {
+			entity_id,
+			x: to.x,
+			y: to.y,
+			z: to.z,
+			yaw: rotation.0,
+			pitch: rotation.1,
+			grounded
+		}.into()
+	}
+}
+
+/// The metadata fields every entity has, set from `metadata`.
+fn metadata_values(metadata: &Metadata) -> Vec<(u8, MetadataValue)> {
+	let flags = [
+		(metadata.on_fire, 0x01),
+		(metadata.sneaking, 0x02),
+		(metadata.sprinting, 0x08),
+		(metadata.invisible, 0x20),
+		(metadata.glowing, 0x40)
+	].iter().filter(|(set, _)| *set).fold(0, |flags, (_, flag)| flags | flag);
+
+	let name = metadata.custom_name.as_ref()
+		.map(|name| to_string(&name[..]).unwrap());
+	let pose = match metadata.sneaking {
+		true => Pose::Sneaking,
+		false => Pose::Standing
+	};
+
+	vec![
+		(0, MetadataValue::Byte(flags)),
+		(3, MetadataValue::Boolean(name.is_some())), // Show the name.
+		(2, MetadataValue::OptionalChat(name)),
+		(6, MetadataValue::Pose(pose))
+	]
+}
+
+fn chunk_data(chunk: &Chunk) -> PlayChunkData {
+	let heights = chunk.heights().into_iter()
+		.map(|height| height as u64).collect::<Vec<_>>();
+
+	PlayChunkData {
+		position: chunk.position(),
+		height_map: HeightMap {
+			height_map: pack(&heights, 9)
+		},
+		biomes: chunk.biomes().values(),
+		sections: chunk.sections().to_vec()
+	}
+}
+
+/// Packs `values` into longs, `bits` at a time, without letting any value span
+/// across two longs.
+fn pack(values: &[u64], bits: usize) -> Vec<u64> {
+	let per_long = 64 / bits;
+	values.chunks(per_long)
+		.map(|values| values.iter().enumerate()
+			.fold(0, |long, (index, value)| long | value << index * bits))
+		.collect()
+}
+
 fn dimension_and_codecs() -> (Dimension, DimensionCodec) {
 	let this_dimension = Dimension {
 		work_anchor: false,
@@ -156,30 +505,48 @@ fn dimension_and_codecs() -> (Dimension, DimensionCodec) {
 		ultrawarm: false
 	};
 
-	let dimensions = hashmap! {
-		"minecraft:overworld".to_owned() => this_dimension.clone(),
-	};
+	let dimensions = vec![
+		("minecraft:overworld".to_owned(), this_dimension.clone())
+	];
 
-	let biomes = hashmap! {
-		"minecraft:plains".to_owned() => Biome {
-			precipitation: "rain".to_owned(),
-			depth: 0.125,
-			temperature: 0.8,
-			scale: 0.05,
-			downfall: 0.4,
-			category: "plains".to_owned(),
-
-			color_sky: 7907327,
-			color_water_fog: 329011,
-			color_fog: 12638463,
-			color_water: 4159204,
-
-			mood_tick_delay: 6000,
-			mood_offset: 2.,
-			mood_sound: "minecraft:ambient.cave".to_owned(),
-			mood_block_search_extent: 8
-		}
-	};
+	// The client identifies biomes by their position in the codec, so these
+	// have to be listed in the same order as the core's biome identifiers.
+	let biomes = BIOMES.iter()
+		.map(|name| (format!("minecraft:{}", name), biome(name)))
+		.collect();
 
 	(this_dimension, DimensionCodec {dimensions, biomes})
 }
+
+fn biome(name: &str) -> Biome {
+	// (precipitation, depth, scale, temperature, downfall, category, sky color)
+	let (precipitation, depth, scale, temperature, downfall, category, color_sky) =
+			match name {
+		"ocean" => ("rain", -1., 0.1, 0.5, 0.5, "ocean", 8103167),
+		"beach" => ("rain", 0., 0.025, 0.8, 0.4, "beach", 7907327),
+		"desert" => ("none", 0.125, 0.05, 2., 0., "desert", 7254527),
+		"forest" => ("rain", 0.1, 0.2, 0.7, 0.8, "forest", 7972607),
+		"mountains" => ("rain", 1., 0.5, 0.2, 0.3, "extreme_hills", 8233727),
+		"snowy_tundra" => ("snow", 0.125, 0.05, 0., 0.5, "icy", 8364543),
+		_ => ("rain", 0.125, 0.05, 0.8, 0.4, "plains", 7907327)
+	};
+
+	Biome {
+		precipitation: precipitation.to_owned(),
+		depth,
+		temperature,
+		scale,
+		downfall,
+		category: category.to_owned(),
+
+		color_sky,
+		color_water_fog: 329011,
+		color_fog: 12638463,
+		color_water: 4159204,
+
+		mood_tick_delay: 6000,
+		mood_offset: 2.,
+		mood_sound: "minecraft:ambient.cave".to_owned(),
+		mood_block_search_extent: 8
+	}
+}
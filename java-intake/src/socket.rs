@@ -1,14 +1,19 @@
 use self::super::{packet::Packet, types::{Bound, Read, State, Write}};
+use flate2::{Compression, read::ZlibDecoder, write::ZlibEncoder};
 use std::{
 	collections::VecDeque,
 	io::{Error, ErrorKind, Read as IORead, Result, Write as IOWrite, copy},
-	net::TcpStream, result::Result as STDResult
+	net::{SocketAddr, TcpStream}, result::Result as STDResult
 };
 
+/// The longest a packet may be once it's decompressed, as vanilla allows.
+const MAX_PACKET_LENGTH: usize = 2_097_152;
+
 pub struct Socket {
 	socket: TcpStream,
 	bound: Bound,
-	_compression: Option<i32>,
+	/// How long packets have to be to be compressed, once compression is on.
+	compression: Option<usize>,
 
 	state: State,
 	read_buffer: ReadBuffer
@@ -21,12 +26,17 @@ impl Socket {
 		Self {
 			socket,
 			bound: Bound::Server,
-			_compression: None,
+			compression: None,
 			state: State::Handshake,
 			read_buffer: ReadBuffer::new()
 		}
 	}
 
+	/// The address of the other end of the connection.
+	pub fn address(&self) -> Option<SocketAddr> {
+		self.socket.peer_addr().ok()
+	}
+
 	pub fn send(&mut self, packets: Vec<Packet>) -> Result<()> {
 		packets.iter().map::<Result<()>, _>(|packet| {
 			eprintln!("< {:?}", packet);
@@ -40,6 +50,10 @@ impl Socket {
 			bytes.variable_integer(packet.packet_id() as i32)?;
 			packet.serialize(&mut bytes)?;
 
+			if let Some(threshold) = self.compression {
+				bytes = compress(&bytes, threshold)?
+			}
+
 			header.variable_integer(bytes.len() as i32)?;
 			header.extend(bytes);
 			self.socket.write(&header)?;
@@ -51,7 +65,8 @@ impl Socket {
 	pub fn recv(&mut self)
 			-> STDResult<Vec<Packet>, (Error, Vec<Packet>)> {
 		match copy(&mut self.socket, &mut self.read_buffer) {
-			Ok(_) => return Ok(vec![]), // Socket closed...
+			Ok(_) => return Err((Error::new( // Socket closed...
+				ErrorKind::ConnectionAborted, "Socket closed."), vec![])),
 			Err(error) => match error.kind() {
 				ErrorKind::WouldBlock => {}, // What we want! Continue.
 				_ => return Err((error, vec![])) // An error occurred...
@@ -65,8 +80,15 @@ impl Socket {
 				if self.read_buffer.len() < size {Err(Error::new(
 					ErrorKind::UnexpectedEof, "Unexpected end of file."))?}
 
-				let (packet_id, read) = Read::variable_integer(&mut self.read_buffer)?;
-				let packet = Packet::deserialize(size - read, &mut self.read_buffer,
+				let mut frame = vec![0; size];
+				self.read_buffer.read_exact(&mut frame)?;
+				if let Some(threshold) = self.compression {
+					frame = decompress(&frame, threshold)?
+				}
+
+				let mut frame = &frame[..];
+				let (packet_id, _) = Read::variable_integer(&mut frame)?;
+				let packet = Packet::deserialize(frame.len(), &mut frame,
 					self.state, self.bound.receiving_bound(), packet_id as u32);
 
 				match packet.transpose()? {
@@ -98,6 +120,52 @@ impl Socket {
 	pub fn state(&self) -> State {
 		self.state
 	}
+
+	/// Compresses every packet sent and received from now on that's at least
+	/// `threshold` bytes long, or stops compressing with `None`. This has to
+	/// happen right after `LoginCompression` is sent.
+	pub fn set_compression(&mut self, threshold: Option<usize>) {
+		self.compression = threshold
+	}
+}
+
+/// Prefixes a packet with it's length, compressing it if it's long enough, or
+/// with zero if it's too short.
+fn compress(bytes: &[u8], threshold: usize) -> Result<Vec<u8>> {
+	let mut frame = Vec::new();
+	if bytes.len() < threshold {
+		frame.variable_integer(0)?;
+		frame.extend(bytes);
+		return Ok(frame)
+	}
+
+	frame.variable_integer(bytes.len() as i32)?;
+	let mut encoder = ZlibEncoder::new(frame, Compression::default());
+	encoder.write_all(bytes)?;
+	encoder.finish()
+}
+
+/// Undoes `compress`, failing if the packet didn't need compressing, or doesn't
+/// decompress to the length it claims to.
+fn decompress(mut frame: &[u8], threshold: usize) -> Result<Vec<u8>> {
+	let length = Read::variable_integer(&mut frame)?.0;
+	if length == 0 {return Ok(frame.to_vec())}
+
+	let bad_length = || Error::new(ErrorKind::InvalidData,
+		format!("Bad decompressed packet length {}.", length));
+	if length < 0 || (length as usize) < threshold
+			|| length as usize > MAX_PACKET_LENGTH {
+		return Err(bad_length())
+	}
+	let length = length as usize;
+
+	// Reading a byte more than claimed catches packets that are longer.
+	let mut bytes = Vec::with_capacity(length);
+	ZlibDecoder::new(frame).take(length as u64 + 1).read_to_end(&mut bytes)?;
+	match bytes.len() == length {
+		true => Ok(bytes),
+		false => Err(bad_length())
+	}
 }
 
 /// A readable buffer that retains any data read from it until it is "marked as
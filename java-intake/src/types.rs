@@ -1,4 +1,5 @@
 use self::super::nbt::Serializer as NBTSerializer;
+use hermaphrodite::{block::Facing, position::BlockPos};
 use serde::{de::Deserialize, ser::Serialize};
 use std::{
 	io::{Error, ErrorKind, Read as IORead, Result, Write as IOWrite},
@@ -158,6 +159,14 @@ pub trait Write {
 	fn nbt<T>(&mut self, value: T, name: &str) -> Result<()>
 		where T: Serialize;
 	fn string(&mut self, value: &str) -> Result<()>;
+	fn bytes(&mut self, value: &[u8]) -> Result<()>;
+	/// Writes a rotation in degrees as 256ths of a full turn.
+	fn angle(&mut self, value: f32) -> Result<()>;
+	fn position(&mut self, value: BlockPos) -> Result<()>;
+	fn slot(&mut self, value: Option<&Slot>) -> Result<()>;
+	/// Writes entity metadata entries, each with the index of the field they
+	/// set, followed by the end marker.
+	fn metadata(&mut self, value: &[(u8, MetadataValue)]) -> Result<()>;
 }
 
 impl<W> Write for W
@@ -195,6 +204,204 @@ impl<W> Write for W
 		self.write(value.as_bytes())?;
 		Ok(())
 	}
+
+	fn bytes(&mut self, value: &[u8]) -> Result<()> {
+		self.write_all(value)
+	}
+
+	fn angle(&mut self, value: f32) -> Result<()> {
+		self.unsigned_byte((value.rem_euclid(360.) / 360. * 256.) as u8)
+	}
+
+	fn position(&mut self, value: BlockPos) -> Result<()> {
+		self.long((value.x as i64 & 0x3FFFFFF) << 38
+			| (value.z as i64 & 0x3FFFFFF) << 12 | value.y as i64 & 0xFFF)
+	}
+
+	fn slot(&mut self, value: Option<&Slot>) -> Result<()> {
+		let slot = match value {
+			Some(slot) => slot,
+			None => return self.bool(false)
+		};
+
+		self.bool(true)?;
+		self.variable_integer(slot.item)?;
+		self.byte(slot.count)?;
+		match &slot.nbt {
+			Some(nbt) => self.bytes(nbt),
+			None => self.byte(0) // An end tag, for no NBT at all.
+		}
+	}
+
+	fn metadata(&mut self, value: &[(u8, MetadataValue)]) -> Result<()> {
+		value.iter().try_for_each(|(index, value)| {
+			self.unsigned_byte(*index)?;
+			self.variable_integer(value.type_id())?;
+
+			match value {
+				MetadataValue::Byte(value) => self.byte(*value),
+				MetadataValue::VarInt(value) => self.variable_integer(*value),
+				MetadataValue::Float(value) => self.float(*value),
+				MetadataValue::String(value) | MetadataValue::Chat(value) =>
+					self.string(value),
+				MetadataValue::OptionalChat(value) => {
+					self.bool(value.is_some())?;
+					value.iter().try_for_each(|value| self.string(value))
+				},
+				MetadataValue::Slot(value) => self.slot(value.as_ref()),
+				MetadataValue::Boolean(value) => self.bool(*value),
+				MetadataValue::Rotation(x, y, z) => {
+					self.float(*x)?;
+					self.float(*y)?;
+					self.float(*z)
+				},
+				MetadataValue::Position(value) => self.position(*value),
+				MetadataValue::OptionalPosition(value) => {
+					self.bool(value.is_some())?;
+					value.iter().try_for_each(|value| self.position(*value))
+				},
+				MetadataValue::Direction(value) => self.variable_integer(match value {
+					Facing::Down => 0,
+					Facing::Up => 1,
+					Facing::North => 2,
+					Facing::South => 3,
+					Facing::West => 4,
+					Facing::East => 5
+				}),
+				MetadataValue::OptionalUuid(value) => {
+					self.bool(value.is_some())?;
+					value.iter().try_for_each(|value| self.uuid(*value))
+				},
+				// Air stands in for no block at all.
+				MetadataValue::OptionalBlock(value) =>
+					self.variable_integer(value.unwrap_or(0) as i32),
+				MetadataValue::Nbt(value) => self.bytes(value),
+				MetadataValue::Particle(Particle {id, data}) => {
+					self.variable_integer(*id)?;
+					match data {
+						ParticleData::None => Ok(()),
+						ParticleData::Block(state) => self.variable_integer(*state as i32),
+						ParticleData::Dust {red, green, blue, scale} => {
+							self.float(*red)?;
+							self.float(*green)?;
+							self.float(*blue)?;
+							self.float(*scale)
+						},
+						ParticleData::Item(slot) => self.slot(slot.as_ref())
+					}
+				},
+				MetadataValue::VillagerData {kind, profession, level} => {
+					self.variable_integer(*kind)?;
+					self.variable_integer(*profession)?;
+					self.variable_integer(*level)
+				},
+				// Zero stands in for nothing, so everything else is shifted by one.
+				MetadataValue::OptionalVarInt(value) =>
+					self.variable_integer(value.map(|value| value + 1).unwrap_or(0)),
+				MetadataValue::Pose(value) => self.variable_integer(*value as i32)
+			}
+		})?;
+
+		self.unsigned_byte(0xFF)
+	}
+}
+
+/// A stack of items.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slot {
+	pub item: i32,
+	pub count: i8,
+	/// An NBT compound, already written with `Write::nbt`.
+	pub nbt: Option<Vec<u8>>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Particle {
+	pub id: i32,
+	pub data: ParticleData
+}
+
+/// The extra data some particles need.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParticleData {
+	None,
+	/// The block state of `block` and `falling_dust` particles.
+	Block(u32),
+	Dust {
+		red: f32,
+		green: f32,
+		blue: f32,
+		scale: f32
+	},
+	Item(Option<Slot>)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Pose {
+	Standing,
+	FallFlying,
+	Sleeping,
+	Swimming,
+	SpinAttack,
+	Sneaking,
+	Dying
+}
+
+/// The value of one field of an entity's metadata. Which fields an entity has,
+/// and what their indices are, depends on what kind of entity it is.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataValue {
+	Byte(i8),
+	VarInt(i32),
+	Float(f32),
+	String(String),
+	/// A chat component, as JSON.
+	Chat(String),
+	OptionalChat(Option<String>),
+	Slot(Option<Slot>),
+	Boolean(bool),
+	Rotation(f32, f32, f32),
+	Position(BlockPos),
+	OptionalPosition(Option<BlockPos>),
+	Direction(Facing),
+	OptionalUuid(Option<u128>),
+	OptionalBlock(Option<u32>),
+	/// An NBT compound, already written with `Write::nbt`.
+	Nbt(Vec<u8>),
+	Particle(Particle),
+	VillagerData {
+		kind: i32,
+		profession: i32,
+		level: i32
+	},
+	OptionalVarInt(Option<i32>),
+	Pose(Pose)
+}
+
+impl MetadataValue {
+	pub fn type_id(&self) -> i32 {
+		match self {
+			Self::Byte(_) => 0,
+			Self::VarInt(_) => 1,
+			Self::Float(_) => 2,
+			Self::String(_) => 3,
+			Self::Chat(_) => 4,
+			Self::OptionalChat(_) => 5,
+			Self::Slot(_) => 6,
+			Self::Boolean(_) => 7,
+			Self::Rotation(..) => 8,
+			Self::Position(_) => 9,
+			Self::OptionalPosition(_) => 10,
+			Self::Direction(_) => 11,
+			Self::OptionalUuid(_) => 12,
+			Self::OptionalBlock(_) => 13,
+			Self::Nbt(_) => 14,
+			Self::Particle(_) => 15,
+			Self::VillagerData {..} => 16,
+			Self::OptionalVarInt(_) => 17,
+			Self::Pose(_) => 18
+		}
+	}
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
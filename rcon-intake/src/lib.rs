@@ -0,0 +1,7 @@
+pub mod packet;
+pub mod server;
+
+pub use self::{
+	packet::Packet,
+	server::{run_listener, run_server}
+};
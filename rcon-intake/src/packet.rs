@@ -0,0 +1,91 @@
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+/// Sent by clients to log in with the password.
+pub const AUTH: i32 = 3;
+/// Sent by the server to tell clients whether they logged in. It shares it's
+/// type with `EXECUTE_COMMAND`, as they're sent in opposite directions.
+pub const AUTH_RESPONSE: i32 = 2;
+/// Sent by clients to run a command.
+pub const EXECUTE_COMMAND: i32 = 2;
+/// Sent by the server with what a command replied. Clients may send an empty
+/// one after a command too, which is sent back once every response to the
+/// command has been, so they know where responses split across packets end.
+pub const RESPONSE_VALUE: i32 = 0;
+
+/// The request ID the server answers logging in with a wrong password with.
+pub const AUTH_FAILED: i32 = -1;
+
+/// How long the body of a packet may be, in bytes. Longer responses are split
+/// across packets.
+pub const MAX_BODY: usize = 4096;
+
+/// The ID, type and both null terminators, which every packet has after it's
+/// length.
+const HEADER_LENGTH: usize = 10;
+
+/// A packet of the Source RCON protocol, which looks the same in both
+/// directions. Every integer is in little endian.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Packet {
+	/// Chosen by the client, and echoed back in responses.
+	pub id: i32,
+	pub kind: i32,
+	pub body: String
+}
+
+impl Packet {
+	pub fn new(id: i32, kind: i32, body: impl Into<String>) -> Self {
+		Self {id, kind, body: body.into()}
+	}
+
+	/// Reads a packet, failing if it's longer than packets may be.
+	pub fn read(reader: &mut impl Read) -> Result<Self> {
+		let length = read_i32(reader)?;
+		let body_length = (length as usize).checked_sub(HEADER_LENGTH)
+			.filter(|length| *length <= MAX_BODY)
+			.ok_or_else(|| Error::new(ErrorKind::InvalidData,
+				format!("A packet can't be {} bytes long.", length)))?;
+
+		let id = read_i32(reader)?;
+		let kind = read_i32(reader)?;
+		let mut body = vec![0; body_length + 2];
+		reader.read_exact(&mut body)?;
+		body.truncate(body_length);
+
+		// Some clients include the null terminator in the body too.
+		let body = String::from_utf8_lossy(&body).trim_end_matches('\0').to_owned();
+		Ok(Self {id, kind, body})
+	}
+
+	pub fn write(&self, writer: &mut impl Write) -> Result<()> {
+		let length = (HEADER_LENGTH + self.body.len()) as i32;
+		let mut bytes = Vec::with_capacity(4 + length as usize);
+		bytes.extend_from_slice(&length.to_le_bytes());
+		bytes.extend_from_slice(&self.id.to_le_bytes());
+		bytes.extend_from_slice(&self.kind.to_le_bytes());
+		bytes.extend_from_slice(self.body.as_bytes());
+		bytes.extend_from_slice(&[0, 0]);
+		writer.write_all(&bytes)
+	}
+
+	/// The responses to the request `id`, split so no body is too long.
+	pub fn responses(id: i32, response: &str) -> Vec<Self> {
+		let mut packets = Vec::new();
+		let mut rest = response;
+		loop {
+			// Characters aren't split between packets.
+			let mut end = rest.len().min(MAX_BODY);
+			while !rest.is_char_boundary(end) {end -= 1}
+
+			packets.push(Self::new(id, RESPONSE_VALUE, &rest[..end]));
+			rest = &rest[end..];
+			if rest.is_empty() {return packets}
+		}
+	}
+}
+
+fn read_i32(reader: &mut impl Read) -> Result<i32> {
+	let mut bytes = [0; 4];
+	reader.read_exact(&mut bytes)?;
+	Ok(i32::from_le_bytes(bytes))
+}
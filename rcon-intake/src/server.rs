@@ -0,0 +1,203 @@
+use self::super::packet::{
+	AUTH, AUTH_FAILED, AUTH_RESPONSE, EXECUTE_COMMAND, Packet, RESPONSE_VALUE
+};
+use hermaphrodite::interface::MinecraftServer;
+use std::{
+	io::{Error, ErrorKind, Result},
+	net::{TcpListener, TcpStream, ToSocketAddrs},
+	sync::Arc,
+	thread::{sleep, spawn as thread},
+	time::Duration
+};
+
+/// How long to wait between checking for new connections.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long to wait for clients to send something before checking whether the
+/// server stopped.
+const IDLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long clients have to finish sending a packet once they've started.
+const PACKET_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Accepts remote console connections until the server stops running. Clients
+/// have to log in with `password` before they may run commands, and notice the
+/// server stopping on their own.
+pub fn run_server<'s, S>(server: Arc<S>, address: impl ToSocketAddrs,
+		password: String)
+		where S: MinecraftServer<'s> + 'static {
+	run_listener(server, TcpListener::bind(address).unwrap(), password)
+}
+
+/// Accepts remote console connections on a socket that's already listening,
+/// like `run_server`.
+pub fn run_listener<'s, S>(server: Arc<S>, socket: TcpListener, password: String)
+		where S: MinecraftServer<'s> + 'static {
+	// Accepting can't block, so that the server stopping is noticed.
+	socket.set_nonblocking(true).unwrap();
+	let password: Arc<str> = password.into();
+	let mut next_id = 0u32;
+
+	while server.running() {
+		match socket.accept() {
+			Ok((client, address)) => {
+				let (server, password) = (server.clone(), password.clone());
+				let id = next_id;
+				next_id = next_id.wrapping_add(1);
+				thread(move || {
+					if let Err(error) = run_client(&*server, client, id, &password) {
+						eprintln!("Remote console {} disconnected: {}", address, error)
+					}
+				});
+			},
+			Err(error) if error.kind() == ErrorKind::WouldBlock =>
+				sleep(ACCEPT_INTERVAL),
+			Err(error) => eprintln!("Couldn't accept a connection: {}", error)
+		}
+	}
+}
+
+/// Answers a client's packets, running the commands it sends as the remote
+/// console `id`, until it disconnects or the server stops running.
+fn run_client<'s, S>(server: &S, mut client: TcpStream, id: u32, password: &str)
+		-> Result<()>
+		where S: MinecraftServer<'s> {
+	client.set_nonblocking(false)?;
+	let mut authenticated = false;
+
+	while server.running() {
+		// Wait for the next packet, without missing the server stopping.
+		client.set_read_timeout(Some(IDLE_INTERVAL))?;
+		match client.peek(&mut [0]) {
+			Ok(0) => return Ok(()),
+			Ok(_) => (),
+			Err(error) if error.kind() == ErrorKind::WouldBlock
+				|| error.kind() == ErrorKind::TimedOut => continue,
+			Err(error) => return Err(error)
+		}
+		client.set_read_timeout(Some(PACKET_TIMEOUT))?;
+		let request = Packet::read(&mut client)?;
+
+		let responses = match (request.kind, authenticated) {
+			(AUTH, _) => {
+				authenticated = !password.is_empty() && request.body == password;
+				match authenticated {
+					true => vec![Packet::new(request.id, AUTH_RESPONSE, "")],
+					false => {
+						Packet::new(AUTH_FAILED, AUTH_RESPONSE, "").write(&mut client)?;
+						return Err(Error::new(ErrorKind::PermissionDenied,
+							"Wrong password."))
+					}
+				}
+			},
+			(_, false) => return Err(Error::new(ErrorKind::PermissionDenied,
+				"Sent a request before logging in.")),
+			(EXECUTE_COMMAND, true) => {
+				// Commands may be written with the slash players use too.
+				let command = request.body.trim();
+				let command = command.strip_prefix('/').unwrap_or(command);
+				Packet::responses(request.id,
+					&server.command_execute_remote(id, command))
+			},
+			(RESPONSE_VALUE, true) =>
+				vec![Packet::new(request.id, RESPONSE_VALUE, "")],
+			(kind, true) => return Err(Error::new(ErrorKind::InvalidData,
+				format!("Unknown packet type {}.", kind)))
+		};
+		responses.iter().try_for_each(|response| response.write(&mut client))?;
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use self::super::{*, super::packet::MAX_BODY};
+	use hermaphrodite::{access::AccessLists, server::Server};
+	use std::{
+		env::temp_dir, fs::create_dir_all, io::Read, net::SocketAddr, process
+	};
+
+	const PASSWORD: &str = "hunter2";
+
+	/// Starts a remote console for a new server, returning where it listens.
+	fn start(test: &str) -> SocketAddr {
+		// Bans are saved, so every test keeps them in a directory of it's own.
+		let directory =
+			temp_dir().join(format!("rcon-intake-{}-{}", test, process::id()));
+		create_dir_all(&directory).unwrap();
+		let server =
+			Arc::new(Server::new().with_access(AccessLists::load(directory).unwrap()));
+
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let address = listener.local_addr().unwrap();
+		thread(move || run_listener(server, listener, PASSWORD.to_owned()));
+		address
+	}
+
+	/// Connects to a remote console, and logs in.
+	fn log_in(address: SocketAddr) -> TcpStream {
+		let mut client = TcpStream::connect(address).unwrap();
+		client.set_read_timeout(Some(PACKET_TIMEOUT)).unwrap();
+		Packet::new(1, AUTH, PASSWORD).write(&mut client).unwrap();
+		assert_eq!(Packet::read(&mut client).unwrap(),
+			Packet::new(1, AUTH_RESPONSE, ""));
+		client
+	}
+
+	fn execute(client: &mut TcpStream, id: i32, command: &str) -> Packet {
+		Packet::new(id, EXECUTE_COMMAND, command).write(client).unwrap();
+		Packet::read(client).unwrap()
+	}
+
+	#[test]
+	fn wrong_password() {
+		let mut client = TcpStream::connect(start("wrong-password")).unwrap();
+		client.set_read_timeout(Some(PACKET_TIMEOUT)).unwrap();
+		Packet::new(1, AUTH, "hunter3").write(&mut client).unwrap();
+
+		assert_eq!(Packet::read(&mut client).unwrap(),
+			Packet::new(AUTH_FAILED, AUTH_RESPONSE, ""));
+		// The client is disconnected afterwards.
+		assert_eq!(client.read(&mut [0]).unwrap(), 0);
+	}
+
+	#[test]
+	fn command() {
+		let mut client = log_in(start("command"));
+		assert_eq!(execute(&mut client, 2, "list"),
+			Packet::new(2, RESPONSE_VALUE, "There are 0 players online: "));
+		assert_eq!(execute(&mut client, 3, "/ban Notch"),
+			Packet::new(3, RESPONSE_VALUE, "Banned Notch: Banned by an operator."));
+	}
+
+	#[test]
+	fn long_response() {
+		let mut client = log_in(start("long-response"));
+		let names = (0..200).map(|index| format!("Player{}", index)).collect::<Vec<_>>();
+		names.iter().enumerate().for_each(|(index, name)|
+			{execute(&mut client, index as i32, &format!("ban {}", name));});
+
+		// Clients find out where a response ends by sending an empty packet
+		// after the command, which is answered once the response is.
+		Packet::new(1000, EXECUTE_COMMAND, "banlist").write(&mut client).unwrap();
+		Packet::new(1001, RESPONSE_VALUE, "").write(&mut client).unwrap();
+		let mut responses = Vec::new();
+		loop {
+			let response = Packet::read(&mut client).unwrap();
+			if response.id == 1001 {
+				assert_eq!(response, Packet::new(1001, RESPONSE_VALUE, ""));
+				break
+			}
+			responses.push(response);
+		}
+
+		assert!(responses.len() > 1);
+		assert!(responses.iter().all(|response| response.id == 1000
+			&& response.kind == RESPONSE_VALUE && response.body.len() <= MAX_BODY));
+		let banlist = responses.iter().map(|response| response.body.as_str())
+			.collect::<String>();
+		assert!(banlist.len() > MAX_BODY);
+		assert!(names.iter().all(|name| banlist.contains(&format!("{} was", name))));
+	}
+}